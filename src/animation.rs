@@ -0,0 +1,94 @@
+use std::{f64::consts::PI, fs, io, path::Path};
+
+use crate::{camera::Camera, canvas::Canvas, transformation::rotation_y, world::World};
+
+// Renders `frames` images of the world while orbiting the camera around the
+// world's y axis, one full turn over the whole sequence
+pub fn turntable_frames(world: &World, camera: &Camera, frames: usize) -> Vec<Canvas> {
+    let mut frame_camera = camera.clone();
+
+    (0..frames)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f64 / frames as f64;
+            frame_camera.transform = camera.transform.clone() * rotation_y(angle);
+
+            frame_camera.render(world)
+        })
+        .collect()
+}
+
+// Renders a turntable animation and writes each frame into output_dir as
+// frame_000.ppm, frame_001.ppm, ...
+pub fn render_turntable(
+    world: &World,
+    camera: &Camera,
+    frames: usize,
+    output_dir: &str,
+) -> io::Result<()> {
+    fs::create_dir_all(output_dir)?;
+
+    for (i, frame) in turntable_frames(world, camera, frames).iter().enumerate() {
+        let path = Path::new(output_dir).join(format!("frame_{:03}.ppm", i));
+        frame.to_ppm(&path.to_string_lossy())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        color::Color,
+        transformation::view_transform,
+        tuples::{Point, Vector},
+    };
+
+    use super::*;
+
+    fn small_camera() -> Camera {
+        let mut c = Camera::new(5, 5, PI / 2.0);
+        c.transform = view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::origin(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        c
+    }
+
+    fn is_black(canvas: &Canvas) -> bool {
+        (0..canvas.height)
+            .all(|y| (0..canvas.width).all(|x| canvas.pixel_at(x, y) == Color::black()))
+    }
+
+    #[test]
+    fn turntable_frames_rotate_around_world() {
+        let w = World::default();
+        let c = small_camera();
+
+        let frames = turntable_frames(&w, &c, 2);
+
+        assert_eq!(frames.len(), 2);
+        assert!(!is_black(&frames[0]));
+        assert!(!is_black(&frames[1]));
+        // The second frame looks at the sphere from the side facing away from the light
+        assert_ne!(frames[0].pixel_at(2, 2), frames[1].pixel_at(2, 2));
+    }
+
+    #[test]
+    fn render_turntable_writes_numbered_frames() {
+        let w = World::default();
+        let c = small_camera();
+        // Unique to this process, so concurrent test runs don't share frames
+        let dir =
+            std::env::temp_dir().join(format!("ray_tracer_turntable_test_{}", std::process::id()));
+        let output_dir = dir.to_string_lossy();
+
+        render_turntable(&w, &c, 2, &output_dir).unwrap();
+
+        assert!(dir.join("frame_000.ppm").exists());
+        assert!(dir.join("frame_001.ppm").exists());
+        assert!(!dir.join("frame_002.ppm").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
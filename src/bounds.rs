@@ -0,0 +1,201 @@
+use crate::{
+    constants::EPSILON,
+    matrices::Matrix,
+    rays::Ray,
+    tuples::{Point, Vector},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl BoundingBox {
+    pub fn new(min: Point, max: Point) -> Self {
+        BoundingBox { min, max }
+    }
+
+    // A box containing nothing, which grows as points are added to it
+    pub fn empty() -> Self {
+        BoundingBox {
+            min: Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    pub fn infinite() -> Self {
+        BoundingBox {
+            min: Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            max: Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.0 > self.max.0 || self.min.1 > self.max.1 || self.min.2 > self.max.2
+    }
+
+    pub fn is_finite(&self) -> bool {
+        [
+            self.min.0, self.min.1, self.min.2, self.max.0, self.max.1, self.max.2,
+        ]
+        .iter()
+        .all(|c| c.is_finite())
+    }
+
+    pub fn add_point(&mut self, point: Point) {
+        self.min = Point::min(self.min, point);
+        self.max = Point::max(self.max, point);
+    }
+
+    pub fn add_box(&mut self, other: &BoundingBox) {
+        if other.is_empty() {
+            return;
+        }
+
+        self.add_point(other.min);
+        self.add_point(other.max);
+    }
+
+    pub fn contains_point(&self, point: Point) -> bool {
+        (self.min.0..=self.max.0).contains(&point.0)
+            && (self.min.1..=self.max.1).contains(&point.1)
+            && (self.min.2..=self.max.2).contains(&point.2)
+    }
+
+    pub fn extent(&self) -> Vector {
+        self.max - self.min
+    }
+
+    // Transforms all eight corners of the box and returns the axis-aligned
+    // box that contains them. Infinite boxes stay infinite since transforming
+    // infinite coordinates produces NaNs.
+    pub fn transform(&self, m: &Matrix) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+        if !self.is_finite() {
+            return BoundingBox::infinite();
+        }
+
+        let corners = [
+            Point::new(self.min.0, self.min.1, self.min.2),
+            Point::new(self.min.0, self.min.1, self.max.2),
+            Point::new(self.min.0, self.max.1, self.min.2),
+            Point::new(self.min.0, self.max.1, self.max.2),
+            Point::new(self.max.0, self.min.1, self.min.2),
+            Point::new(self.max.0, self.min.1, self.max.2),
+            Point::new(self.max.0, self.max.1, self.min.2),
+            Point::new(self.max.0, self.max.1, self.max.2),
+        ];
+
+        let mut transformed = BoundingBox::empty();
+        for corner in corners {
+            transformed.add_point(m.clone() * corner);
+        }
+
+        transformed
+    }
+
+    // Returns the range of t over which the ray is inside the box, if any
+    pub fn intersect(&self, ray: Ray) -> Option<(f64, f64)> {
+        let (xtmin, xtmax) = check_axis(self.min.0, self.max.0, ray.origin.0, ray.direction.0);
+        let (ytmin, ytmax) = check_axis(self.min.1, self.max.1, ray.origin.1, ray.direction.1);
+        let (ztmin, ztmax) = check_axis(self.min.2, self.max.2, ray.origin.2, ray.direction.2);
+
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+
+        if tmin > tmax {
+            return None;
+        }
+
+        Some((tmin, tmax))
+    }
+}
+
+fn check_axis(min: f64, max: f64, origin: f64, direction: f64) -> (f64, f64) {
+    let tmin_numerator = min - origin;
+    let tmax_numerator = max - origin;
+
+    let (tmin, tmax) = if direction.abs() >= EPSILON {
+        (tmin_numerator / direction, tmax_numerator / direction)
+    } else {
+        (
+            tmin_numerator * f64::INFINITY,
+            tmax_numerator * f64::INFINITY,
+        )
+    };
+
+    if tmin > tmax {
+        (tmax, tmin)
+    } else {
+        (tmin, tmax)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transformation::{rotation_y, translation};
+
+    use super::*;
+
+    #[test]
+    fn adding_points_to_empty_box() {
+        let mut b = BoundingBox::empty();
+        assert!(b.is_empty());
+
+        b.add_point(Point::new(-5.0, 2.0, 0.0));
+        b.add_point(Point::new(7.0, 0.0, -3.0));
+
+        assert_eq!(b.min, Point::new(-5.0, 0.0, -3.0));
+        assert_eq!(b.max, Point::new(7.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn adding_box_to_another() {
+        let mut b1 = BoundingBox::new(Point::new(-5.0, -2.0, 0.0), Point::new(7.0, 4.0, 4.0));
+        let b2 = BoundingBox::new(Point::new(8.0, -7.0, -2.0), Point::new(14.0, 2.0, 8.0));
+
+        b1.add_box(&b2);
+
+        assert_eq!(b1.min, Point::new(-5.0, -7.0, -2.0));
+        assert_eq!(b1.max, Point::new(14.0, 4.0, 8.0));
+    }
+
+    #[test]
+    fn transforming_bounding_box() {
+        let b = BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+
+        let t = b.transform(&(translation(1.0, 0.0, 0.0) * rotation_y(std::f64::consts::PI / 4.0)));
+
+        assert_eq!(
+            t.min,
+            Point::new(1.0 - 2.0_f64.sqrt(), -1.0, -(2.0_f64.sqrt()))
+        );
+        assert_eq!(t.max, Point::new(1.0 + 2.0_f64.sqrt(), 1.0, 2.0_f64.sqrt()));
+    }
+
+    #[test]
+    fn transforming_infinite_box_stays_infinite() {
+        let b = BoundingBox::new(
+            Point::new(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+            Point::new(f64::INFINITY, 0.0, f64::INFINITY),
+        );
+
+        let t = b.transform(&translation(0.0, 1.0, 0.0));
+
+        assert!(!t.is_finite());
+    }
+
+    #[test]
+    fn intersecting_ray_with_bounding_box() {
+        let b = BoundingBox::new(Point::new(5.0, -2.0, 0.0), Point::new(11.0, 4.0, 7.0));
+
+        let r = Ray::new(Point::new(15.0, 1.0, 2.0), Vector::new(-1.0, 0.0, 0.0));
+        assert_eq!(b.intersect(r), Some((4.0, 10.0)));
+
+        let r = Ray::new(Point::new(15.0, 20.0, 2.0), Vector::new(-1.0, 0.0, 0.0));
+        assert_eq!(b.intersect(r), None);
+    }
+}
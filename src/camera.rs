@@ -1,8 +1,111 @@
+use std::{
+    collections::HashMap,
+    f64::consts::PI,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
 use crate::{
-    canvas::Canvas, constants::MAX_REFLECTION_DEPTH, matrices::Matrix, rays::Ray, tuples::Point,
+    canvas::Canvas,
+    color::Color,
+    constants::MAX_REFLECTION_DEPTH,
+    matrices::Matrix,
+    rays::Ray,
+    tuples::Point,
+    utils::{halton, seeded_random, StableHasher},
     world::World,
 };
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    // Largest difference in any color channel between the corner samples of
+    // a region before that region gets subdivided
+    pub threshold: f64,
+    // Maximum number of times a pixel can be subdivided
+    pub max_depth: usize,
+}
+
+// Keeps adding rays to a pixel until its color settles, so noisy pixels (like
+// those in soft shadow edges) get more rays than clean ones
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgressiveSampling {
+    // Largest standard error of the pixel's mean luminance to stop at
+    pub threshold: f64,
+    // Rays traced before the first check, so a few lucky samples that agree
+    // don't stop the pixel early
+    pub min_samples: usize,
+    pub max_samples: usize,
+}
+
+// Where the supersamples of a pixel land, see SamplingPattern::offsets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SamplingPattern {
+    // Centers of a regular grid of cells
+    Grid,
+    // A random point inside each grid cell
+    Jittered { seed: u64 },
+    // Successive points of the base 2/3 Halton sequence, which stay evenly
+    // spread for any sample count
+    Halton,
+    // Random points kept apart from each other by a minimum distance
+    Poisson { seed: u64 },
+}
+
+impl SamplingPattern {
+    // Offsets of count samples within the pixel at (x, y), with (0, 0) and
+    // (1, 1) being opposite corners of the pixel. Grid and Jittered use the
+    // smallest square grid with at least count cells, filled row by row.
+    pub fn offsets(&self, count: usize, x: usize, y: usize) -> Vec<(f64, f64)> {
+        let side = (count as f64).sqrt().ceil() as usize;
+        let cell = |i: usize, (dx, dy): (f64, f64)| {
+            (
+                ((i % side) as f64 + dx) / side as f64,
+                ((i / side) as f64 + dy) / side as f64,
+            )
+        };
+
+        match *self {
+            SamplingPattern::Grid => (0..count).map(|i| cell(i, (0.5, 0.5))).collect(),
+            SamplingPattern::Jittered { seed } => (0..count)
+                .map(|i| {
+                    let random =
+                        |axis: f64| seeded_random(seed, &[x as f64, y as f64, i as f64, axis]);
+                    cell(i, (random(0.0), random(1.0)))
+                })
+                .collect(),
+            // Starts at 1 since every sequence begins at the corner (0, 0)
+            SamplingPattern::Halton => (1..=count).map(|i| (halton(i, 2), halton(i, 3))).collect(),
+            SamplingPattern::Poisson { seed } => poisson_offsets(seed, count, x, y),
+        }
+    }
+}
+
+// Dart throwing: random candidates are accepted when they are far enough from
+// every accepted sample. If too many candidates get rejected the rest are
+// accepted regardless, so there are always count samples.
+fn poisson_offsets(seed: u64, count: usize, x: usize, y: usize) -> Vec<(f64, f64)> {
+    let min_distance = 0.7 / (count as f64).sqrt();
+    let max_attempts = 30 * count;
+    let mut samples: Vec<(f64, f64)> = Vec::with_capacity(count);
+    let mut attempt = 0;
+
+    while samples.len() < count {
+        let random = |axis: f64| seeded_random(seed, &[x as f64, y as f64, attempt as f64, axis]);
+        let candidate = (random(0.0), random(1.0));
+        attempt += 1;
+
+        let far_enough = samples.iter().all(|(sx, sy)| {
+            ((sx - candidate.0).powi(2) + (sy - candidate.1).powi(2)).sqrt() >= min_distance
+        });
+        if far_enough || attempt > max_attempts {
+            samples.push(candidate);
+        }
+    }
+
+    samples
+}
+
+#[derive(Debug, Clone)]
 pub struct Camera {
     // Horizontal size, in pixels, of the canvas that the picture will be rendered to
     hsize: usize,
@@ -12,6 +115,26 @@ pub struct Camera {
     field_of_view: f64,
     // Matrix describing how the world should be oriented relative to camera
     pub transform: Matrix,
+    // Adaptive supersampling, a single ray through the pixel center when None
+    pub antialiasing: Option<AdaptiveSampling>,
+    // Variance driven sampling, which takes precedence over antialiasing and
+    // samples when set
+    pub progressive: Option<ProgressiveSampling>,
+    // Rays per pixel along each axis when not sampling adaptively, so each
+    // pixel averages samples² rays on a regular grid
+    pub samples: usize,
+    // Where those samples land inside the pixel
+    pub sampling_pattern: SamplingPattern,
+    // How many times rays may bounce off reflective surfaces
+    pub reflection_depth: usize,
+    // Radius of the lens. 0 makes a pinhole camera with everything in
+    // focus, anything larger blurs what isn't at focal_distance.
+    pub aperture: f64,
+    // Distance in front of the camera of the plane that is in sharp focus
+    pub focal_distance: f64,
+    // Rays per pixel spread over the lens when aperture is above 0 and
+    // nothing else is sampling the pixel
+    pub lens_samples: usize,
 
     half_width: f64,
     half_height: f64,
@@ -41,21 +164,94 @@ impl Camera {
             vsize,
             field_of_view,
             transform: Matrix::identity(),
+            antialiasing: None,
+            progressive: None,
+            samples: 1,
+            sampling_pattern: SamplingPattern::Grid,
+            reflection_depth: MAX_REFLECTION_DEPTH,
+            aperture: 0.0,
+            focal_distance: 1.0,
+            lens_samples: 16,
             half_width,
             half_height,
             pixel_size,
         }
     }
 
-    fn ray_for_pixel(&self, px: f64, py: f64) -> Ray {
-        // The offset from the edge of the canvas to the pixel's center
-        let x_offset = (px + 0.5) * self.pixel_size;
-        let y_offset = (py + 0.5) * self.pixel_size;
+    pub fn hsize(&self) -> usize {
+        self.hsize
+    }
 
-        // The untransformed coordinates of the pixel in world space
-        // (Camera looks towards -z, so +x is the LEFT)
-        let world_x = self.half_width - x_offset;
-        let world_y = self.half_height - y_offset;
+    pub fn vsize(&self) -> usize {
+        self.vsize
+    }
+
+    pub fn field_of_view(&self) -> f64 {
+        self.field_of_view
+    }
+
+    // Width of a pixel on the canvas one unit in front of the camera
+    pub fn pixel_size(&self) -> f64 {
+        self.pixel_size
+    }
+
+    // Hash of the settings that affect what the camera renders, see
+    // World::scene_hash
+    pub fn scene_hash(&self) -> u64 {
+        let mut h = StableHasher::new();
+
+        h.write_usize(self.hsize);
+        h.write_usize(self.vsize);
+        h.write_f64(self.field_of_view);
+        self.transform.hash_into(&mut h);
+
+        h.write_bool(self.antialiasing.is_some());
+        if let Some(a) = self.antialiasing {
+            h.write_f64(a.threshold);
+            h.write_usize(a.max_depth);
+        }
+        h.write_bool(self.progressive.is_some());
+        if let Some(p) = self.progressive {
+            h.write_f64(p.threshold);
+            h.write_usize(p.min_samples);
+            h.write_usize(p.max_samples);
+        }
+
+        h.write_usize(self.samples);
+        match self.sampling_pattern {
+            SamplingPattern::Grid => h.write_u64(0),
+            SamplingPattern::Jittered { seed } => {
+                h.write_u64(1);
+                h.write_u64(seed);
+            }
+            SamplingPattern::Halton => h.write_u64(2),
+            SamplingPattern::Poisson { seed } => {
+                h.write_u64(3);
+                h.write_u64(seed);
+            }
+        }
+
+        h.write_usize(self.reflection_depth);
+        h.write_f64s(&[self.aperture, self.focal_distance]);
+        h.write_usize(self.lens_samples);
+
+        h.finish()
+    }
+
+    // The ray through the center of the pixel at (x, y), for tools that trace
+    // rays themselves
+    pub fn ray_through_pixel(&self, x: usize, y: usize) -> Ray {
+        self.ray_for_pixel(x as f64, y as f64)
+    }
+
+    // The ray through a fractional position on the canvas, where whole
+    // numbers are pixel centers, for tools doing their own sub-pixel sampling
+    pub fn ray_for_subpixel(&self, px: f64, py: f64) -> Ray {
+        self.ray_for_pixel(px, py)
+    }
+
+    fn ray_for_pixel(&self, px: f64, py: f64) -> Ray {
+        let (world_x, world_y) = self.canvas_point(px, py);
 
         // Using the camera matrix, transform the canvas point and the origin
         // then compute the ray's direction vector
@@ -67,13 +263,51 @@ impl Camera {
         Ray::new(origin, direction)
     }
 
-    pub fn render(self, world: World) -> Canvas {
+    // The untransformed coordinates of the pixel on the canvas at z = -1
+    fn canvas_point(&self, px: f64, py: f64) -> (f64, f64) {
+        // The offset from the edge of the canvas to the pixel's center
+        let x_offset = (px + 0.5) * self.pixel_size;
+        let y_offset = (py + 0.5) * self.pixel_size;
+
+        // (Camera looks towards -z, so +x is the LEFT)
+        (self.half_width - x_offset, self.half_height - y_offset)
+    }
+
+    // The sample-th ray through the pixel from a random point on the lens,
+    // aimed at where the pinhole ray crosses the focal plane. Points at
+    // focal_distance are hit by every lens ray of the pixel, so they stay
+    // sharp while nearer and farther ones smear out. Without an aperture
+    // this is just ray_for_pixel.
+    fn lens_ray(&self, px: f64, py: f64, sample: usize) -> Ray {
+        if self.aperture <= 0.0 {
+            return self.ray_for_pixel(px, py);
+        }
+
+        let (world_x, world_y) = self.canvas_point(px, py);
+        let focus = Point::new(
+            world_x * self.focal_distance,
+            world_y * self.focal_distance,
+            -self.focal_distance,
+        );
+
+        // Uniformly spread over the disk, hence the square root
+        let random = |axis: f64| seeded_random(sample as u64, &[px, py, axis]);
+        let radius = self.aperture * random(0.0).sqrt();
+        let angle = 2.0 * PI * random(1.0);
+        let lens = Point::new(radius * angle.cos(), radius * angle.sin(), 0.0);
+
+        let focus = self.transform.inverse() * focus;
+        let origin = self.transform.inverse() * lens;
+
+        Ray::new(origin, (focus - origin).normalize())
+    }
+
+    pub fn render(&self, world: &World) -> Canvas {
         let mut image = Canvas::new(self.hsize, self.vsize);
 
         for y in 0..self.vsize {
             for x in 0..self.hsize {
-                let ray = self.ray_for_pixel(x as f64, y as f64);
-                let color = world.color_at(ray, MAX_REFLECTION_DEPTH);
+                let (color, _) = self.render_pixel(world, x, y);
 
                 image.write_pixel(x, y, color);
             }
@@ -81,6 +315,371 @@ impl Camera {
 
         image
     }
+
+    // Same as render, but rows are shared out over one thread per available
+    // core, each taking the next row not yet started as it finishes one
+    pub fn render_parallel(&self, world: &World) -> Canvas {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+
+        self.render_in_tiles(world, self.hsize, 1, threads)
+    }
+
+    // Renders on several threads at once. The image is cut into square tiles
+    // that threads take from a shared queue as they finish their previous
+    // one, so expensive parts of the image get spread over all threads.
+    pub fn render_tiled(&self, world: &World, tile_size: usize, threads: usize) -> Canvas {
+        self.render_in_tiles(world, tile_size, tile_size, threads)
+    }
+
+    // The shared queue behind render_tiled and render_parallel, with tiles of
+    // any width and height so that whole rows can be handed out as well
+    fn render_in_tiles(
+        &self,
+        world: &World,
+        width: usize,
+        height: usize,
+        threads: usize,
+    ) -> Canvas {
+        let (width, height) = (width.max(1), height.max(1));
+        let tiles_across = self.hsize.div_ceil(width);
+        let tile_count = tiles_across * self.vsize.div_ceil(height);
+        let next_tile = AtomicUsize::new(0);
+
+        let rendered: Vec<Vec<(usize, usize, Color)>> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.max(1))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut pixels = Vec::new();
+
+                        loop {
+                            let tile = next_tile.fetch_add(1, Ordering::Relaxed);
+                            if tile >= tile_count {
+                                break;
+                            }
+
+                            let x0 = (tile % tiles_across) * width;
+                            let y0 = (tile / tiles_across) * height;
+                            for y in y0..(y0 + height).min(self.vsize) {
+                                for x in x0..(x0 + width).min(self.hsize) {
+                                    let (color, _) = self.render_pixel(world, x, y);
+                                    pixels.push((x, y, color));
+                                }
+                            }
+                        }
+
+                        pixels
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect()
+        });
+
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        for (x, y, color) in rendered.into_iter().flatten() {
+            image.write_pixel(x, y, color);
+        }
+
+        image
+    }
+
+    // Fast, blocky preview that only shades every stride-th pixel in each
+    // direction and copies its color over the block of pixels it stands for
+    pub fn render_preview(&self, world: &World, stride: usize) -> Canvas {
+        let stride = stride.max(1);
+        let mut image = Canvas::new(self.hsize, self.vsize);
+
+        for y in (0..self.vsize).step_by(stride) {
+            for x in (0..self.hsize).step_by(stride) {
+                let (color, _) = self.render_pixel(world, x, y);
+
+                for block_y in y..(y + stride).min(self.vsize) {
+                    for block_x in x..(x + stride).min(self.hsize) {
+                        image.write_pixel(block_x, block_y, color);
+                    }
+                }
+            }
+        }
+
+        image
+    }
+
+    // Renders the image along with a heatmap of how many samples each pixel
+    // took, normalized so the most sampled pixel is white
+    pub fn render_with_heatmap(&self, world: &World) -> (Canvas, Canvas) {
+        let (image, counts) = self.render_with_sample_counts(world);
+
+        let max_count = counts.iter().copied().max().unwrap_or(1).max(1);
+        let mut heatmap = Canvas::new(self.hsize, self.vsize);
+        for (i, &count) in counts.iter().enumerate() {
+            let color = heat_color(count as f64 / max_count as f64);
+            heatmap.write_pixel(i % self.hsize, i / self.hsize, color);
+        }
+
+        (image, heatmap)
+    }
+
+    // Renders the image along with the number of rays traced for each pixel,
+    // row by row
+    pub fn render_with_sample_counts(&self, world: &World) -> (Canvas, Vec<usize>) {
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        let mut counts = Vec::with_capacity(self.hsize * self.vsize);
+
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                let (color, samples) = self.render_pixel(world, x, y);
+
+                image.write_pixel(x, y, color);
+                counts.push(samples);
+            }
+        }
+
+        (image, counts)
+    }
+
+    // Renders the image along with a grayscale alpha matte from
+    // World::alpha_at, white where the pixel is opaque. The matte takes one
+    // ray through the center of each pixel.
+    pub fn render_with_alpha(&self, world: &World) -> (Canvas, Canvas) {
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        let mut matte = Canvas::new(self.hsize, self.vsize);
+
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                let (color, _) = self.render_pixel(world, x, y);
+                let alpha = world.alpha_at(self.ray_for_pixel(x as f64, y as f64));
+
+                image.write_pixel(x, y, color);
+                matte.write_pixel(x, y, Color::white() * alpha);
+            }
+        }
+
+        (image, matte)
+    }
+
+    // Returns the color of the pixel and the number of rays it took
+    fn render_pixel(&self, world: &World, x: usize, y: usize) -> (Color, usize) {
+        if let Some(progressive) = self.progressive {
+            return self.sample_progressive(world, progressive, x, y);
+        }
+
+        match self.antialiasing {
+            None if self.samples <= 1 && self.aperture > 0.0 => self.sample_lens(world, x, y),
+            None if self.samples <= 1 => {
+                let ray = self.ray_for_pixel(x as f64, y as f64);
+                (world.color_at(ray, self.reflection_depth), 1)
+            }
+            None => self.sample_grid(world, x, y),
+            Some(sampling) => self.sample_adaptive(world, sampling, x as f64, y as f64),
+        }
+    }
+
+    // Averages lens_samples rays through the pixel center from all over the
+    // lens
+    fn sample_lens(&self, world: &World, x: usize, y: usize) -> (Color, usize) {
+        let count = self.lens_samples.max(1);
+        let color = (0..count)
+            .map(|i| {
+                let ray = self.lens_ray(x as f64, y as f64, i);
+                world.color_at(ray, self.reflection_depth)
+            })
+            .fold(Color::black(), |sum, c| sum + c);
+
+        (color * (1.0 / count as f64), count)
+    }
+
+    // Averages samples² rays placed over the pixel by the sampling pattern
+    fn sample_grid(&self, world: &World, x: usize, y: usize) -> (Color, usize) {
+        let count = self.samples * self.samples;
+        let mut color = Color::black();
+
+        for (i, (dx, dy)) in self
+            .sampling_pattern
+            .offsets(count, x, y)
+            .into_iter()
+            .enumerate()
+        {
+            // ray_for_pixel aims at the pixel center, so offset from it
+            let ray = self.lens_ray(x as f64 + dx - 0.5, y as f64 + dy - 0.5, i);
+
+            color = color + world.color_at(ray, self.reflection_depth);
+        }
+
+        (color * (1.0 / count as f64), count)
+    }
+
+    // Traces rays at successive Halton points over the pixel, tracking the
+    // running mean and variance of their luminance, until the mean is known
+    // to within the threshold or the sample budget runs out
+    fn sample_progressive(
+        &self,
+        world: &World,
+        sampling: ProgressiveSampling,
+        x: usize,
+        y: usize,
+    ) -> (Color, usize) {
+        let max_samples = sampling.max_samples.max(1);
+        let mut sum = Color::black();
+        let mut mean = 0.0;
+        let mut squared_deviations = 0.0;
+        let mut n = 0;
+
+        while n < max_samples {
+            n += 1;
+            let (dx, dy) = (halton(n, 2), halton(n, 3));
+            let ray = self.lens_ray(x as f64 + dx - 0.5, y as f64 + dy - 0.5, n);
+            let color = world.color_at(ray, self.reflection_depth);
+            sum = sum + color;
+
+            // Welford's update, which stays accurate over many samples
+            let luminance = color.luminance();
+            let delta = luminance - mean;
+            mean += delta / n as f64;
+            squared_deviations += delta * (luminance - mean);
+
+            if n >= sampling.min_samples.max(2) {
+                let variance = squared_deviations / (n - 1) as f64;
+                if (variance / n as f64).sqrt() <= sampling.threshold {
+                    break;
+                }
+            }
+        }
+
+        (sum * (1.0 / n as f64), n)
+    }
+
+    // Adaptively samples the pixel at (x, y), starting from its four corners
+    fn sample_adaptive(
+        &self,
+        world: &World,
+        sampling: AdaptiveSampling,
+        x: f64,
+        y: f64,
+    ) -> (Color, usize) {
+        let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)];
+        let corners = corners.map(|(dx, dy)| self.corner_color(world, x + dx, y + dy));
+        let (color, samples) = self.sample_region(world, sampling, x, y, 1.0, corners, 0);
+
+        (color, samples + corners.len())
+    }
+
+    // Color at a corner of a pixel region, given in pixel coordinates. The
+    // lens sample only depends on where the corner is, so corners shared by
+    // neighbouring regions come out the same.
+    fn corner_color(&self, world: &World, x: f64, y: f64) -> Color {
+        // ray_for_pixel aims at the pixel center, so shift by half a pixel to hit the corners
+        let ray = self.lens_ray(x - 0.5, y - 0.5, 0);
+
+        world.color_at(ray, self.reflection_depth)
+    }
+
+    // Averages the square region starting at (x, y), given the colors already
+    // traced at its top left, top right, bottom left and bottom right corners.
+    // Regions whose corners disagree too much are split into quadrants, which
+    // share the parent's corners and the five new points between them, so no
+    // point is traced twice. Returns the number of rays it traced.
+    #[allow(clippy::too_many_arguments)]
+    fn sample_region(
+        &self,
+        world: &World,
+        sampling: AdaptiveSampling,
+        x: f64,
+        y: f64,
+        size: f64,
+        corners: [Color; 4],
+        depth: usize,
+    ) -> (Color, usize) {
+        let max_difference = corners
+            .iter()
+            .flat_map(|a| corners.iter().map(move |b| *a - *b))
+            .map(|d| d.0.abs().max(d.1.abs()).max(d.2.abs()))
+            .fold(0.0, f64::max);
+
+        if max_difference <= sampling.threshold || depth >= sampling.max_depth {
+            let sum = corners.iter().fold(Color::black(), |acc, c| acc + *c);
+            return (sum * 0.25, 0);
+        }
+
+        let half = size / 2.0;
+        let [top_left, top_right, bottom_left, bottom_right] = corners;
+        let top = self.corner_color(world, x + half, y);
+        let left = self.corner_color(world, x, y + half);
+        let center = self.corner_color(world, x + half, y + half);
+        let right = self.corner_color(world, x + size, y + half);
+        let bottom = self.corner_color(world, x + half, y + size);
+
+        let quadrants = [
+            (0.0, 0.0, [top_left, top, left, center]),
+            (half, 0.0, [top, top_right, center, right]),
+            (0.0, half, [left, center, bottom_left, bottom]),
+            (half, half, [center, right, bottom, bottom_right]),
+        ];
+
+        let mut color = Color::black();
+        let mut samples = 5;
+        for (dx, dy, corners) in quadrants {
+            let (c, n) =
+                self.sample_region(world, sampling, x + dx, y + dy, half, corners, depth + 1);
+            color = color + c * 0.25;
+            samples += n;
+        }
+
+        (color, samples)
+    }
+}
+
+// A set of named cameras for looking at the same world from several
+// viewpoints
+#[derive(Debug, Clone, Default)]
+pub struct CameraRig {
+    cameras: HashMap<String, Camera>,
+}
+
+impl CameraRig {
+    pub fn new() -> Self {
+        CameraRig {
+            cameras: HashMap::new(),
+        }
+    }
+
+    // Registers a camera under the given name, replacing any camera that
+    // was already registered with it
+    pub fn add_camera(&mut self, name: &str, camera: Camera) {
+        self.cameras.insert(name.to_string(), camera);
+    }
+
+    pub fn camera(&self, name: &str) -> Option<&Camera> {
+        self.cameras.get(name)
+    }
+
+    pub fn camera_mut(&mut self, name: &str) -> Option<&mut Camera> {
+        self.cameras.get_mut(name)
+    }
+
+    pub fn render(&self, name: &str, world: &World) -> Option<Canvas> {
+        self.camera(name).map(|c| c.render(world))
+    }
+
+    pub fn render_all(&self, world: &World) -> HashMap<String, Canvas> {
+        self.cameras
+            .iter()
+            .map(|(name, camera)| (name.clone(), camera.render(world)))
+            .collect()
+    }
+}
+
+// Maps 0..1 onto a black -> red -> yellow -> white ramp
+fn heat_color(t: f64) -> Color {
+    let t = t.clamp(0.0, 1.0) * 3.0;
+
+    Color(
+        t.min(1.0),
+        (t - 1.0).clamp(0.0, 1.0),
+        (t - 2.0).clamp(0.0, 1.0),
+    )
 }
 
 #[cfg(test)]
@@ -90,12 +689,29 @@ mod tests {
     use crate::{
         color::Color,
         constants::EPSILON,
+        lights::PointLight,
+        shapes::{plane::Plane, sphere::Sphere, Shape},
         transformation::{rotation_y, translation, view_transform},
         tuples::{Point, Vector},
     };
 
     use super::*;
 
+    #[test]
+    fn scene_hash_changes_with_camera_settings() {
+        let c1 = Camera::new(160, 120, PI / 2.0);
+        let mut c2 = Camera::new(160, 120, PI / 2.0);
+
+        assert_eq!(c1.scene_hash(), c2.scene_hash());
+
+        c2.transform = translation(0.0, 1.0, 0.0);
+        assert_ne!(c1.scene_hash(), c2.scene_hash());
+        assert_ne!(
+            c1.scene_hash(),
+            Camera::new(160, 121, PI / 2.0).scene_hash()
+        );
+    }
+
     #[test]
     fn constructing_camera() {
         let hsize = 160;
@@ -128,6 +744,34 @@ mod tests {
         assert_eq!(r.direction, Vector::new(0.0, 0.0, -1.0));
     }
 
+    #[test]
+    fn public_ray_through_pixel_matches_private_ray() {
+        let c = Camera::new(201, 101, PI / 2.0);
+        let r = c.ray_through_pixel(100, 50);
+
+        assert_eq!(r.origin, Point::origin());
+        assert_eq!(r.direction, Vector::new(0.0, 0.0, -1.0));
+        assert_eq!(
+            c.ray_through_pixel(0, 0).direction,
+            c.ray_for_pixel(0.0, 0.0).direction
+        );
+    }
+
+    #[test]
+    fn constructing_ray_between_pixel_centers() {
+        let c = Camera::new(201, 101, PI / 2.0);
+        let center = c.ray_through_pixel(100, 50).direction;
+        let next = c.ray_through_pixel(101, 51).direction;
+
+        let r = c.ray_for_subpixel(100.5, 50.5);
+
+        assert_eq!(r.origin, Point::origin());
+        assert_ne!(r.direction, center);
+        // Pixels to the right and below point further towards -x and -y
+        assert!(next.0 < r.direction.0 && r.direction.0 < center.0);
+        assert!(next.1 < r.direction.1 && r.direction.1 < center.1);
+    }
+
     #[test]
     fn constructing_ray_through_corner_of_canvas() {
         let c = Camera::new(201, 101, PI / 2.0);
@@ -160,8 +804,415 @@ mod tests {
         let up = Vector::new(0.0, 1.0, 0.0);
         c.transform = view_transform(from, to, up);
 
-        let image = c.render(w);
+        let image = c.render(&w);
 
         assert_eq!(image.pixel_at(5, 5), Color(0.38066, 0.47583, 0.2855));
     }
+
+    #[test]
+    fn zero_aperture_camera_renders_like_pinhole() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::origin(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        c.aperture = 0.0;
+        c.focal_distance = 3.0;
+
+        let image = c.render(&w);
+
+        assert_eq!(image.pixel_at(5, 5), Color(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn lens_rays_meet_on_focal_plane() {
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = translation(0.0, 0.0, 2.0);
+        c.aperture = 0.5;
+        c.focal_distance = 4.0;
+
+        let pinhole = c.ray_for_pixel(2.0, 7.0);
+        let focus = pinhole.position(-c.focal_distance / pinhole.direction.2);
+
+        let origins: Vec<Point> = (0..8)
+            .map(|i| {
+                let r = c.lens_ray(2.0, 7.0, i);
+                let t = -c.focal_distance / r.direction.2;
+                assert_eq!(r.position(t), focus);
+
+                r.origin
+            })
+            .collect();
+
+        // The rays all leave from different points within the aperture
+        for (i, origin) in origins.iter().enumerate() {
+            assert!((*origin - pinhole.origin).magnitude() <= c.aperture);
+            assert!(origins[i + 1..].iter().all(|other| other != origin));
+        }
+    }
+
+    #[test]
+    fn rendering_same_world_twice_from_one_camera() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::origin(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let camera = &c;
+
+        let first = camera.render(&w);
+        let second = camera.render(&w);
+
+        for y in 0..11 {
+            for x in 0..11 {
+                assert_eq!(first.pixel_at(x, y), second.pixel_at(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn rendering_shared_world_from_several_threads() {
+        fn assert_sync<T: Send + Sync>() {}
+        assert_sync::<World>();
+
+        let w = World::default();
+        let mut front = Camera::new(11, 11, PI / 2.0);
+        front.transform = view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::origin(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let mut side = Camera::new(11, 11, PI / 2.0);
+        side.transform = view_transform(
+            Point::new(-5.0, 0.0, 0.0),
+            Point::origin(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+
+        let (a, b) = thread::scope(|scope| {
+            let a = scope.spawn(|| front.render(&w));
+            let b = scope.spawn(|| side.render(&w));
+
+            (a.join().unwrap(), b.join().unwrap())
+        });
+
+        assert_eq!(a.pixel_at(5, 5), Color(0.38066, 0.47583, 0.2855));
+        // The light is as far to the side as it is in front, so both views
+        // see the same shading
+        assert_eq!(b.pixel_at(5, 5), Color(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn parallel_render_matches_sequential_render() {
+        let w = World::default();
+        let mut c = Camera::new(21, 17, PI / 2.0);
+        c.transform = view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::origin(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+
+        let sequential = c.render(&w);
+        let parallel = c.render_parallel(&w);
+
+        for y in 0..17 {
+            for x in 0..21 {
+                assert_eq!(parallel.pixel_at(x, y), sequential.pixel_at(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn tiled_render_matches_sequential_render() {
+        // Reflections only in the bottom half make some tiles much slower
+        let mut w = World::default();
+        let mut floor = Plane::new();
+        floor.material.reflective = 0.5;
+        floor.set_transformation(translation(0.0, -1.0, 0.0));
+        w.objects.push(Box::new(floor));
+
+        let mut c = Camera::new(15, 10, PI / 2.0);
+        c.transform = view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::origin(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+
+        let sequential = c.render(&w);
+        let tiled = c.render_tiled(&w, 4, 3);
+
+        for y in 0..10 {
+            for x in 0..15 {
+                assert_eq!(tiled.pixel_at(x, y), sequential.pixel_at(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn supersampling_on_a_grid() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::origin(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let single = c.render(&w);
+
+        c.samples = 3;
+        let (image, heatmap) = c.render_with_heatmap(&w);
+
+        // Every pixel takes the same number of samples
+        assert_eq!(heatmap.pixel_at(0, 0), heatmap.pixel_at(5, 5));
+        // The middle of the sphere barely changes, but the sharp specular
+        // highlight next to it gets averaged with its surroundings
+        assert!((image.pixel_at(5, 5).1 - single.pixel_at(5, 5).1).abs() < 0.05);
+        assert!((image.pixel_at(4, 5).1 - single.pixel_at(4, 5).1).abs() > 0.05);
+    }
+
+    #[test]
+    fn grid_pattern_samples_quadrant_centers() {
+        let offsets = SamplingPattern::Grid.offsets(4, 3, 7);
+
+        assert_eq!(
+            offsets,
+            vec![(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)]
+        );
+    }
+
+    #[test]
+    fn halton_pattern_follows_base_2_and_3_sequences() {
+        let offsets = SamplingPattern::Halton.offsets(4, 0, 0);
+        let expected = [
+            (1.0 / 2.0, 1.0 / 3.0),
+            (1.0 / 4.0, 2.0 / 3.0),
+            (3.0 / 4.0, 1.0 / 9.0),
+            (1.0 / 8.0, 4.0 / 9.0),
+        ];
+
+        for ((x, y), (ex, ey)) in offsets.iter().zip(expected) {
+            assert!((x - ex).abs() < EPSILON && (y - ey).abs() < EPSILON);
+        }
+        // Deterministic and the same for every pixel
+        assert_eq!(offsets, SamplingPattern::Halton.offsets(4, 5, 9));
+    }
+
+    #[test]
+    fn random_patterns_are_seeded_and_stay_in_their_pixel() {
+        for pattern in [
+            SamplingPattern::Jittered { seed: 3 },
+            SamplingPattern::Poisson { seed: 3 },
+        ] {
+            let offsets = pattern.offsets(9, 2, 4);
+
+            assert_eq!(offsets.len(), 9);
+            assert_eq!(offsets, pattern.offsets(9, 2, 4));
+            assert_ne!(offsets, pattern.offsets(9, 3, 4));
+            assert!(offsets
+                .iter()
+                .all(|&(x, y)| (0.0..1.0).contains(&x) && (0.0..1.0).contains(&y)));
+        }
+
+        // Each jittered sample stays in its own cell of a 3x3 grid
+        let jittered = SamplingPattern::Jittered { seed: 3 }.offsets(9, 2, 4);
+        for (i, (x, y)) in jittered.iter().enumerate() {
+            assert_eq!(((x * 3.0) as usize, (y * 3.0) as usize), (i % 3, i / 3));
+        }
+    }
+
+    #[test]
+    fn previewing_with_stride_one_matches_render() {
+        let w = World::default();
+        let mut c = Camera::new(6, 6, PI / 2.0);
+        c.transform = view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::origin(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+
+        let full = c.render(&w);
+        let preview = c.render_preview(&w, 1);
+
+        for y in 0..6 {
+            for x in 0..6 {
+                assert_eq!(preview.pixel_at(x, y), full.pixel_at(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn previewing_with_stride_two_shades_quarter_of_pixels() {
+        let w = World::default();
+        let mut c = Camera::new(6, 6, PI / 2.0);
+        c.transform = view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::origin(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+
+        let full = c.render(&w);
+        w.reset_ray_count();
+        let preview = c.render_preview(&w, 2);
+
+        assert_eq!(w.ray_count(), 9);
+        for y in 0..6 {
+            for x in 0..6 {
+                let shaded = (x - x % 2, y - y % 2);
+                assert_eq!(preview.pixel_at(x, y), full.pixel_at(shaded.0, shaded.1));
+            }
+        }
+    }
+
+    #[test]
+    fn rendering_world_from_every_camera_in_rig() {
+        let mut w = World::default();
+        w.lights = vec![Box::new(PointLight::new(
+            Point::new(0.0, 0.0, -10.0),
+            Color::white(),
+        ))];
+
+        let mut front = Camera::new(5, 5, PI / 2.0);
+        front.transform = view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::origin(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let mut top = Camera::new(7, 3, PI / 2.0);
+        top.transform = view_transform(
+            Point::new(0.0, 5.0, 0.0),
+            Point::origin(),
+            Vector::new(0.0, 0.0, 1.0),
+        );
+
+        let mut rig = CameraRig::new();
+        rig.add_camera("front", front);
+        rig.add_camera("top", top);
+
+        let images = rig.render_all(&w);
+
+        assert_eq!(images.len(), 2);
+        assert_eq!((images["front"].width, images["front"].height), (5, 5));
+        assert_eq!((images["top"].width, images["top"].height), (7, 3));
+        // Lit from the front, the sphere looks darker from above
+        assert_ne!(images["front"].pixel_at(2, 2), images["top"].pixel_at(3, 1));
+        assert_eq!(
+            rig.render("top", &w).unwrap().pixel_at(3, 1),
+            images["top"].pixel_at(3, 1)
+        );
+        assert!(rig.render("side", &w).is_none());
+    }
+
+    #[test]
+    fn heatmap_is_brighter_on_sphere_edges_than_background() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 6.0);
+        c.transform = view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::origin(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        c.antialiasing = Some(AdaptiveSampling {
+            threshold: 0.05,
+            max_depth: 2,
+        });
+
+        let (_, heatmap) = c.render_with_heatmap(&w);
+        let brightness = |c: Color| c.0 + c.1 + c.2;
+
+        // The sphere's silhouette crosses the middle row a couple of pixels from the center
+        let edge = (0..11)
+            .map(|x| brightness(heatmap.pixel_at(x, 5)))
+            .fold(0.0, f64::max);
+        let background = brightness(heatmap.pixel_at(0, 0));
+
+        assert!(edge > background);
+        assert_eq!(edge, 3.0);
+    }
+
+    #[test]
+    fn adaptive_sampling_traces_shared_corners_once() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 6.0);
+        c.transform = view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::origin(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        c.antialiasing = Some(AdaptiveSampling {
+            threshold: 0.05,
+            max_depth: 1,
+        });
+
+        let (_, counts) = c.render_with_sample_counts(&w);
+
+        // 4 corners, plus the 5 points between the quadrants when split
+        assert!(counts.iter().all(|&n| n == 4 || n == 9));
+        assert!(counts.contains(&9));
+    }
+
+    #[test]
+    fn heatmap_without_antialiasing_is_uniform() {
+        let w = World::default();
+        let c = Camera::new(5, 5, PI / 2.0);
+
+        let (_, heatmap) = c.render_with_heatmap(&w);
+
+        assert_eq!(heatmap.pixel_at(0, 0), Color::white());
+        assert_eq!(heatmap.pixel_at(2, 2), Color::white());
+    }
+
+    #[test]
+    fn progressive_sampling_spends_rays_on_soft_shadow_edges() {
+        // A ball on the floor under a large jittered light, seen from above
+        let mut light = PointLight::new(Point::new(0.0, 10.0, 0.0), Color::white());
+        light.jitter_radius = 1.0;
+        // Matte, so the moving light doesn't make highlights flicker
+        let mut floor = Plane::new();
+        floor.material.specular = 0.0;
+        let mut ball = Sphere::new();
+        ball.set_transformation(translation(0.0, 1.0, 0.0));
+        ball.material.specular = 0.0;
+        let w = World::from_shapes([Box::new(floor) as Box<dyn Shape>, Box::new(ball)])
+            .with_light(Box::new(light));
+
+        let mut c = Camera::new(8, 8, 2.0 * 0.2_f64.atan());
+        c.transform = view_transform(
+            Point::new(0.0, 20.0, 0.0),
+            Point::origin(),
+            Vector::new(0.0, 0.0, 1.0),
+        );
+        c.progressive = Some(ProgressiveSampling {
+            threshold: 0.02,
+            min_samples: 8,
+            max_samples: 64,
+        });
+
+        let (image, counts) = c.render_with_sample_counts(&w);
+
+        // Flat, fully lit floor in the corner stops as early as it can
+        assert_eq!(counts[0], 8);
+
+        // The busiest pixels look at the penumbra just around the ball
+        let busiest = (0..counts.len()).max_by_key(|&i| counts[i]).unwrap();
+        assert_eq!(counts[busiest], 64);
+        let r = c.ray_through_pixel(busiest % 8, busiest / 8);
+        let floor = r.position(-r.origin.1 / r.direction.1);
+        assert!((floor.0.powi(2) + floor.2.powi(2)).sqrt() < 2.0);
+
+        // And the result matches a render with many rays everywhere
+        c.progressive = None;
+        c.samples = 8;
+        c.sampling_pattern = SamplingPattern::Halton;
+        let reference = c.render(&w);
+        for y in 0..8 {
+            for x in 0..8 {
+                let d = image.pixel_at(x, y).luminance() - reference.pixel_at(x, y).luminance();
+                assert!(d.abs() < 0.1);
+            }
+        }
+    }
 }
@@ -3,10 +3,62 @@ use std::{fs::File, io};
 
 use crate::{color::Color, constants::MAX_COLOR_VALUE};
 
+// Which corner of the canvas pixel (0, 0) refers to in write_pixel and
+// pixel_at. The PPM output always starts from the top row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Origin {
+    TopLeft,
+    BottomLeft,
+}
+
+// Pixel counts per bin, with bins evenly splitting 0 to 1. Values outside
+// that range land in the first or last bin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub red: Vec<usize>,
+    pub green: Vec<usize>,
+    pub blue: Vec<usize>,
+    pub luminance: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+// Per pixel noise added before rounding so smooth gradients break up into a
+// mix of neighboring levels instead of visible bands
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dither {
+    // Repeating 4x4 Bayer matrix
+    Ordered,
+    // Interleaved gradient noise, a cheap stand-in for a blue noise texture
+    // without the Bayer matrix's visible cross-hatching
+    BlueNoise,
+}
+
+impl Dither {
+    // Shift for the pixel at (x, y), between -0.5 and 0.5 of a level
+    fn offset(&self, x: usize, y: usize) -> f64 {
+        match self {
+            Dither::Ordered => (BAYER_4X4[y % 4][x % 4] as f64 + 0.5) / 16.0 - 0.5,
+            Dither::BlueNoise => {
+                let f = (0.06711056 * x as f64 + 0.00583715 * y as f64).fract();
+                (52.9829189 * f).fract() - 0.5
+            }
+        }
+    }
+}
+
 pub struct Canvas {
     pub width: usize,
     pub height: usize,
     color_grid: Vec<Vec<Color>>,
+    origin: Origin,
+    // Dither applied when colors are rounded to 8 bits for the PPM output
+    ppm_dither: Option<Dither>,
 }
 
 impl Canvas {
@@ -22,6 +74,8 @@ impl Canvas {
             width,
             height,
             color_grid: color_matrix,
+            origin: Origin::TopLeft,
+            ppm_dither: None,
         }
     }
 
@@ -37,9 +91,19 @@ impl Canvas {
             width,
             height,
             color_grid: color_matrix,
+            origin: Origin::TopLeft,
+            ppm_dither: None,
         }
     }
 
+    pub fn set_origin(&mut self, origin: Origin) {
+        self.origin = origin;
+    }
+
+    pub fn set_ppm_dither(&mut self, dither: Option<Dither>) {
+        self.ppm_dither = dither;
+    }
+
     pub fn write_pixel(&mut self, x: usize, y: usize, color: Color) {
         if x >= self.width || y >= self.height {
             println!(
@@ -48,14 +112,253 @@ impl Canvas {
             );
             return;
         }
-        self.color_grid[y][x] = color;
+        let row = self.row(y);
+        self.color_grid[row][x] = color;
     }
 
     pub fn pixel_at(&self, x: usize, y: usize) -> Color {
-        self.color_grid[y][x]
+        self.color_grid[self.row(y)][x]
+    }
+
+    // Index into color_grid, which is always stored top row first
+    fn row(&self, y: usize) -> usize {
+        match self.origin {
+            Origin::TopLeft => y,
+            Origin::BottomLeft => self.height - 1 - y,
+        }
+    }
+
+    // Mirrors the image top to bottom
+    pub fn flip_vertical(&mut self) {
+        self.color_grid.reverse();
+    }
+
+    // Mirrors the image left to right
+    pub fn flip_horizontal(&mut self) {
+        for row in self.color_grid.iter_mut() {
+            row.reverse();
+        }
+    }
+
+    // A copy of the image turned 90° clockwise, so its width and height are
+    // swapped
+    pub fn rotate_90(&self) -> Canvas {
+        let mut rotated = Canvas::new(self.height, self.width);
+        rotated.origin = self.origin;
+        rotated.ppm_dither = self.ppm_dither;
+
+        for (y, row) in rotated.color_grid.iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = self.color_grid[self.height - 1 - x][y];
+            }
+        }
+
+        rotated
+    }
+
+    // Snaps every channel to the nearest of levels evenly spaced values
+    // between 0 and 1
+    pub fn quantize(&mut self, levels: usize) {
+        self.quantize_with_offsets(levels, |_, _| 0.0);
+    }
+
+    // Like quantize, but nudges each pixel by an ordered (Bayer) dither
+    // pattern first so flat areas between two levels become a mix of both
+    // instead of a hard band
+    pub fn quantize_dithered(&mut self, levels: usize) {
+        self.quantize_with_offsets(levels, |x, y| Dither::Ordered.offset(x, y));
+    }
+
+    // offset gives a per pixel shift in units of one quantization step
+    fn quantize_with_offsets(&mut self, levels: usize, offset: impl Fn(usize, usize) -> f64) {
+        let steps = levels.max(2) as f64 - 1.0;
+
+        for (y, row) in self.color_grid.iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let shift = offset(x, y);
+                let snap =
+                    |c: f64| ((c.clamp(0.0, 1.0) * steps + shift).round() / steps).clamp(0.0, 1.0);
+
+                *pixel = Color(snap(pixel.0), snap(pixel.1), snap(pixel.2));
+            }
+        }
+    }
+
+    // Scales every pixel so the brightest channel in the canvas becomes target_max
+    pub fn auto_expose(&mut self, target_max: f64) {
+        let max = self
+            .color_grid
+            .iter()
+            .flatten()
+            .map(|c| c.0.max(c.1).max(c.2))
+            .fold(0.0, f64::max);
+
+        // Nothing to normalize in an entirely black canvas
+        if max <= 0.0 {
+            return;
+        }
+
+        let scale = target_max / max;
+        for color in self.color_grid.iter_mut().flatten() {
+            *color = *color * scale;
+        }
     }
 
-    fn construct_ppm(self) -> String {
+    pub fn histogram(&self, bins: usize) -> Histogram {
+        let bins = bins.max(1);
+        let bin = |c: f64| ((c.clamp(0.0, 1.0) * bins as f64) as usize).min(bins - 1);
+
+        let mut histogram = Histogram {
+            red: vec![0; bins],
+            green: vec![0; bins],
+            blue: vec![0; bins],
+            luminance: vec![0; bins],
+        };
+        for color in self.color_grid.iter().flatten() {
+            histogram.red[bin(color.0)] += 1;
+            histogram.green[bin(color.1)] += 1;
+            histogram.blue[bin(color.2)] += 1;
+            histogram.luminance[bin(color.luminance())] += 1;
+        }
+
+        histogram
+    }
+
+    // Min, max and mean luminance over every pixel
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            mean: 0.0,
+        };
+        let mut count = 0;
+
+        for color in self.color_grid.iter().flatten() {
+            let luminance = color.luminance();
+            stats.min = stats.min.min(luminance);
+            stats.max = stats.max.max(luminance);
+            stats.mean += luminance;
+            count += 1;
+        }
+
+        // An empty canvas has nothing to measure
+        if count == 0 {
+            return Stats {
+                min: 0.0,
+                max: 0.0,
+                mean: 0.0,
+            };
+        }
+
+        stats.mean /= count as f64;
+        stats
+    }
+
+    // Replaces each pixel with the average of the (2 * radius + 1)² pixels
+    // around it, clamped at the canvas edges
+    pub fn box_blur(&mut self, radius: usize) {
+        self.filter(radius, |_, _, _| 1.0);
+    }
+
+    // Edge-preserving bilateral filter: neighbors are weighted by distance
+    // and by how close their color is to the center pixel, so flat noisy
+    // regions get smoothed while sharp edges are kept. Smaller sigma
+    // preserves more edges, down to none at all at 0.
+    pub fn denoise(&mut self, radius: usize, sigma: f64) {
+        // Only identical colors would be averaged, which changes nothing, and
+        // the weights would come out as NaN
+        if sigma <= 0.0 {
+            return;
+        }
+
+        let spatial = 2.0 * (radius as f64).powi(2);
+        let range = 2.0 * sigma.powi(2);
+
+        self.filter(radius, |distance_squared, center, neighbor| {
+            let d = center - neighbor;
+            let color_distance_squared = d.0.powi(2) + d.1.powi(2) + d.2.powi(2);
+
+            (-distance_squared / spatial - color_distance_squared / range).exp()
+        });
+    }
+
+    // Zoom blur: each pixel becomes the average of samples pixels on the line
+    // from it towards center (in pixel coordinates), reaching strength of
+    // the way there. A strength of 0 leaves the canvas unchanged.
+    pub fn radial_blur(&mut self, center: (f64, f64), strength: f64, samples: usize) {
+        if strength == 0.0 || samples <= 1 {
+            return;
+        }
+
+        let mut blurred = Canvas::new(self.width, self.height);
+        blurred.set_origin(self.origin);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut sum = Color::black();
+
+                for i in 0..samples {
+                    let t = strength * i as f64 / (samples - 1) as f64;
+                    let sx = x as f64 + (center.0 - x as f64) * t;
+                    let sy = y as f64 + (center.1 - y as f64) * t;
+                    let sx = (sx.round().max(0.0) as usize).min(self.width - 1);
+                    let sy = (sy.round().max(0.0) as usize).min(self.height - 1);
+
+                    sum = sum + self.pixel_at(sx, sy);
+                }
+
+                blurred.write_pixel(x, y, sum * (1.0 / samples as f64));
+            }
+        }
+
+        self.color_grid = blurred.color_grid;
+    }
+
+    // Applies a weighted average over each pixel's neighborhood. The weight
+    // function receives the squared pixel distance, the center color, and
+    // the neighbor color.
+    fn filter(&mut self, radius: usize, weight: impl Fn(f64, Color, Color) -> f64) {
+        if radius == 0 {
+            return;
+        }
+
+        let r = radius as isize;
+        let mut filtered = self.color_grid.clone();
+
+        for (y, row) in filtered.iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let center = self.color_grid[y][x];
+                let mut sum = Color::black();
+                let mut total_weight = 0.0;
+
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        let nx = x as isize + dx;
+                        let ny = y as isize + dy;
+                        if nx < 0
+                            || ny < 0
+                            || nx >= self.width as isize
+                            || ny >= self.height as isize
+                        {
+                            continue;
+                        }
+
+                        let neighbor = self.color_grid[ny as usize][nx as usize];
+                        let w = weight((dx * dx + dy * dy) as f64, center, neighbor);
+
+                        sum = sum + neighbor * w;
+                        total_weight += w;
+                    }
+                }
+
+                *pixel = sum * (1.0 / total_weight);
+            }
+        }
+
+        self.color_grid = filtered;
+    }
+
+    fn construct_ppm(&self) -> String {
         let header = self.construct_ppm_header();
         let body = self.construct_ppm_body();
 
@@ -68,33 +371,41 @@ impl Canvas {
 
     fn construct_ppm_body(&self) -> String {
         let mut body = String::new();
-        let mut last_newline_idx = 0;
-
-        for row in &self.color_grid {
-            for (i, color) in row.iter().enumerate() {
-                let color_value_string = format!(
-                    "{} {} {} ",
-                    clamp_and_scale_color_value(color.0),
-                    clamp_and_scale_color_value(color.1),
-                    clamp_and_scale_color_value(color.2)
-                );
-                body += &color_value_string;
-
-                // This is to make sure each line in PPM file does not go over 70
-                if body.len() - last_newline_idx > 58 && i != row.len() - 1 {
-                    body.pop();
-                    body += "\n";
-                    last_newline_idx = body.len() - 1;
+
+        for (y, row) in self.color_grid.iter().enumerate() {
+            // Characters on the current line, which may not go over 70
+            let mut line_length = 0;
+
+            for (x, color) in row.iter().enumerate() {
+                let shift = match self.ppm_dither {
+                    Some(dither) => dither.offset(x, y) / f64::from(MAX_COLOR_VALUE),
+                    None => 0.0,
+                };
+
+                let values = color
+                    .channels()
+                    .map(|channel| clamp_and_scale_color_value(channel + shift).to_string())
+                    .join(" ");
+
+                // Pixels are kept together on one line
+                if line_length == 0 {
+                    line_length = values.len();
+                } else if line_length + 1 + values.len() > PPM_LINE_LENGTH {
+                    body.push('\n');
+                    line_length = values.len();
+                } else {
+                    body.push(' ');
+                    line_length += 1 + values.len();
                 }
+                body += &values;
             }
-            body.pop();
-            body += "\n";
-            last_newline_idx = body.len() - 1;
+            body.push('\n');
         }
 
         body
     }
-    pub fn to_ppm(self, path: &str) -> io::Result<()> {
+
+    pub fn to_ppm(&self, path: &str) -> io::Result<()> {
         let mut file = File::create(path)?;
 
         write!(file, "{}", self.construct_ppm())?;
@@ -103,6 +414,10 @@ impl Canvas {
     }
 }
 
+const PPM_LINE_LENGTH: usize = 70;
+
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
 fn clamp_and_scale_color_value(c: f64) -> u8 {
     if c < 0.0 {
         return 0;
@@ -110,11 +425,13 @@ fn clamp_and_scale_color_value(c: f64) -> u8 {
     if c > 1.0 {
         return MAX_COLOR_VALUE;
     }
-    return (c * f64::from(MAX_COLOR_VALUE)).round() as u8;
+    (c * f64::from(MAX_COLOR_VALUE)).round() as u8
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::constants::EPSILON;
+
     use super::*;
 
     #[test]
@@ -137,6 +454,248 @@ mod tests {
         assert_eq!(c.pixel_at(2, 3), red);
     }
 
+    #[test]
+    fn flipping_twice_is_identity() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(0, 0, Color(1.0, 0.0, 0.0));
+        c.write_pixel(2, 1, Color(0.0, 0.0, 1.0));
+
+        c.flip_vertical();
+        assert_eq!(c.pixel_at(0, 1), Color(1.0, 0.0, 0.0));
+        c.flip_vertical();
+        c.flip_horizontal();
+        c.flip_horizontal();
+
+        assert_eq!(c.pixel_at(0, 0), Color(1.0, 0.0, 0.0));
+        assert_eq!(c.pixel_at(2, 1), Color(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn flipping_horizontally_swaps_first_and_last_column() {
+        let mut c = Canvas::new(3, 2);
+        for y in 0..2 {
+            c.write_pixel(0, y, Color(1.0, 0.0, 0.0));
+            c.write_pixel(2, y, Color(0.0, 0.0, 1.0));
+        }
+
+        c.flip_horizontal();
+
+        for y in 0..2 {
+            assert_eq!(c.pixel_at(0, y), Color(0.0, 0.0, 1.0));
+            assert_eq!(c.pixel_at(1, y), Color::black());
+            assert_eq!(c.pixel_at(2, y), Color(1.0, 0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn rotating_canvas_90_degrees() {
+        let red = Color(1.0, 0.0, 0.0);
+        let green = Color(0.0, 1.0, 0.0);
+        let blue = Color(0.0, 0.0, 1.0);
+        let mut c = Canvas::new(2, 3);
+        c.write_pixel(0, 0, red);
+        c.write_pixel(1, 0, green);
+        c.write_pixel(0, 2, blue);
+        c.write_pixel(1, 2, Color::white());
+
+        let rotated = c.rotate_90();
+
+        // Clockwise, so the left column becomes the top row
+        assert_eq!((rotated.width, rotated.height), (3, 2));
+        assert_eq!(rotated.pixel_at(0, 0), blue);
+        assert_eq!(rotated.pixel_at(2, 0), red);
+        assert_eq!(rotated.pixel_at(0, 1), Color::white());
+        assert_eq!(rotated.pixel_at(2, 1), green);
+    }
+
+    #[test]
+    fn auto_exposing_scales_brightest_channel_to_target() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(0, 0, Color(4.0, 2.0, 0.0));
+        c.write_pixel(2, 1, Color(1.0, 0.5, 0.25));
+
+        c.auto_expose(1.0);
+
+        assert_eq!(c.pixel_at(0, 0), Color(1.0, 0.5, 0.0));
+        assert_eq!(c.pixel_at(2, 1), Color(0.25, 0.125, 0.0625));
+        assert_eq!(c.pixel_at(1, 0), Color::black());
+    }
+
+    #[test]
+    fn histogram_and_stats_of_half_black_half_white_canvas() {
+        let mut c = Canvas::new(4, 2);
+        for x in 0..4 {
+            c.write_pixel(x, 1, Color::white());
+        }
+
+        let histogram = c.histogram(4);
+        let stats = c.stats();
+
+        assert_eq!(histogram.luminance, vec![4, 0, 0, 4]);
+        assert_eq!(histogram.red, vec![4, 0, 0, 4]);
+        assert_eq!(histogram.green, histogram.blue);
+        assert_eq!(stats.min, 0.0);
+        assert!((stats.max - 1.0).abs() < EPSILON);
+        assert!((stats.mean - 0.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn radial_blur_with_zero_strength_does_nothing() {
+        let mut c = Canvas::new(5, 5);
+        c.write_pixel(3, 1, Color(0.2, 0.4, 0.6));
+
+        c.radial_blur((2.0, 2.0), 0.0, 8);
+
+        assert_eq!(c.pixel_at(3, 1), Color(0.2, 0.4, 0.6));
+        assert_eq!(c.pixel_at(2, 2), Color::black());
+    }
+
+    #[test]
+    fn radial_blur_streaks_pixels_away_from_center() {
+        let mut c = Canvas::new(16, 9);
+        c.write_pixel(8, 4, Color::white());
+
+        c.radial_blur((0.0, 4.0), 0.5, 11);
+
+        // Pixels further out on the line through the center pick up the
+        // bright pixel, while pixels nearer the center and off the line don't
+        assert!(c.pixel_at(8, 4).0 > 0.0);
+        assert!(c.pixel_at(10, 4).0 > 0.0);
+        assert!(c.pixel_at(15, 4).0 > 0.0);
+        assert_eq!(c.pixel_at(6, 4), Color::black());
+        assert_eq!(c.pixel_at(10, 2), Color::black());
+        assert_eq!(c.pixel_at(10, 6), Color::black());
+    }
+
+    #[test]
+    fn auto_exposing_black_canvas_does_nothing() {
+        let mut c = Canvas::new(2, 2);
+
+        c.auto_expose(1.0);
+
+        assert_eq!(c.pixel_at(0, 0), Color::black());
+    }
+
+    #[test]
+    fn writing_pixels_with_top_left_origin() {
+        let mut c = Canvas::new(3, 2);
+        c.set_origin(Origin::TopLeft);
+
+        c.write_pixel(0, 0, Color::white());
+
+        assert_eq!(c.color_grid[0][0], Color::white());
+        assert_eq!(c.pixel_at(0, 0), Color::white());
+    }
+
+    #[test]
+    fn writing_pixels_with_bottom_left_origin() {
+        let mut c = Canvas::new(3, 2);
+        c.set_origin(Origin::BottomLeft);
+
+        c.write_pixel(0, 0, Color::white());
+
+        assert_eq!(c.color_grid[1][0], Color::white());
+        assert_eq!(c.color_grid[0][0], Color::black());
+        assert_eq!(c.pixel_at(0, 0), Color::white());
+        assert_eq!(c.pixel_at(0, 1), Color::black());
+    }
+
+    #[test]
+    fn quantizing_to_two_levels() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color(0.4, 0.6, 0.0));
+        c.write_pixel(1, 0, Color(1.2, -0.3, 0.5001));
+
+        c.quantize(2);
+
+        assert_eq!(c.pixel_at(0, 0), Color(0.0, 1.0, 0.0));
+        assert_eq!(c.pixel_at(1, 0), Color(1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn quantizing_to_more_levels() {
+        let mut c = Canvas::with_filled_color(1, 1, Color(0.3, 0.45, 0.9));
+
+        c.quantize(3);
+
+        assert_eq!(c.pixel_at(0, 0), Color(0.5, 0.5, 1.0));
+    }
+
+    #[test]
+    fn dithering_mixes_nearest_levels() {
+        let mut c = Canvas::with_filled_color(4, 4, Color(0.5, 0.5, 0.5));
+
+        c.quantize_dithered(2);
+
+        let whites = (0..4)
+            .flat_map(|y| (0..4).map(move |x| (x, y)))
+            .filter(|&(x, y)| c.pixel_at(x, y) == Color::white())
+            .count();
+        let blacks = (0..4)
+            .flat_map(|y| (0..4).map(move |x| (x, y)))
+            .filter(|&(x, y)| c.pixel_at(x, y) == Color::black())
+            .count();
+
+        assert_eq!(whites + blacks, 16);
+        assert_eq!(whites, 8);
+    }
+
+    #[test]
+    fn box_blur_spreads_noise_to_neighbors() {
+        let mut c = Canvas::with_filled_color(5, 5, Color(0.5, 0.5, 0.5));
+        c.write_pixel(2, 2, Color(1.4, 1.4, 1.4));
+
+        c.box_blur(1);
+
+        assert_eq!(c.pixel_at(2, 2), Color(0.6, 0.6, 0.6));
+        assert_eq!(c.pixel_at(1, 1), Color(0.6, 0.6, 0.6));
+        assert_eq!(c.pixel_at(0, 0), Color(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn filtering_with_zero_radius_does_nothing() {
+        let mut c = Canvas::with_filled_color(3, 3, Color(0.5, 0.5, 0.5));
+        c.write_pixel(1, 1, Color::white());
+
+        c.box_blur(0);
+        c.denoise(0, 0.1);
+
+        assert_eq!(c.pixel_at(1, 1), Color::white());
+        assert_eq!(c.pixel_at(0, 1), Color(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn denoising_smooths_noise_but_keeps_edges() {
+        // Left half black, right half white, with a slightly noisy pixel
+        let mut c = Canvas::new(6, 4);
+        for y in 0..4 {
+            for x in 3..6 {
+                c.write_pixel(x, y, Color::white());
+            }
+        }
+        c.write_pixel(1, 1, Color(0.1, 0.1, 0.1));
+
+        c.denoise(1, 0.2);
+
+        // The noise is pulled towards the surrounding black
+        assert!(c.pixel_at(1, 1).0 < 0.1);
+        assert!(c.pixel_at(1, 1).0 > 0.0);
+        // The edge between the halves barely moves
+        assert!(c.pixel_at(2, 2).0 < 0.01);
+        assert!(c.pixel_at(3, 2).0 > 0.99);
+    }
+
+    #[test]
+    fn denoising_with_zero_sigma_does_nothing() {
+        let mut c = Canvas::with_filled_color(3, 3, Color(0.5, 0.5, 0.5));
+        c.write_pixel(1, 1, Color::white());
+
+        c.denoise(1, 0.0);
+
+        assert_eq!(c.pixel_at(1, 1), Color::white());
+        assert_eq!(c.pixel_at(0, 1), Color(0.5, 0.5, 0.5));
+    }
+
     #[test]
     fn constructing_ppm_header() {
         let c = Canvas::new(5, 3);
@@ -164,6 +723,47 @@ mod tests {
         assert_eq!(ppm, String::from(expected_literal));
     }
 
+    #[test]
+    fn ppm_lines_never_exceed_70_characters() {
+        for width in [1, 6, 7, 20, 33] {
+            let c = Canvas::with_filled_color(width, 2, Color::white());
+
+            let ppm = c.construct_ppm_body();
+
+            assert!(ppm.lines().all(|line| line.len() <= 70));
+            assert!(ppm.lines().all(|line| !line.starts_with(' ')));
+            assert_eq!(ppm.split_whitespace().count(), width * 2 * 3);
+            assert!(ppm.split_whitespace().all(|v| v == "255"));
+        }
+    }
+
+    #[test]
+    fn dithering_breaks_up_flat_bands_in_ppm() {
+        // A gradient narrower than one 8 bit level, which rounds to 128 everywhere
+        let mut c = Canvas::new(16, 1);
+        for x in 0..16 {
+            c.write_pixel(x, 0, Color::white() * (0.5 + 0.002 * x as f64 / 15.0));
+        }
+        let red_values = |c: &Canvas| -> Vec<String> {
+            c.construct_ppm_body()
+                .split_whitespace()
+                .step_by(3)
+                .map(String::from)
+                .collect()
+        };
+
+        let flat = red_values(&c);
+        assert!(flat.iter().all(|v| v == "128"));
+
+        for dither in [Dither::Ordered, Dither::BlueNoise] {
+            c.set_ppm_dither(Some(dither));
+            let dithered = red_values(&c);
+
+            assert!(dithered.windows(2).any(|w| w[0] != w[1]));
+            assert!(dithered.iter().all(|v| v == "127" || v == "128"));
+        }
+    }
+
     #[test]
     fn splitting_long_lines() {
         let background_color = Color(1.0, 0.8, 0.6);
@@ -11,6 +11,49 @@ impl Color {
     pub fn white() -> Self {
         Color(1.0, 1.0, 1.0)
     }
+
+    // Whether every channel is too small to show, so adding the color or
+    // tracing rays to find it would be wasted work
+    pub fn is_black(&self) -> bool {
+        self.channels().iter().all(|c| c.abs() < EPSILON)
+    }
+
+    // Red, green and blue in that order
+    pub fn channels(&self) -> [f64; 3] {
+        [self.0, self.1, self.2]
+    }
+
+    // Perceived brightness using the Rec. 709 channel weights
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.0 + 0.7152 * self.1 + 0.0722 * self.2
+    }
+
+    // Brings channels above 1 back into range by blending towards the gray
+    // of the same luminance, so overexposed highlights wash out towards
+    // white instead of clipping into a more saturated hue. Colors brighter
+    // than white become white.
+    pub fn desaturate_overflow(self) -> Self {
+        let max = self.0.max(self.1).max(self.2);
+        if max <= 1.0 {
+            return self;
+        }
+
+        let luminance = self.luminance();
+        if luminance >= 1.0 {
+            return Color::white();
+        }
+
+        let gray = Color(luminance, luminance, luminance);
+        let scale = (1.0 - luminance) / (max - luminance);
+
+        gray + (self - gray) * scale
+    }
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Color::black()
+    }
 }
 
 impl PartialEq for Color {
@@ -57,6 +100,33 @@ impl std::ops::Mul for Color {
 mod tests {
     use super::*;
 
+    #[test]
+    fn desaturating_overflow_keeps_luminance() {
+        let c = Color(1.4, 0.2, 0.2);
+        let clamped = Color(1.0, 0.2, 0.2);
+
+        let d = c.desaturate_overflow();
+
+        assert!((d.luminance() - c.luminance()).abs() < EPSILON);
+        assert!((d.0 - 1.0).abs() < EPSILON);
+        // Lighter and less purely red than clamping each channel
+        assert!(d.luminance() > clamped.luminance());
+        assert!(d.1 > clamped.1 && d.2 > clamped.2);
+
+        assert_eq!(
+            Color(0.5, 0.2, 1.0).desaturate_overflow(),
+            Color(0.5, 0.2, 1.0)
+        );
+        assert_eq!(Color(3.0, 2.0, 2.0).desaturate_overflow(), Color::white());
+    }
+
+    #[test]
+    fn black_within_epsilon() {
+        assert!(Color::black().is_black());
+        assert!(Color(1e-9, 0.0, 0.0).is_black());
+        assert!(!Color(0.001, 0.0, 0.0).is_black());
+    }
+
     #[test]
     fn adding_colors() {
         let c1 = Color(0.9, 0.6, 0.75);
@@ -0,0 +1,175 @@
+use std::{collections::HashMap, f64::consts::PI, str::FromStr};
+
+use crate::{camera::Camera, constants::MAX_REFLECTION_DEPTH, error::Error, world::World};
+
+// Render settings that would otherwise be hard coded in each experiment
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderConfig {
+    pub width: usize,
+    pub height: usize,
+    // Field of view in radians
+    pub fov: f64,
+    // Path the rendered PPM is written to
+    pub output: String,
+    pub reflection_depth: usize,
+    // Supersamples per pixel along each axis, see Camera::samples
+    pub samples: usize,
+}
+
+impl RenderConfig {
+    pub fn new() -> Self {
+        Self {
+            width: 100,
+            height: 50,
+            fov: PI / 3.0,
+            output: String::from("image.ppm"),
+            reflection_depth: MAX_REFLECTION_DEPTH,
+            samples: 1,
+        }
+    }
+
+    // Builds a config from key/value pairs like the ones a command line
+    // would give, using the defaults for any missing keys
+    pub fn from_map(map: &HashMap<String, String>) -> Result<Self, Error> {
+        let mut config = RenderConfig::new();
+
+        for (key, value) in map {
+            match key.as_str() {
+                "width" => config.width = parse_value(key, value)?,
+                "height" => config.height = parse_value(key, value)?,
+                "fov" => config.fov = parse_value(key, value)?,
+                "output" => config.output = value.clone(),
+                "reflection_depth" => config.reflection_depth = parse_value(key, value)?,
+                "samples" => config.samples = parse_value(key, value)?,
+                _ => return Err(Error::UnknownConfigKey(key.clone())),
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn camera(&self) -> Camera {
+        let mut camera = Camera::new(self.width, self.height, self.fov);
+        camera.reflection_depth = self.reflection_depth;
+        camera.samples = self.samples;
+
+        camera
+    }
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str) -> Result<T, Error> {
+    value.parse().map_err(|_| Error::InvalidConfigValue {
+        key: key.to_string(),
+        value: value.to_string(),
+    })
+}
+
+// Renders the world produced by world_builder and saves it to the configured
+// output. The builder also gets the camera so it can point it at the scene.
+pub fn run(
+    config: &RenderConfig,
+    world_builder: impl FnOnce(&mut Camera) -> World,
+) -> Result<(), Error> {
+    let mut camera = config.camera();
+    let world = world_builder(&mut camera);
+
+    camera.render(&world).to_ppm(&config.output)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        transformation::view_transform,
+        tuples::{Point, Vector},
+    };
+
+    use super::*;
+
+    fn map(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn building_camera_from_config_map() {
+        let config = RenderConfig::from_map(&map(&[
+            ("width", "320"),
+            ("height", "240"),
+            ("fov", "0.5"),
+            ("samples", "2"),
+        ]))
+        .unwrap();
+
+        let camera = config.camera();
+
+        assert_eq!(camera.hsize(), 320);
+        assert_eq!(camera.vsize(), 240);
+        assert_eq!(camera.field_of_view(), 0.5);
+        assert_eq!(camera.samples, 2);
+        assert_eq!(camera.reflection_depth, MAX_REFLECTION_DEPTH);
+        assert_eq!(config.output, "image.ppm");
+    }
+
+    #[test]
+    fn invalid_config_values_are_errors() {
+        assert_eq!(
+            RenderConfig::from_map(&map(&[("width", "wide")])).unwrap_err(),
+            Error::InvalidConfigValue {
+                key: String::from("width"),
+                value: String::from("wide"),
+            }
+        );
+        assert_eq!(
+            RenderConfig::from_map(&map(&[("colour", "red")])).unwrap_err(),
+            Error::UnknownConfigKey(String::from("colour"))
+        );
+    }
+
+    #[test]
+    fn running_config_writes_output() {
+        // Unique to this process, so concurrent test runs don't share it
+        let path =
+            std::env::temp_dir().join(format!("ray_tracer_config_test_{}.ppm", std::process::id()));
+        let config = RenderConfig::from_map(&map(&[
+            ("width", "4"),
+            ("height", "3"),
+            ("output", &path.to_string_lossy()),
+        ]))
+        .unwrap();
+
+        run(&config, |camera| {
+            camera.transform = view_transform(
+                Point::new(0.0, 0.0, -5.0),
+                Point::origin(),
+                Vector::new(0.0, 1.0, 0.0),
+            );
+            World::default()
+        })
+        .unwrap();
+
+        let ppm = fs::read_to_string(&path).unwrap();
+        assert!(ppm.starts_with("P3\n4 3\n"));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn failing_to_write_output_is_an_error() {
+        let config = RenderConfig::from_map(&map(&[
+            ("width", "2"),
+            ("height", "2"),
+            ("output", "/nonexistent/ray_tracer/image.ppm"),
+        ]))
+        .unwrap();
+
+        let result = run(&config, |_| World::default());
+
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+}
@@ -0,0 +1,54 @@
+use std::fmt::{self, Display};
+
+// Errors from building geometry or render settings out of data that didn't
+// come from the tracer itself, like a parsed scene file, and from saving the
+// results
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    EmptyMatrix,
+    // Rows with different numbers of columns
+    RaggedMatrix,
+    // Determinant is zero
+    NonInvertibleMatrix,
+    // A tuple needs exactly 4 components, holds the number received
+    InvalidTupleLength(usize),
+    // Ray origin wasn't a point (w = 1) or direction wasn't a vector (w = 0)
+    InvalidRay,
+    // Render config key that RenderConfig doesn't know about
+    UnknownConfigKey(String),
+    // Render config value that couldn't be parsed, with its key
+    InvalidConfigValue { key: String, value: String },
+    // Reading or writing a file failed, holds the reason. io::Error can't be
+    // cloned or compared, so only its message is kept.
+    Io(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::EmptyMatrix => write!(f, "A matrix needs at least one row and column."),
+            Error::RaggedMatrix => write!(f, "The number of columns should equal in all rows."),
+            Error::NonInvertibleMatrix => write!(f, "The matrix is not invertible."),
+            Error::InvalidTupleLength(len) => {
+                write!(f, "Invalid length of vector received: {}", len)
+            }
+            Error::InvalidRay => write!(
+                f,
+                "The origin of ray should be a point and direction should be a vector."
+            ),
+            Error::UnknownConfigKey(key) => write!(f, "Unknown render config key: {}", key),
+            Error::InvalidConfigValue { key, value } => {
+                write!(f, "Invalid value for render config key {}: {}", key, value)
+            }
+            Error::Io(reason) => write!(f, "File access failed: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e.to_string())
+    }
+}
@@ -1,7 +1,7 @@
 use std::{error::Error, f64::consts::PI};
 
 use crate::{
-    canvas::Canvas,
+    canvas::{Canvas, Origin},
     color::Color,
     tuples::{Point, Tuple},
 };
@@ -12,6 +12,7 @@ const FULL_LENGTH: f64 = 100.0;
 
 pub fn draw_clock() -> Result<(), Box<dyn Error>> {
     let mut canvas = Canvas::new(FULL_LENGTH as usize, FULL_LENGTH as usize);
+    canvas.set_origin(Origin::BottomLeft);
     let twelve_oclock = Point::new(0.0, 0.0, ARM_LENGTH);
     draw_point(&mut canvas, &twelve_oclock);
 
@@ -27,7 +28,7 @@ pub fn draw_clock() -> Result<(), Box<dyn Error>> {
 fn draw_point(canvas: &mut Canvas, point: &Point) {
     canvas.write_pixel(
         (MID_POINT + point.2) as usize,
-        (MID_POINT - point.0) as usize,
+        (MID_POINT + point.0) as usize,
         Color::white(),
     );
 }
@@ -76,7 +76,11 @@ pub fn draw_chapter_10_first_page() -> Result<(), Box<dyn Error>> {
             Box::new(big_sphere),
             Box::new(small_sphere),
         ],
-        light: PointLight::new(Point::new(-10.0, 10.0, -10.0), Color(0.5, 0.5, 0.5)),
+        lights: vec![Box::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color(0.5, 0.5, 0.5),
+        ))],
+        ..World::new()
     };
 
     let mut camera = Camera::new(150, 75, PI / 3.0);
@@ -86,7 +90,7 @@ pub fn draw_chapter_10_first_page() -> Result<(), Box<dyn Error>> {
         Vector::new(0.0, 1.0, 0.0),
     );
 
-    let canvas = camera.render(world);
+    let canvas = camera.render(&world);
     canvas.to_ppm("images/chapter_10_first_page.ppm")?;
 
     Ok(())
@@ -100,7 +104,11 @@ pub fn radial_gradient_floor() -> Result<(), Box<dyn Error>> {
 
     let world = World {
         objects: vec![Box::new(floor)],
-        light: PointLight::new(Point::new(-10.0, 10.0, -10.0), Color(1.0, 1.0, 1.0)),
+        lights: vec![Box::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color(1.0, 1.0, 1.0),
+        ))],
+        ..World::new()
     };
 
     let mut camera = Camera::new(150, 75, PI / 3.0);
@@ -110,7 +118,7 @@ pub fn radial_gradient_floor() -> Result<(), Box<dyn Error>> {
         Vector::new(0.0, 1.0, 0.0),
     );
 
-    let canvas = camera.render(world);
+    let canvas = camera.render(&world);
     canvas.to_ppm("images/radial_gradient_floor.ppm")?;
 
     Ok(())
@@ -137,7 +145,11 @@ pub fn nested_pattern_floor() -> Result<(), Box<dyn Error>> {
 
     let world = World {
         objects: vec![Box::new(floor)],
-        light: PointLight::new(Point::new(-10.0, 10.0, -10.0), Color(1.0, 1.0, 1.0)),
+        lights: vec![Box::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color(1.0, 1.0, 1.0),
+        ))],
+        ..World::new()
     };
 
     let mut camera = Camera::new(150, 75, PI / 3.0);
@@ -147,7 +159,7 @@ pub fn nested_pattern_floor() -> Result<(), Box<dyn Error>> {
         Vector::new(0.0, 1.0, 0.0),
     );
 
-    let canvas = camera.render(world);
+    let canvas = camera.render(&world);
     canvas.to_ppm("images/nested_pattern_floor.ppm")?;
 
     Ok(())
@@ -175,7 +187,11 @@ pub fn blended_pattern_floor() -> Result<(), Box<dyn Error>> {
 
     let world = World {
         objects: vec![Box::new(floor)],
-        light: PointLight::new(Point::new(-10.0, 10.0, -10.0), Color(1.0, 1.0, 1.0)),
+        lights: vec![Box::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color(1.0, 1.0, 1.0),
+        ))],
+        ..World::new()
     };
 
     let mut camera = Camera::new(150, 75, PI / 3.0);
@@ -185,8 +201,56 @@ pub fn blended_pattern_floor() -> Result<(), Box<dyn Error>> {
         Vector::new(0.0, 1.0, 0.0),
     );
 
-    let canvas = camera.render(world);
+    let canvas = camera.render(&world);
     canvas.to_ppm("images/blended_pattern_floor.ppm")?;
 
     Ok(())
 }
+
+// Checks that pattern and object transforms stack correctly: the checkers
+// should follow the sphere's scale and tilt rather than stay aligned with
+// the world axes
+pub fn transformed_checkered_sphere() -> Result<(), Box<dyn Error>> {
+    let mut floor = Plane::new();
+    let mut floor_material = Material::new();
+    floor_material.pattern = Box::new(Solid::new(Color(0.8, 0.8, 0.8)));
+    floor.set_material(floor_material);
+
+    let mut checker = Checker::new(
+        Box::new(Solid::new(Color(0.9, 0.2, 0.2))),
+        Box::new(Solid::new(Color::white())),
+    );
+    checker.set_transformation(scaling(0.25, 0.25, 0.25) * rotation_y(PI / 8.0));
+
+    let mut sphere = Sphere::new();
+    let mut sphere_material = Material::new();
+    sphere_material.pattern = Box::new(checker);
+    sphere.set_material(sphere_material);
+    sphere.set_transformation(
+        translation(0.0, 1.0, 0.0)
+            * rotation_z(PI / 6.0)
+            * rotation_x(-PI / 5.0)
+            * scaling(1.5, 0.75, 0.75),
+    );
+
+    let world = World {
+        objects: vec![Box::new(floor), Box::new(sphere)],
+        lights: vec![Box::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color(1.0, 1.0, 1.0),
+        ))],
+        ..World::new()
+    };
+
+    let mut camera = Camera::new(150, 75, PI / 3.0);
+    camera.transform = view_transform(
+        Point::new(0.0, 1.5, -5.0),
+        Point::new(0.0, 1.0, 0.0),
+        Vector::new(0.0, 1.0, 0.0),
+    );
+
+    let canvas = camera.render(&world);
+    canvas.to_ppm("images/transformed_checkered_sphere.ppm")?;
+
+    Ok(())
+}
@@ -1,7 +1,11 @@
 use std::error::Error;
 
 use crate::tuples::{Point, Vector};
-use crate::{canvas::Canvas, color::Color, tuples};
+use crate::{
+    canvas::{Canvas, Origin},
+    color::Color,
+    tuples,
+};
 
 pub struct Projectile {
     pub position: Point,
@@ -25,7 +29,7 @@ pub fn tick_until_fallen(canvas: &mut Canvas, env: &Environment, proj: &mut Proj
         tick(env, proj);
         canvas.write_pixel(
             proj.position.0 as usize,
-            canvas.height - proj.position.1 as usize,
+            proj.position.1 as usize,
             Color::white(),
         );
         height = proj.position.1;
@@ -45,6 +49,7 @@ pub fn draw_projectile() -> Result<(), Box<dyn Error>> {
     let e = Environment { gravity, wind };
 
     let mut c = Canvas::new(900, 550);
+    c.set_origin(Origin::BottomLeft);
 
     tick_until_fallen(&mut c, &e, &mut p);
 
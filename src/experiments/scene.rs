@@ -69,7 +69,11 @@ pub fn draw_scene() -> Result<(), Box<dyn Error>> {
             Box::new(right),
             Box::new(left),
         ],
-        light: PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::white()),
+        lights: vec![Box::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color::white(),
+        ))],
+        ..World::new()
     };
 
     let mut camera = Camera::new(100, 50, PI / 3.0);
@@ -79,7 +83,7 @@ pub fn draw_scene() -> Result<(), Box<dyn Error>> {
         Vector::new(0.0, 1.0, 0.0),
     );
 
-    let canvas = camera.render(world);
+    let canvas = camera.render(&world);
     canvas.to_ppm("images/scene.ppm")?;
 
     Ok(())
@@ -117,7 +121,11 @@ pub fn draw_scene_with_plane() -> Result<(), Box<dyn Error>> {
             Box::new(right),
             Box::new(left),
         ],
-        light: PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::white()),
+        lights: vec![Box::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color::white(),
+        ))],
+        ..World::new()
     };
 
     let mut camera = Camera::new(100, 50, PI / 3.0);
@@ -127,7 +135,7 @@ pub fn draw_scene_with_plane() -> Result<(), Box<dyn Error>> {
         Vector::new(0.0, 1.0, 0.0),
     );
 
-    let canvas = camera.render(world);
+    let canvas = camera.render_parallel(&world);
     canvas.to_ppm("images/scene_with_plane.ppm")?;
 
     Ok(())
@@ -45,10 +45,9 @@ pub fn draw_sphere() -> Result<(), Box<dyn Error>> {
             let xs = sphere.intersect(r);
 
             let hit = hit(xs);
-            if hit.is_some() {
-                let hit = hit.unwrap();
+            if let Some(hit) = hit {
                 let point = r.position(hit.t);
-                let normal = hit.object.normal_at(point);
+                let normal = hit.object.normal_at_uv(point, hit.uv);
                 let eye = -r.direction;
 
                 let color = hit
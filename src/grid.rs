@@ -0,0 +1,284 @@
+use crate::{bounds::BoundingBox, constants::EPSILON, rays::Ray, shapes::Shape};
+
+// Uniform spatial grid that buckets objects by their bounds so a ray only
+// has to be tested against the objects in the cells it passes through.
+// The grid stores indices into the object list it was built from, so it has
+// to be rebuilt whenever that list changes.
+#[derive(Debug)]
+pub struct UniformGrid {
+    bounds: BoundingBox,
+    resolution: usize,
+    cells: Vec<Vec<usize>>,
+    // Objects without finite bounds (like planes) are tested for every ray
+    unbounded: Vec<usize>,
+    object_count: usize,
+}
+
+impl UniformGrid {
+    pub fn new(objects: &[Box<dyn Shape>], resolution: usize) -> Self {
+        let resolution = resolution.max(1);
+
+        let mut bounds = BoundingBox::empty();
+        let mut unbounded = Vec::new();
+        let mut object_bounds = Vec::with_capacity(objects.len());
+
+        for (i, object) in objects.iter().enumerate() {
+            let b = object.parent_space_bounds();
+            if b.is_finite() {
+                bounds.add_box(&b);
+                object_bounds.push(Some(b));
+            } else {
+                unbounded.push(i);
+                object_bounds.push(None);
+            }
+        }
+
+        let mut grid = UniformGrid {
+            bounds,
+            resolution,
+            cells: vec![Vec::new(); resolution.pow(3)],
+            unbounded,
+            object_count: objects.len(),
+        };
+
+        for (i, b) in object_bounds.iter().enumerate() {
+            if let Some(b) = b {
+                let (x0, y0, z0) = grid.cell_of(b.min.0, b.min.1, b.min.2);
+                let (x1, y1, z1) = grid.cell_of(b.max.0, b.max.1, b.max.2);
+
+                for x in x0..=x1 {
+                    for y in y0..=y1 {
+                        for z in z0..=z1 {
+                            let idx = grid.index(x, y, z);
+                            grid.cells[idx].push(i);
+                        }
+                    }
+                }
+            }
+        }
+
+        grid
+    }
+
+    // Length of the object list the grid was built from
+    pub fn object_count(&self) -> usize {
+        self.object_count
+    }
+
+    // Indices of the objects that may be hit by the ray, found by walking the
+    // cells the ray passes through with a 3D DDA
+    pub fn candidates(&self, ray: Ray) -> Vec<usize> {
+        let mut seen = vec![false; self.object_count];
+        let mut candidates = Vec::new();
+
+        for &i in &self.unbounded {
+            seen[i] = true;
+            candidates.push(i);
+        }
+
+        if self.bounds.is_empty() {
+            return candidates;
+        }
+
+        let (tmin, tmax) = match self.bounds.intersect(ray) {
+            Some(range) => range,
+            None => return candidates,
+        };
+        if tmax < 0.0 {
+            return candidates;
+        }
+
+        let t_start = tmin.max(0.0);
+        let start = ray.position(t_start);
+        let size = self.cell_size();
+
+        let origin = [ray.origin.0, ray.origin.1, ray.origin.2];
+        let direction = [ray.direction.0, ray.direction.1, ray.direction.2];
+        let grid_min = [self.bounds.min.0, self.bounds.min.1, self.bounds.min.2];
+
+        let (cx, cy, cz) = self.cell_of(start.0, start.1, start.2);
+        let mut cell = [cx as isize, cy as isize, cz as isize];
+
+        let mut step = [0_isize; 3];
+        let mut t_max = [f64::INFINITY; 3];
+        let mut t_delta = [f64::INFINITY; 3];
+
+        for axis in 0..3 {
+            if direction[axis].abs() < EPSILON {
+                continue;
+            }
+
+            // The t at which the ray crosses the next cell boundary on this axis
+            let next_boundary = if direction[axis] > 0.0 {
+                step[axis] = 1;
+                grid_min[axis] + (cell[axis] + 1) as f64 * size[axis]
+            } else {
+                step[axis] = -1;
+                grid_min[axis] + cell[axis] as f64 * size[axis]
+            };
+
+            t_max[axis] = (next_boundary - origin[axis]) / direction[axis];
+            t_delta[axis] = size[axis] / direction[axis].abs();
+        }
+
+        let n = self.resolution as isize;
+        loop {
+            let idx = self.index(cell[0] as usize, cell[1] as usize, cell[2] as usize);
+            for &i in &self.cells[idx] {
+                if !seen[i] {
+                    seen[i] = true;
+                    candidates.push(i);
+                }
+            }
+
+            // Step along the axis whose boundary is crossed first
+            let axis = if t_max[0] < t_max[1] && t_max[0] < t_max[2] {
+                0
+            } else if t_max[1] < t_max[2] {
+                1
+            } else {
+                2
+            };
+
+            if t_max[axis] > tmax {
+                break;
+            }
+
+            cell[axis] += step[axis];
+            if cell[axis] < 0 || cell[axis] >= n {
+                break;
+            }
+            t_max[axis] += t_delta[axis];
+        }
+
+        candidates
+    }
+
+    fn cell_size(&self) -> [f64; 3] {
+        let extent = self.bounds.extent();
+        let n = self.resolution as f64;
+
+        // A flat grid still needs cells with some thickness to walk through
+        [
+            extent.0.max(EPSILON) / n,
+            extent.1.max(EPSILON) / n,
+            extent.2.max(EPSILON) / n,
+        ]
+    }
+
+    fn cell_of(&self, x: f64, y: f64, z: f64) -> (usize, usize, usize) {
+        let size = self.cell_size();
+        let clamp = |v: f64, min: f64, size: f64| {
+            (((v - min) / size).floor().max(0.0) as usize).min(self.resolution - 1)
+        };
+
+        (
+            clamp(x, self.bounds.min.0, size[0]),
+            clamp(y, self.bounds.min.1, size[1]),
+            clamp(z, self.bounds.min.2, size[2]),
+        )
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (x * self.resolution + y) * self.resolution + z
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        intersection::hit,
+        shapes::{plane::Plane, sphere::Sphere},
+        transformation::{scaling, translation},
+        tuples::{Point, Vector},
+        world::World,
+    };
+
+    use super::*;
+
+    fn scattered_spheres() -> World {
+        let mut w = World::default();
+        w.objects.clear();
+
+        for i in 0..4 {
+            for j in 0..4 {
+                for k in 0..4 {
+                    let mut s = Sphere::new();
+                    let offset = ((i * 7 + j * 3 + k) % 5) as f64 * 0.1;
+                    s.transformation = translation(
+                        i as f64 * 3.0 - 4.5 + offset,
+                        j as f64 * 3.0 - 4.5 - offset,
+                        k as f64 * 3.0 - 4.5,
+                    ) * scaling(0.8, 0.8, 0.8);
+                    w.objects.push(Box::new(s));
+                }
+            }
+        }
+
+        w
+    }
+
+    fn probe_rays() -> Vec<Ray> {
+        vec![
+            Ray::new(Point::new(0.0, 0.0, -20.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(-1.5, -1.5, -20.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(
+                Point::new(-20.0, -18.0, -16.0),
+                Vector::new(1.0, 1.0, 1.0).normalize(),
+            ),
+            Ray::new(
+                Point::new(10.0, 3.0, -10.0),
+                Vector::new(-1.0, -0.2, 0.9).normalize(),
+            ),
+            Ray::new(
+                Point::new(1.5, 1.6, 1.5),
+                Vector::new(0.3, -1.0, 0.1).normalize(),
+            ),
+            Ray::new(Point::new(0.0, 30.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+        ]
+    }
+
+    #[test]
+    fn grid_hits_match_brute_force() {
+        let brute_force = scattered_spheres();
+        let mut accelerated = scattered_spheres();
+        accelerated.build_grid(4);
+
+        for r in probe_rays() {
+            let expected = hit(brute_force.intersect(r));
+            let actual = hit(accelerated.intersect(r));
+
+            match (expected, actual) {
+                (Some(expected), Some(actual)) => {
+                    assert_eq!(expected.t, actual.t);
+                    assert!(expected.object.equals(actual.object));
+                }
+                (None, None) => {}
+                (expected, actual) => panic!("{:?} != {:?} for {:?}", expected, actual, r),
+            }
+        }
+    }
+
+    #[test]
+    fn grid_skips_objects_away_from_ray() {
+        let w = scattered_spheres();
+        let grid = UniformGrid::new(&w.objects, 4);
+
+        let r = Ray::new(Point::new(-4.5, -4.5, -20.0), Vector::new(0.0, 0.0, 1.0));
+        let candidates = grid.candidates(r);
+
+        // Only the column of spheres along the ray should be considered
+        assert!(!candidates.is_empty());
+        assert!(candidates.len() < w.objects.len() / 4);
+    }
+
+    #[test]
+    fn unbounded_objects_are_always_candidates() {
+        let objects: Vec<Box<dyn Shape>> = vec![Box::new(Sphere::new()), Box::new(Plane::new())];
+        let grid = UniformGrid::new(&objects, 2);
+
+        let r = Ray::new(Point::new(5.0, 5.0, 5.0), Vector::new(0.0, 1.0, 0.0));
+
+        assert_eq!(grid.candidates(r), vec![1]);
+    }
+}
@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::{cmp::Ordering, rc::Rc};
 
 use crate::{
     constants::EPSILON,
@@ -7,6 +7,7 @@ use crate::{
     tuples::{Point, Vector},
 };
 
+#[derive(Debug, Clone, Copy)]
 pub struct Computations<'a> {
     pub t: f64,
     pub object: &'a dyn Shape,
@@ -14,29 +15,105 @@ pub struct Computations<'a> {
     pub eyev: Vector,
     pub normalv: Vector,
     pub reflectv: Vector,
-    inside: bool,
+    // Whether the hit is on the inside of the surface, i.e. the ray is
+    // leaving the object. The normal is flipped to face the eye in that case.
+    pub inside: bool,
+    // The hit nudged just above and just below the surface along the normal,
+    // for rays leaving the surface on either side without hitting it again
     pub over_point: Point,
+    pub under_point: Point,
+    pub part: Option<Part>,
+    // Refractive indices of the materials the ray is leaving and entering
+    // at the hit
+    pub n1: f64,
+    pub n2: f64,
+}
+
+// The feature of a shape that was hit, for shapes built from several
+// surfaces (like the side and caps of a closed cylinder)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Part {
+    Side,
+    Cap,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Intersection<'a> {
     pub t: f64,
     pub object: &'a dyn Shape,
+    pub part: Option<Part>,
+    // Barycentric coordinates of the hit, for shapes that interpolate
+    // across their surface like SmoothTriangle
+    pub uv: Option<(f64, f64)>,
 }
 
 impl<'a> Intersection<'a> {
     pub fn new(t: f64, object: &'a dyn Shape) -> Self {
-        Intersection { t, object }
+        Intersection {
+            t,
+            object,
+            part: None,
+            uv: None,
+        }
+    }
+
+    pub fn with_part(t: f64, object: &'a dyn Shape, part: Part) -> Self {
+        Intersection {
+            t,
+            object,
+            part: Some(part),
+            uv: None,
+        }
+    }
+
+    pub fn with_uv(t: f64, object: &'a dyn Shape, u: f64, v: f64) -> Self {
+        Intersection {
+            t,
+            object,
+            part: None,
+            uv: Some((u, v)),
+        }
     }
 
     fn equals(&self, other: &Intersection) -> bool {
         self.t == other.t && self.object.equals(other.object)
     }
 
+    // Computations for a hit that is the only intersection along the ray,
+    // so it is taken to be surrounded by vacuum
     pub fn prepare_computations(&self, ray: Ray) -> Computations<'a> {
+        let mut comps = self.prepare_computations_with_hits(ray, &[*self]);
+
+        // On its own the hit looks like the ray entering the object, but from
+        // the inside it is leaving it
+        if comps.inside {
+            (comps.n1, comps.n2) = (comps.n2, comps.n1);
+        }
+
+        comps
+    }
+
+    // Computations for the hit among every intersection along the ray, sorted
+    // by t, which tell it what it is nested inside for n1 and n2
+    pub fn prepare_computations_with_hits(
+        &self,
+        ray: Ray,
+        xs: &[Intersection<'a>],
+    ) -> Computations<'a> {
+        self.prepare_computations_with_offset(ray, xs, 1.0)
+    }
+
+    // Like prepare_computations_with_hits, with over_point and under_point
+    // offset from the surface by offset_scale times EPSILON
+    pub fn prepare_computations_with_offset(
+        &self,
+        ray: Ray,
+        xs: &[Intersection<'a>],
+        offset_scale: f64,
+    ) -> Computations<'a> {
         let point = ray.position(self.t);
         let eyev = -ray.direction;
-        let mut normalv = self.object.normal_at(point);
+        let mut normalv = self.object.normal_at_uv(point, self.uv);
         let inside: bool;
 
         if normalv.dot(&eyev) < 0.0 {
@@ -48,9 +125,12 @@ impl<'a> Intersection<'a> {
 
         // Bump the point just a bit to make sure the intersection does not hide
         // behind the surface due to floating number errors
-        let over_point = point + normalv * EPSILON;
+        let offset = normalv * EPSILON * offset_scale;
+        let over_point = point + offset;
+        let under_point = point - offset;
 
         let reflectv = ray.direction.reflect(normalv);
+        let (n1, n2) = self.refractive_indices(xs);
 
         Computations {
             t: self.t,
@@ -61,22 +141,69 @@ impl<'a> Intersection<'a> {
             reflectv,
             inside,
             over_point,
+            under_point,
+            part: self.part,
+            n1,
+            n2,
         }
     }
+
+    // Walks the intersections up to this one keeping track of which objects
+    // the ray is inside, to find the refractive index on either side of the
+    // hit. Space outside of every object is a vacuum.
+    fn refractive_indices(&self, xs: &[Intersection<'a>]) -> (f64, f64) {
+        let mut containers: Vec<&dyn Shape> = Vec::new();
+        let last_index = |containers: &Vec<&dyn Shape>| {
+            containers
+                .last()
+                .map_or(1.0, |object| object.material().refractive_index)
+        };
+
+        for i in xs {
+            let is_hit = i.t == self.t && std::ptr::addr_eq(i.object, self.object);
+            let n1 = last_index(&containers);
+
+            match containers
+                .iter()
+                .position(|&object| std::ptr::addr_eq(object, i.object))
+            {
+                Some(index) => {
+                    containers.remove(index);
+                }
+                None => containers.push(i.object),
+            }
+
+            if is_hit {
+                return (n1, last_index(&containers));
+            }
+        }
+
+        (1.0, 1.0)
+    }
 }
 
-pub fn hit(intersections: Vec<Intersection>) -> Option<Intersection> {
-    let lowest_non_negative_t = intersections
+// NaN t values (from degenerate rays or transforms) are never hits
+pub fn hit(intersections: Vec<Intersection<'_>>) -> Option<Intersection<'_>> {
+    intersections
         .into_iter()
         .filter(|int| int.t >= 0.0)
-        .min_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        .min_by(compare_t)
+}
 
-    lowest_non_negative_t
+// Orders intersections by t, with NaN after every other value
+pub fn compare_t(a: &Intersection<'_>, b: &Intersection<'_>) -> Ordering {
+    a.t.is_nan()
+        .cmp(&b.t.is_nan())
+        .then(a.t.partial_cmp(&b.t).unwrap_or(Ordering::Equal))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{constants::EPSILON, shapes::plane::Plane, transformation::translation};
+    use crate::{
+        constants::EPSILON,
+        shapes::plane::Plane,
+        transformation::{scaling, translation},
+    };
 
     use super::*;
 
@@ -125,6 +252,36 @@ mod tests {
         assert!(i.is_none());
     }
 
+    #[test]
+    fn hit_skips_nan_intersections() {
+        let s = Sphere::new();
+        let i1 = Intersection::new(f64::NAN, &s);
+        let i2 = Intersection::new(3.0, &s);
+        let i3 = Intersection::new(-1.0, &s);
+        let i4 = Intersection::new(2.0, &s);
+
+        let i = hit(vec![i1, i2, i3, i4]).unwrap();
+
+        assert!(i.equals(&i4));
+        assert!(hit(vec![i1]).is_none());
+    }
+
+    #[test]
+    fn sorting_puts_nan_intersections_last() {
+        let s = Sphere::new();
+        let mut xs = [
+            Intersection::new(f64::NAN, &s),
+            Intersection::new(3.0, &s),
+            Intersection::new(-1.0, &s),
+        ];
+
+        xs.sort_by(compare_t);
+
+        assert_eq!(xs[0].t, -1.0);
+        assert_eq!(xs[1].t, 3.0);
+        assert!(xs[2].t.is_nan());
+    }
+
     #[test]
     fn hit_is_the_lowest_nonnegative_intersection() {
         let s = Sphere::new();
@@ -143,10 +300,7 @@ mod tests {
     fn precomputing_state_of_intersection() {
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let shape = Sphere::new();
-        let i = Intersection {
-            t: 4.0,
-            object: &shape,
-        };
+        let i = Intersection::new(4.0, &shape);
 
         let comps = i.prepare_computations(r);
 
@@ -157,6 +311,18 @@ mod tests {
         assert_eq!(comps.normalv, Vector::new(0.0, 0.0, -1.0));
     }
 
+    #[test]
+    fn precomputing_carries_the_part_that_was_hit() {
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let shape = Sphere::new();
+
+        let comps = Intersection::new(4.0, &shape).prepare_computations(r);
+        assert_eq!(comps.part, None);
+
+        let comps = Intersection::with_part(4.0, &shape, Part::Cap).prepare_computations(r);
+        assert_eq!(comps.part, Some(Part::Cap));
+    }
+
     #[test]
     fn precomputing_reflection_vector() {
         let shape = Plane::new();
@@ -178,24 +344,18 @@ mod tests {
     fn hit_when_intersection_occurs_outside() {
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let shape = Sphere::new();
-        let i = Intersection {
-            t: 4.0,
-            object: &shape,
-        };
+        let i = Intersection::new(4.0, &shape);
 
         let comps = i.prepare_computations(r);
 
-        assert_eq!(comps.inside, false);
+        assert!(!comps.inside);
     }
 
     #[test]
     fn hit_when_intersection_occurs_inside() {
         let r = Ray::new(Point::origin(), Vector::new(0.0, 0.0, 1.0));
         let shape = Sphere::new();
-        let i = Intersection {
-            t: 1.0,
-            object: &shape,
-        };
+        let i = Intersection::new(1.0, &shape);
 
         let comps = i.prepare_computations(r);
 
@@ -203,7 +363,36 @@ mod tests {
         assert_eq!(comps.eyev, Vector::new(0.0, 0.0, -1.0));
         // Normal is inverted
         assert_eq!(comps.normalv, Vector::new(0.0, 0.0, -1.0));
-        assert_eq!(comps.inside, true);
+        assert!(comps.inside);
+    }
+
+    #[test]
+    fn inside_flag_tracks_entering_and_exiting_glass_sphere() {
+        let shape = Sphere::glass();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let xs = [
+            Intersection::new(4.0, &shape),
+            Intersection::new(6.0, &shape),
+        ];
+
+        let entering = xs[0].prepare_computations_with_hits(r, &xs);
+        let exiting = xs[1].prepare_computations_with_hits(r, &xs);
+
+        assert!(!entering.inside);
+        assert_eq!(entering.normalv, Vector::new(0.0, 0.0, -1.0));
+        // Air to glass
+        assert_eq!((entering.n1, entering.n2), (1.0, 1.5));
+        assert!(exiting.inside);
+        // The flipped normal still faces back along the ray
+        assert_eq!(exiting.normalv, Vector::new(0.0, 0.0, -1.0));
+        // Glass to air
+        assert_eq!((exiting.n1, exiting.n2), (1.5, 1.0));
+
+        // A lone hit agrees with the full list on either side
+        let lone_exit = xs[1].prepare_computations(r);
+        assert!(lone_exit.inside);
+        assert_eq!((lone_exit.n1, lone_exit.n2), (1.5, 1.0));
     }
 
     #[test]
@@ -218,4 +407,107 @@ mod tests {
         assert!(comps.over_point.2 < -EPSILON / 2.0);
         assert!(comps.point.2 > comps.over_point.2);
     }
+
+    #[test]
+    fn under_point_is_offset_below_the_surface() {
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let mut shape = Sphere::new();
+        shape.set_transformation(translation(0.0, 0.0, 1.0));
+        let i = Intersection::new(5.0, &shape);
+
+        let comps = i.prepare_computations(r);
+
+        assert!(comps.under_point.2 > EPSILON / 2.0);
+        assert!(comps.point.2 < comps.under_point.2);
+    }
+
+    #[test]
+    fn offset_scale_moves_both_points_symmetrically() {
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let shape = Sphere::new();
+        let i = Intersection::new(4.0, &shape);
+
+        let near = i.prepare_computations(r);
+        let far = i.prepare_computations_with_offset(r, &[i], 10.0);
+
+        assert_eq!(far.point, near.point);
+        assert!(((far.point - far.over_point).magnitude() - 10.0 * EPSILON).abs() < 1e-12);
+        assert!(((far.under_point - far.point).magnitude() - 10.0 * EPSILON).abs() < 1e-12);
+        assert!(far.over_point.2 < near.over_point.2);
+        assert!(far.under_point.2 > near.under_point.2);
+    }
+
+    #[test]
+    fn finding_n1_and_n2_at_various_intersections() {
+        let mut a = Sphere::glass();
+        a.set_transformation(scaling(2.0, 2.0, 2.0));
+        a.material.refractive_index = 1.5;
+        let mut b = Sphere::glass();
+        b.set_transformation(translation(0.0, 0.0, -0.25));
+        b.material.refractive_index = 2.0;
+        let mut c = Sphere::glass();
+        c.set_transformation(translation(0.0, 0.0, 0.25));
+        c.material.refractive_index = 2.5;
+
+        let r = Ray::new(Point::new(0.0, 0.0, -4.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = [
+            Intersection::new(2.0, &a),
+            Intersection::new(2.75, &b),
+            Intersection::new(3.25, &c),
+            Intersection::new(4.75, &b),
+            Intersection::new(5.25, &c),
+            Intersection::new(6.0, &a),
+        ];
+        let expected = [
+            (1.0, 1.5),
+            (1.5, 2.0),
+            (2.0, 2.5),
+            (2.5, 2.5),
+            (2.5, 1.5),
+            (1.5, 1.0),
+        ];
+
+        for (i, (n1, n2)) in xs.iter().zip(expected) {
+            let comps = i.prepare_computations_with_hits(r, &xs);
+
+            assert_eq!(comps.n1, n1);
+            assert_eq!(comps.n2, n2);
+        }
+    }
+
+    #[test]
+    fn finding_n1_and_n2_where_two_spheres_touch() {
+        // b starts exactly where a ends, so the ray leaves a and enters b at
+        // the same t and only the objects tell those two hits apart
+        let a = Sphere::glass();
+        let mut b = Sphere::glass();
+        b.set_transformation(translation(0.0, 0.0, 2.0));
+        b.material.refractive_index = 2.0;
+
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = [
+            Intersection::new(4.0, &a),
+            Intersection::new(6.0, &a),
+            Intersection::new(6.0, &b),
+            Intersection::new(8.0, &b),
+        ];
+        let expected = [(1.0, 1.5), (1.5, 1.0), (1.0, 2.0), (2.0, 1.0)];
+
+        for (i, (n1, n2)) in xs.iter().zip(expected) {
+            let comps = i.prepare_computations_with_hits(r, &xs);
+
+            assert_eq!(comps.n1, n1);
+            assert_eq!(comps.n2, n2);
+        }
+    }
+
+    #[test]
+    fn lone_hit_refracts_between_vacuum_and_its_material() {
+        let shape = Sphere::glass();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let comps = Intersection::new(4.0, &shape).prepare_computations(r);
+
+        assert_eq!((comps.n1, comps.n2), (1.0, 1.5));
+    }
 }
@@ -1,9 +1,84 @@
-use crate::{color::Color, tuples::Point};
+use std::{any::Any, fmt::Debug};
 
-#[derive(Debug, PartialEq)]
+use crate::{
+    color::Color,
+    tuples::{Point, Vector},
+    utils::{seeded_random, StableHasher},
+    world::World,
+};
+
+pub trait Light: Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+
+    fn equals(&self, other: &dyn Light) -> bool;
+
+    fn clone_box(&self) -> Box<dyn Light>;
+
+    // Feeds the light's type and every field into h, for World::scene_hash
+    fn hash_into(&self, h: &mut StableHasher);
+
+    // Color and brightness of the light at its source
+    fn intensity(&self) -> Color;
+
+    // Center of the light
+    fn position(&self) -> Point;
+
+    // The light's color where it reaches point, after any falloff, ignoring
+    // anything in the way
+    fn intensity_towards(&self, _point: Point) -> Color {
+        self.intensity()
+    }
+
+    // Points on the light that shading averages over. Lights that move their
+    // samples around use inputs to pick them, and the same inputs always
+    // give the same points. Shading a point uses sample_inputs(point).
+    fn sample_points(&self, _inputs: &[f64]) -> Vec<Point> {
+        vec![self.position()]
+    }
+
+    // Fraction of the light that reaches point without anything in the way,
+    // which for lights with a single sample is either 0 or 1
+    fn intensity_at(&self, point: Point, world: &World) -> f64 {
+        world.visible_fraction(&self.sample_points(&sample_inputs(point)), point)
+    }
+}
+
+// Inputs that pick the samples of a light that shade and shadow point, see
+// Light::sample_points
+pub fn sample_inputs(point: Point) -> [f64; 3] {
+    [point.0, point.1, point.2]
+}
+
+impl Clone for Box<dyn Light> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+impl PartialEq for dyn Light {
+    fn eq(&self, other: &Self) -> bool {
+        self.equals(other)
+    }
+}
+
+// How a light's intensity drops off with distance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Falloff {
+    // Same brightness at any distance, like the book's lights
+    None,
+    // Inverse square law, with full intensity one unit from the light
+    Quadratic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct PointLight {
     pub position: Point,
     pub intensity: Color,
+    // Half the size of the cube the light is randomly moved around in for
+    // each shaded ray. Averaging several rays per pixel then softens shadow
+    // edges as if the light had a small area. 0 keeps the light fixed.
+    pub jitter_radius: f64,
+    pub falloff: Falloff,
 }
 
 impl PointLight {
@@ -11,12 +86,348 @@ impl PointLight {
         PointLight {
             position,
             intensity,
+            jitter_radius: 0.0,
+            falloff: Falloff::None,
+        }
+    }
+
+    // Luminance of intensity_towards, for previewing how far a light reaches
+    pub fn illuminance_at(&self, point: Point) -> f64 {
+        self.intensity_towards(point).luminance()
+    }
+
+    // Position of the light to use when shading with the given inputs. The
+    // same inputs always give the same position.
+    pub fn jittered_position(&self, inputs: &[f64]) -> Point {
+        if self.jitter_radius == 0.0 {
+            return self.position;
+        }
+
+        let offset = |axis: u64| (2.0 * seeded_random(axis, inputs) - 1.0) * self.jitter_radius;
+
+        self.position + Vector::new(offset(0), offset(1), offset(2))
+    }
+}
+
+impl Light for PointLight {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn equals(&self, other: &dyn Light) -> bool {
+        other.as_any().downcast_ref::<PointLight>() == Some(self)
+    }
+
+    fn clone_box(&self) -> Box<dyn Light> {
+        Box::new(*self)
+    }
+
+    fn hash_into(&self, h: &mut StableHasher) {
+        let (p, i) = (self.position, self.intensity);
+        h.write_str("PointLight");
+        h.write_f64s(&[p.0, p.1, p.2, i.0, i.1, i.2, self.jitter_radius]);
+        h.write_u64(self.falloff as u64);
+    }
+
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn position(&self) -> Point {
+        self.position
+    }
+
+    // After falloff
+    fn intensity_towards(&self, point: Point) -> Color {
+        match self.falloff {
+            Falloff::None => self.intensity,
+            Falloff::Quadratic => {
+                let d = self.position - point;
+                self.intensity * (1.0 / d.dot(&d))
+            }
+        }
+    }
+
+    fn sample_points(&self, inputs: &[f64]) -> Vec<Point> {
+        vec![self.jittered_position(inputs)]
+    }
+}
+
+// A point light that only shines inside a cone around direction. Points
+// within inner_angle of direction get the full intensity, which then fades
+// smoothly to nothing at outer_angle. Both angles are kept as cosines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpotLight {
+    pub position: Point,
+    pub direction: Vector,
+    pub intensity: Color,
+    pub cos_inner: f64,
+    pub cos_outer: f64,
+}
+
+impl SpotLight {
+    // The angles are in radians from direction, with inner_angle no larger
+    // than outer_angle
+    pub fn new(
+        position: Point,
+        direction: Vector,
+        intensity: Color,
+        inner_angle: f64,
+        outer_angle: f64,
+    ) -> Self {
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            intensity,
+            cos_inner: inner_angle.cos(),
+            cos_outer: outer_angle.cos(),
+        }
+    }
+
+    // How much of the light's intensity reaches the point, from 1 inside the
+    // inner cone to 0 outside the outer one, ignoring anything in the way
+    pub fn cone_factor(&self, point: Point) -> f64 {
+        let cos_angle = (point - self.position).normalize().dot(&self.direction);
+
+        if cos_angle >= self.cos_inner {
+            return 1.0;
+        }
+        if cos_angle <= self.cos_outer {
+            return 0.0;
+        }
+
+        // Smoothstep, so the edge of the cone doesn't show up as a crease
+        let t = (cos_angle - self.cos_outer) / (self.cos_inner - self.cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn equals(&self, other: &dyn Light) -> bool {
+        other.as_any().downcast_ref::<SpotLight>() == Some(self)
+    }
+
+    fn clone_box(&self) -> Box<dyn Light> {
+        Box::new(*self)
+    }
+
+    fn hash_into(&self, h: &mut StableHasher) {
+        let (p, d, i) = (self.position, self.direction, self.intensity);
+        h.write_str("SpotLight");
+        h.write_f64s(&[p.0, p.1, p.2, d.0, d.1, d.2, i.0, i.1, i.2]);
+        h.write_f64s(&[self.cos_inner, self.cos_outer]);
+    }
+
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn position(&self) -> Point {
+        self.position
+    }
+
+    fn intensity_towards(&self, point: Point) -> Color {
+        self.intensity * self.cone_factor(point)
+    }
+}
+
+// A fixed cycle of values, standing in for random numbers wherever results
+// have to be reproducible
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sequence {
+    values: Vec<f64>,
+}
+
+impl Sequence {
+    pub fn new(values: Vec<f64>) -> Self {
+        assert!(!values.is_empty(), "A sequence needs at least one value");
+
+        Sequence { values }
+    }
+
+    // The nth value, starting over from the first after the last
+    pub fn nth(&self, n: usize) -> f64 {
+        self.values[n % self.values.len()]
+    }
+}
+
+// A rectangular light with one corner at corner and sides along full_uvec
+// and full_vvec, split into usteps by vsteps cells with one sample point in
+// each. Points that only some of the samples reach fall in the penumbra of
+// a soft shadow.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AreaLight {
+    pub corner: Point,
+    // Size of a single cell along each side
+    pub uvec: Vector,
+    pub usteps: usize,
+    pub vvec: Vector,
+    pub vsteps: usize,
+    pub samples: usize,
+    // Center of the light, for anything that needs to treat it as a point
+    pub position: Point,
+    pub intensity: Color,
+    // Where in its cell each sample point sits, as fractions of the cell's
+    // sides. Without one every sample is at its cell's center.
+    pub jitter_by: Option<Sequence>,
+    // Spend the samples where the light looks biggest from the shaded point
+    // instead of one per cell, see importance_sample_points
+    pub importance_sampled: bool,
+}
+
+impl AreaLight {
+    pub fn new(
+        corner: Point,
+        full_uvec: Vector,
+        usteps: usize,
+        full_vvec: Vector,
+        vsteps: usize,
+        intensity: Color,
+    ) -> Self {
+        AreaLight {
+            corner,
+            uvec: full_uvec / usteps as f64,
+            usteps,
+            vvec: full_vvec / vsteps as f64,
+            vsteps,
+            samples: usteps * vsteps,
+            position: corner + full_uvec / 2.0 + full_vvec / 2.0,
+            intensity,
+            jitter_by: None,
+            importance_sampled: false,
+        }
+    }
+
+    // The sample point in cell (u, v). Cells are numbered row by row, and
+    // each one takes the next two values of jitter_by for its offsets along
+    // u and v.
+    pub fn point_on_light(&self, u: usize, v: usize) -> Point {
+        let (u_offset, v_offset) = match &self.jitter_by {
+            Some(sequence) => {
+                let cell = v * self.usteps + u;
+                (sequence.nth(2 * cell), sequence.nth(2 * cell + 1))
+            }
+            None => (0.5, 0.5),
+        };
+
+        self.corner + self.uvec * (u as f64 + u_offset) + self.vvec * (v as f64 + v_offset)
+    }
+
+    // Solid angle a small patch of the light around sample takes up as seen
+    // from point, per unit of the patch's area
+    pub fn solid_angle_weight(&self, sample: Point, point: Point) -> f64 {
+        let to_point = point - sample;
+        let distance = to_point.magnitude();
+        let facing = self.uvec.cross(&self.vvec).normalize().dot(&to_point) / distance;
+
+        facing.abs() / (distance * distance)
+    }
+
+    // As many points as there are cells, each in a cell picked with a
+    // probability proportional to the solid angle of its center seen from
+    // point, at a random spot within it. Near, face-on parts of a large
+    // light then get most of the samples, so the share of them that reach
+    // point estimates how much of the light's solid angle is unblocked with
+    // less noise than spreading them evenly. The same inputs always give
+    // the same points.
+    pub fn importance_sample_points(&self, point: Point, inputs: &[f64]) -> Vec<Point> {
+        let cells: Vec<(usize, usize)> = (0..self.vsteps)
+            .flat_map(|v| (0..self.usteps).map(move |u| (u, v)))
+            .collect();
+        let mut cumulative = Vec::with_capacity(cells.len());
+        let mut total = 0.0;
+        for &(u, v) in &cells {
+            let center = self.corner + self.uvec * (u as f64 + 0.5) + self.vvec * (v as f64 + 0.5);
+            total += self.solid_angle_weight(center, point);
+            cumulative.push(total);
         }
+
+        (0..self.samples as u64)
+            .map(|i| {
+                let pick = seeded_random(3 * i, inputs) * total;
+                let cell = cumulative
+                    .partition_point(|&w| w <= pick)
+                    .min(cells.len() - 1);
+                let (u, v) = cells[cell];
+                let u_offset = seeded_random(3 * i + 1, inputs);
+                let v_offset = seeded_random(3 * i + 2, inputs);
+
+                self.corner + self.uvec * (u as f64 + u_offset) + self.vvec * (v as f64 + v_offset)
+            })
+            .collect()
+    }
+}
+
+impl Light for AreaLight {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn equals(&self, other: &dyn Light) -> bool {
+        other.as_any().downcast_ref::<AreaLight>() == Some(self)
+    }
+
+    fn clone_box(&self) -> Box<dyn Light> {
+        Box::new(self.clone())
+    }
+
+    fn hash_into(&self, h: &mut StableHasher) {
+        let (c, u, v, i) = (self.corner, self.uvec, self.vvec, self.intensity);
+        h.write_str("AreaLight");
+        h.write_f64s(&[c.0, c.1, c.2, u.0, u.1, u.2, v.0, v.1, v.2, i.0, i.1, i.2]);
+        h.write_usize(self.usteps);
+        h.write_usize(self.vsteps);
+        h.write_usize(self.samples);
+        h.write_bool(self.jitter_by.is_some());
+        if let Some(sequence) = &self.jitter_by {
+            h.write_usize(sequence.values.len());
+            h.write_f64s(&sequence.values);
+        }
+        h.write_bool(self.importance_sampled);
+    }
+
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn position(&self) -> Point {
+        self.position
+    }
+
+    // One point per cell, row by row
+    fn sample_points(&self, _inputs: &[f64]) -> Vec<Point> {
+        (0..self.vsteps)
+            .flat_map(|v| (0..self.usteps).map(move |u| (u, v)))
+            .map(|(u, v)| self.point_on_light(u, v))
+            .collect()
+    }
+
+    fn intensity_at(&self, point: Point, world: &World) -> f64 {
+        let inputs = sample_inputs(point);
+        let samples = if self.importance_sampled {
+            self.importance_sample_points(point, &inputs)
+        } else {
+            self.sample_points(&inputs)
+        };
+
+        world.visible_fraction(&samples, point)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::f64::consts::PI;
+
+    use crate::{
+        constants::EPSILON,
+        shapes::{sphere::Sphere, Shape},
+        transformation::{scaling, translation},
+    };
+
     use super::*;
 
     #[test]
@@ -28,5 +439,351 @@ mod tests {
 
         assert_eq!(light.position, position);
         assert_eq!(light.intensity, intensity);
+        assert_eq!(light.jitter_radius, 0.0);
+        assert_eq!(light.falloff, Falloff::None);
+    }
+
+    #[test]
+    fn illuminance_without_falloff_is_constant() {
+        let light = PointLight::new(Point::origin(), Color::white());
+
+        assert_eq!(light.illuminance_at(Point::new(0.0, 1.0, 0.0)), 1.0);
+        assert_eq!(light.illuminance_at(Point::new(0.0, 0.0, 10.0)), 1.0);
+    }
+
+    #[test]
+    fn quadratic_falloff_follows_inverse_square_law() {
+        let mut light = PointLight::new(Point::new(0.0, 1.0, 0.0), Color(2.0, 2.0, 2.0));
+        light.falloff = Falloff::Quadratic;
+
+        for d in [1.0, 2.0, 5.0] {
+            let illuminance = light.illuminance_at(Point::new(d, 1.0, 0.0));
+            assert!((illuminance - 2.0 / (d * d)).abs() < EPSILON);
+        }
+        assert_eq!(
+            light.intensity_towards(Point::new(0.0, 3.0, 0.0)),
+            Color(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn jittering_light_position() {
+        let mut light = PointLight::new(Point::new(0.0, 10.0, 0.0), Color::white());
+        assert_eq!(light.jittered_position(&[1.0, 2.0]), light.position);
+
+        light.jitter_radius = 0.5;
+        let a = light.jittered_position(&[1.0, 2.0]);
+        let b = light.jittered_position(&[1.0, 3.0]);
+
+        assert_ne!(a, b);
+        assert_eq!(a, light.jittered_position(&[1.0, 2.0]));
+        for p in [a, b] {
+            assert!((p.0 - 0.0).abs() <= 0.5);
+            assert!((p.1 - 10.0).abs() <= 0.5);
+            assert!((p.2 - 0.0).abs() <= 0.5);
+        }
+    }
+
+    #[test]
+    fn creating_area_light() {
+        let light = AreaLight::new(
+            Point::origin(),
+            Vector::new(2.0, 0.0, 0.0),
+            4,
+            Vector::new(0.0, 0.0, 1.0),
+            2,
+            Color::white(),
+        );
+
+        assert_eq!(light.corner, Point::origin());
+        assert_eq!(light.uvec, Vector::new(0.5, 0.0, 0.0));
+        assert_eq!(light.usteps, 4);
+        assert_eq!(light.vvec, Vector::new(0.0, 0.0, 0.5));
+        assert_eq!(light.vsteps, 2);
+        assert_eq!(light.samples, 8);
+        assert_eq!(light.position, Point::new(1.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn finding_single_point_on_area_light() {
+        let light = AreaLight::new(
+            Point::origin(),
+            Vector::new(2.0, 0.0, 0.0),
+            4,
+            Vector::new(0.0, 0.0, 1.0),
+            2,
+            Color::white(),
+        );
+        let cases = [
+            (0, 0, Point::new(0.25, 0.0, 0.25)),
+            (1, 0, Point::new(0.75, 0.0, 0.25)),
+            (0, 1, Point::new(0.25, 0.0, 0.75)),
+            (2, 0, Point::new(1.25, 0.0, 0.25)),
+            (3, 1, Point::new(1.75, 0.0, 0.75)),
+        ];
+
+        for (u, v, point) in cases {
+            assert_eq!(light.point_on_light(u, v), point);
+        }
+    }
+
+    #[test]
+    fn point_light_intensity_at_is_all_or_nothing() {
+        let w = World::default();
+        let light = w.lights[0].as_ref();
+        let cases = [
+            (Point::new(0.0, 1.0001, 0.0), 1.0),
+            (Point::new(-1.0001, 0.0, 0.0), 1.0),
+            (Point::new(0.0, 0.0, -1.0001), 1.0),
+            (Point::new(0.0, 0.0, 1.0001), 0.0),
+            (Point::new(1.0001, 0.0, 0.0), 0.0),
+            (Point::new(0.0, -1.0001, 0.0), 0.0),
+            (Point::new(0.0, 0.0, 0.0), 0.0),
+        ];
+
+        for (point, intensity) in cases {
+            assert_eq!(light.intensity_at(point, &w), intensity);
+        }
+    }
+
+    #[test]
+    fn area_light_intensity_function() {
+        let w = World::default();
+        let light = AreaLight::new(
+            Point::new(-0.5, -0.5, -5.0),
+            Vector::new(1.0, 0.0, 0.0),
+            2,
+            Vector::new(0.0, 1.0, 0.0),
+            2,
+            Color::white(),
+        );
+        let cases = [
+            (Point::new(0.0, 0.0, 2.0), 0.0),
+            (Point::new(1.0, -1.0, 2.0), 0.25),
+            (Point::new(1.5, 0.0, 2.0), 0.5),
+            (Point::new(1.25, 1.25, 3.0), 0.75),
+            (Point::new(0.0, 0.0, -2.0), 1.0),
+        ];
+
+        for (point, intensity) in cases {
+            assert_eq!(light.intensity_at(point, &w), intensity);
+        }
+    }
+
+    #[test]
+    fn four_by_four_area_light_casts_penumbra() {
+        let w = World::default();
+        let light = AreaLight::new(
+            Point::new(-1.0, -1.0, -5.0),
+            Vector::new(2.0, 0.0, 0.0),
+            4,
+            Vector::new(0.0, 2.0, 0.0),
+            4,
+            Color::white(),
+        );
+
+        // Moving out sideways from behind the sphere goes from the umbra
+        // through the penumbra into full light, a sixteenth at a time
+        let intensities: Vec<f64> = [0.0, 1.5, 3.0]
+            .iter()
+            .map(|&x| light.intensity_at(Point::new(x, 0.0, 2.0), &w))
+            .collect();
+
+        assert_eq!(intensities[0], 0.0);
+        assert!(intensities[1] > 0.0 && intensities[1] < 1.0);
+        assert_eq!((intensities[1] * 16.0).fract(), 0.0);
+        assert_eq!(intensities[2], 1.0);
+    }
+
+    fn spotlight() -> SpotLight {
+        SpotLight::new(
+            Point::new(0.0, 10.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+            Color::white(),
+            PI / 8.0,
+            PI / 4.0,
+        )
+    }
+
+    // The point on the floor under the spotlight at the given angle from its
+    // direction
+    fn floor_point_at_angle(angle: f64) -> Point {
+        Point::new(10.0 * angle.tan(), 0.0, 0.0)
+    }
+
+    #[test]
+    fn spotlight_is_full_strength_at_cone_center() {
+        let light = spotlight();
+
+        assert_eq!(light.cone_factor(Point::origin()), 1.0);
+        assert_eq!(light.intensity_towards(Point::origin()), Color::white());
+        assert_eq!(light.cone_factor(floor_point_at_angle(PI / 10.0)), 1.0);
+    }
+
+    #[test]
+    fn spotlight_fades_out_towards_outer_edge() {
+        let light = spotlight();
+
+        let just_inside = light.cone_factor(floor_point_at_angle(PI / 4.0 - 0.01));
+
+        assert!(just_inside > 0.0 && just_inside < 0.01);
+        assert_eq!(
+            light.cone_factor(floor_point_at_angle(PI / 4.0 + 0.01)),
+            0.0
+        );
+        assert_eq!(
+            light.intensity_towards(Point::new(0.0, 20.0, 0.0)),
+            Color::black()
+        );
+    }
+
+    #[test]
+    fn spotlight_falloff_is_continuous() {
+        let light = spotlight();
+        let steps = 100;
+
+        let factors: Vec<f64> = (0..=steps)
+            .map(|i| {
+                let angle = PI / 8.0 + (PI / 8.0) * i as f64 / steps as f64;
+                light.cone_factor(floor_point_at_angle(angle))
+            })
+            .collect();
+
+        assert!((factors[0] - 1.0).abs() < EPSILON);
+        assert!(factors[steps].abs() < EPSILON);
+        for pair in factors.windows(2) {
+            assert!(pair[1] <= pair[0]);
+            assert!(pair[0] - pair[1] < 0.05);
+        }
+    }
+
+    #[test]
+    fn sequence_cycles_through_its_values() {
+        let sequence = Sequence::new(vec![0.1, 0.5, 1.0]);
+
+        let values: Vec<f64> = (0..5).map(|n| sequence.nth(n)).collect();
+
+        assert_eq!(values, vec![0.1, 0.5, 1.0, 0.1, 0.5]);
+    }
+
+    #[test]
+    fn finding_single_point_on_jittered_area_light() {
+        let mut light = AreaLight::new(
+            Point::origin(),
+            Vector::new(2.0, 0.0, 0.0),
+            4,
+            Vector::new(0.0, 0.0, 1.0),
+            2,
+            Color::white(),
+        );
+        light.jitter_by = Some(Sequence::new(vec![0.3, 0.7]));
+        let cases = [
+            (0, 0, Point::new(0.15, 0.0, 0.35)),
+            (1, 0, Point::new(0.65, 0.0, 0.35)),
+            (0, 1, Point::new(0.15, 0.0, 0.85)),
+            (2, 0, Point::new(1.15, 0.0, 0.35)),
+            (3, 1, Point::new(1.65, 0.0, 0.85)),
+        ];
+
+        for (u, v, point) in cases {
+            assert_eq!(light.point_on_light(u, v), point);
+        }
+    }
+
+    #[test]
+    fn area_light_with_jittered_samples() {
+        let w = World::default();
+        let mut light = AreaLight::new(
+            Point::new(-0.5, -0.5, -5.0),
+            Vector::new(1.0, 0.0, 0.0),
+            2,
+            Vector::new(0.0, 1.0, 0.0),
+            2,
+            Color::white(),
+        );
+        light.jitter_by = Some(Sequence::new(vec![0.7, 0.3, 0.9, 0.1, 0.5]));
+        let cases = [
+            (Point::new(0.0, 0.0, 2.0), 0.0),
+            (Point::new(1.0, -1.0, 2.0), 0.5),
+            (Point::new(1.5, 0.0, 2.0), 0.75),
+            (Point::new(1.25, 1.25, 3.0), 0.75),
+            (Point::new(0.0, 0.0, -2.0), 1.0),
+        ];
+
+        for (point, intensity) in cases {
+            assert_eq!(light.intensity_at(point, &w), intensity);
+        }
+    }
+
+    #[test]
+    fn importance_sampled_area_light_has_less_noise_than_uniform() {
+        // A large light just above the point, with a small blocker under a
+        // far corner that only hides a sliver of its solid angle
+        let mut light = AreaLight::new(
+            Point::new(-5.0, 1.0, -5.0),
+            Vector::new(10.0, 0.0, 0.0),
+            4,
+            Vector::new(0.0, 0.0, 10.0),
+            4,
+            Color::white(),
+        );
+        light.importance_sampled = true;
+        let mut w = World::new();
+        let mut blocker = Sphere::new();
+        blocker.set_transformation(translation(1.5, 0.5, 1.5) * scaling(0.3, 0.3, 0.3));
+        w.objects = vec![Box::new(blocker)];
+        let point = Point::origin();
+        let full_uvec = light.uvec * light.usteps as f64;
+        let full_vvec = light.vvec * light.vsteps as f64;
+
+        // Average solid angle weight over the light, to scale uniform
+        // samples by so both estimate the same unblocked share
+        let grid = 200;
+        let mean_weight = (0..grid * grid)
+            .map(|i| {
+                let (u, v) = ((i % grid) as f64 + 0.5, (i / grid) as f64 + 0.5);
+                let sample =
+                    light.corner + full_uvec * (u / grid as f64) + full_vvec * (v / grid as f64);
+                light.solid_angle_weight(sample, point)
+            })
+            .sum::<f64>()
+            / (grid * grid) as f64;
+
+        let variance = |estimates: &[f64]| {
+            let mean = estimates.iter().sum::<f64>() / estimates.len() as f64;
+            estimates.iter().map(|e| (e - mean).powi(2)).sum::<f64>() / estimates.len() as f64
+        };
+        let runs: Vec<[f64; 1]> = (0..200).map(|run| [run as f64]).collect();
+
+        let importance: Vec<f64> = runs
+            .iter()
+            .map(|inputs| {
+                let samples = light.importance_sample_points(point, inputs);
+                w.visible_fraction(&samples, point)
+            })
+            .collect();
+        let uniform: Vec<f64> = runs
+            .iter()
+            .map(|inputs| {
+                (0..light.samples as u64)
+                    .map(|i| {
+                        let sample = light.corner
+                            + full_uvec * seeded_random(2 * i, inputs)
+                            + full_vvec * seeded_random(2 * i + 1, inputs);
+                        let lit = if w.is_shadowed(sample, point) {
+                            0.0
+                        } else {
+                            1.0
+                        };
+
+                        lit * light.solid_angle_weight(sample, point) / mean_weight
+                    })
+                    .sum::<f64>()
+                    / light.samples as f64
+            })
+            .collect();
+
+        assert!(importance.iter().any(|&e| e < 1.0));
+        assert!(variance(&importance) < variance(&uniform));
     }
 }
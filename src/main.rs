@@ -2,15 +2,21 @@
 
 use std::error::Error;
 
+mod animation;
+mod bounds;
 mod camera;
 mod canvas;
 mod color;
+mod config;
 mod constants;
+mod error;
 mod experiments;
+mod grid;
 mod intersection;
 mod lights;
 mod materials;
 mod matrices;
+mod parser;
 mod patterns;
 mod rays;
 mod shapes;
@@ -27,5 +33,6 @@ fn main() -> Result<(), Box<dyn Error>> {
     // experiments::sphere::draw_sphere()
     // experiments::scene::draw_scene()
     // experiments::scene::draw_scene_with_plane()
-    experiments::pattern::blended_pattern_floor()
+    // experiments::pattern::blended_pattern_floor()
+    experiments::pattern::transformed_checkered_sphere()
 }
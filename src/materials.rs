@@ -1,30 +1,109 @@
 use crate::{
     color::Color,
-    lights::PointLight,
+    constants::EPSILON,
+    intersection::{Computations, Part},
+    lights::{sample_inputs, Light},
     patterns::{solid::Solid, Pattern},
     shapes::Shape,
     tuples::{Point, Vector},
+    utils::StableHasher,
 };
 
+// Number of specular highlights computed on this thread, so tests can check
+// that matte materials skip the work entirely
+#[cfg(test)]
+thread_local! {
+    static SPECULAR_EVALUATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+// How light scatters off the surface for the diffuse term of lighting
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffuseModel {
+    Lambertian,
+    // Models rough surfaces (like clay) as tiny facets, which makes them
+    // look flatter than Lambertian ones. Roughness is the standard deviation
+    // of the facet angles in radians.
+    OrenNayar { roughness: f64 },
+}
+
+// The separate terms of the Phong lighting model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightingComponents {
+    pub ambient: Color,
+    pub diffuse: Color,
+    pub specular: Color,
+}
+
+impl LightingComponents {
+    // No light at all, for summing the contributions of several lights
+    pub fn none() -> Self {
+        Self {
+            ambient: Color::black(),
+            diffuse: Color::black(),
+            specular: Color::black(),
+        }
+    }
+
+    pub fn total(&self) -> Color {
+        self.ambient + self.diffuse + self.specular
+    }
+}
+
+impl std::ops::Add for LightingComponents {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Self {
+            ambient: self.ambient + other.ambient,
+            diffuse: self.diffuse + other.diffuse,
+            specular: self.specular + other.specular,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Material {
     pub ambient: f64,
     pub diffuse: f64,
+    pub diffuse_model: DiffuseModel,
     pub specular: f64,
+    // Tints the highlight, which is otherwise the color of the light like
+    // on plastics. Metals usually take on their surface color here.
+    pub specular_color: Color,
     pub shininess: f64,
     pub reflective: f64,
     pub transparency: f64,
     pub refractive_index: f64,
     pub pattern: Box<dyn Pattern>,
+    // Grayscale pattern scaling `reflective` across the surface, so parts of
+    // it can be more or less mirror-like
+    pub reflective_map: Option<Box<dyn Pattern>>,
+    // Material used for the caps of shapes that have them, like closed cylinders
+    pub cap_material: Option<Box<Material>>,
+    // Material used where the ray hits the inside of the surface, like the
+    // inner wall of a hollow shell
+    pub back_material: Option<Box<Material>>,
+    // Invisible except for the shadows falling on it, which come out in the
+    // alpha channel for compositing over photos, see World::alpha_at
+    pub shadow_catcher: bool,
 }
 
 impl PartialEq for Material {
     fn eq(&self, other: &Self) -> bool {
         self.ambient == other.ambient
             && self.diffuse == other.diffuse
+            && self.diffuse_model == other.diffuse_model
             && self.specular == other.specular
+            && self.specular_color == other.specular_color
             && self.shininess == other.shininess
             && self.pattern.as_ref().equals(other.pattern.as_ref())
+            && self.cap_material == other.cap_material
+            && match (&self.reflective_map, &other.reflective_map) {
+                (Some(a), Some(b)) => a.as_ref().equals(b.as_ref()),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+            && self.back_material == other.back_material
+            && self.shadow_catcher == other.shadow_catcher
     }
 }
 
@@ -33,63 +112,277 @@ impl Material {
         Self {
             ambient: 0.1,
             diffuse: 0.9,
+            diffuse_model: DiffuseModel::Lambertian,
             specular: 0.9,
+            specular_color: Color::white(),
             shininess: 200.0,
             reflective: 0.0,
             transparency: 0.0,
             refractive_index: 1.0,
             pattern: Box::new(Solid::new(Color::white())),
+            reflective_map: None,
+            cap_material: None,
+            back_material: None,
+            shadow_catcher: false,
+        }
+    }
+
+    // Feeds every field into h, for World::scene_hash
+    pub fn hash_into(&self, h: &mut StableHasher) {
+        h.write_f64s(&[self.ambient, self.diffuse]);
+        match self.diffuse_model {
+            DiffuseModel::Lambertian => h.write_u64(0),
+            DiffuseModel::OrenNayar { roughness } => {
+                h.write_u64(1);
+                h.write_f64(roughness);
+            }
+        }
+        let c = self.specular_color;
+        h.write_f64s(&[self.specular, c.0, c.1, c.2, self.shininess]);
+        h.write_f64s(&[self.reflective, self.transparency, self.refractive_index]);
+        self.pattern.hash_into(h);
+
+        h.write_bool(self.reflective_map.is_some());
+        if let Some(map) = &self.reflective_map {
+            map.hash_into(h);
+        }
+        for m in [&self.cap_material, &self.back_material] {
+            h.write_bool(m.is_some());
+            if let Some(m) = m {
+                m.hash_into(h);
+            }
+        }
+        h.write_bool(self.shadow_catcher);
+    }
+
+    // Sets shininess from a roughness between 0 (mirror-sharp highlights) and
+    // 1 (broad ones), using the Beckmann to Phong mapping
+    // shininess = 2 / roughness⁴ - 2. Roughness is kept above 0.01 so the
+    // exponent stays finite. It only shapes the highlight, unlike the
+    // roughness of DiffuseModel::OrenNayar.
+    pub fn set_specular_roughness(&mut self, roughness: f64) {
+        let r = roughness.clamp(0.01, 1.0);
+        self.shininess = 2.0 / r.powi(4) - 2.0;
+    }
+
+    // The roughness matching the current shininess, see set_specular_roughness
+    pub fn specular_roughness(&self) -> f64 {
+        (2.0 / (self.shininess + 2.0)).powf(0.25)
+    }
+
+    // The material to shade the given part of a shape with
+    pub fn for_part(&self, part: Option<Part>) -> &Material {
+        match (part, &self.cap_material) {
+            (Some(Part::Cap), Some(cap_material)) => cap_material,
+            _ => self,
+        }
+    }
+
+    // The material to shade a prepared hit with, picking the part's material
+    // first and then its back material if the hit is from inside
+    pub fn for_hit(&self, comps: &Computations) -> &Material {
+        let material = self.for_part(comps.part);
+
+        match (comps.inside, &material.back_material) {
+            (true, Some(back_material)) => back_material,
+            _ => material,
+        }
+    }
+
+    // How reflective the surface of the object is at the given world point
+    pub fn reflective_at(&self, object: &dyn Shape, point: Point) -> f64 {
+        match &self.reflective_map {
+            Some(map) => {
+                let Color(r, g, b) = map.at_object(object, point);
+                self.reflective * (r + g + b) / 3.0
+            }
+            None => self.reflective,
         }
     }
 
     pub fn lighting(
         &self,
-        light: &PointLight,
+        light: &dyn Light,
         point: Point,
         eyev: Vector,
         normalv: Vector,
         in_shadow: bool,
         object: &dyn Shape,
+    ) -> Color {
+        self.lighting_components(light, point, eyev, normalv, in_shadow, object)
+            .total()
+    }
+
+    // Lighting where intensity is the fraction of the light that reaches the
+    // point, see Light::intensity_at
+    pub fn lighting_with_intensity(
+        &self,
+        light: &dyn Light,
+        point: Point,
+        eyev: Vector,
+        normalv: Vector,
+        intensity: f64,
+        object: &dyn Shape,
     ) -> Color {
         let color = self.pattern.at_object(object, point);
+        let samples = light.sample_points(&sample_inputs(point));
 
-        // Combine the surface color with the light's color/intensity
-        let effective_color = color * light.intensity;
+        self.shade(color, light, &samples, point, eyev, normalv, intensity)
+            .total()
+    }
 
-        // Find the direction to the light source
-        let lightv = (light.position - point).normalize();
+    pub fn lighting_components(
+        &self,
+        light: &dyn Light,
+        point: Point,
+        eyev: Vector,
+        normalv: Vector,
+        in_shadow: bool,
+        object: &dyn Shape,
+    ) -> LightingComponents {
+        let color = self.pattern.at_object(object, point);
+        let samples = light.sample_points(&sample_inputs(point));
+        let visibility = if in_shadow { 0.0 } else { 1.0 };
+
+        self.shade(color, light, &samples, point, eyev, normalv, visibility)
+    }
+
+    // Lighting at a prepared hit, where footprint is roughly how wide an area
+    // of the surface the hit stands for and visibility is the fraction of the
+    // light that reaches it. The light is sampled from over_point, the same
+    // as the shadow rays of Light::intensity_at.
+    pub fn lighting_at_hit(
+        &self,
+        light: &dyn Light,
+        comps: &Computations,
+        visibility: f64,
+        footprint: f64,
+    ) -> LightingComponents {
+        let color = self.pattern.at_hit(comps.object, comps.point, footprint);
+        let samples = light.sample_points(&sample_inputs(comps.over_point));
+
+        self.shade(
+            color,
+            light,
+            &samples,
+            comps.point,
+            comps.eyev,
+            comps.normalv,
+            visibility,
+        )
+    }
+
+    // Shading from a light made of one or more sample points, with the
+    // diffuse and specular terms averaged over the samples and scaled by
+    // visibility, the fraction of the light that isn't blocked
+    #[allow(clippy::too_many_arguments)]
+    fn shade(
+        &self,
+        color: Color,
+        light: &dyn Light,
+        samples: &[Point],
+        point: Point,
+        eyev: Vector,
+        normalv: Vector,
+        visibility: f64,
+    ) -> LightingComponents {
+        let intensity = light.intensity_towards(point);
+
+        // Combine the surface color with the light's color/intensity
+        let effective_color = color * intensity;
 
         // Compute the ambient contribution
         let ambient = effective_color * self.ambient;
 
-        // light_dot_normal represents the cosine of the angle between the
-        // light vector and the normal vector. A negative number means the
-        // light is on the other side of the surface.
-        let light_dot_normal = lightv.dot(&normalv);
-        let diffuse: Color;
-        let specular: Color;
-        if light_dot_normal < 0.0 || in_shadow {
-            diffuse = Color::black();
-            specular = Color::black();
-        } else {
-            // Compute the diffuse contribution
-            diffuse = effective_color * self.diffuse * light_dot_normal;
-
-            // reflect_dot_eye represents the cosine of the angle between the
-            // reflection vector and the eye vector. A negative number means the
-            // light reflects away from the eye
-            let reflectv = -lightv.reflect(normalv);
-            let reflect_dot_eye = reflectv.dot(&eyev);
-            if reflect_dot_eye <= 0.0 {
-                specular = Color::black();
-            } else {
-                // Compute the specular contribution
-                let factor = reflect_dot_eye.powf(self.shininess);
-                specular = light.intensity * self.specular * factor;
+        let mut diffuse = Color::black();
+        let mut specular = Color::black();
+        if visibility > 0.0 {
+            for &sample in samples {
+                // Find the direction to the light source
+                let lightv = (sample - point).normalize();
+
+                // light_dot_normal represents the cosine of the angle between
+                // the light vector and the normal vector. A negative number
+                // means the light is on the other side of the surface.
+                let light_dot_normal = lightv.dot(&normalv);
+                if light_dot_normal < 0.0 {
+                    continue;
+                }
+
+                // Compute the diffuse contribution
+                diffuse = diffuse
+                    + effective_color
+                        * self.diffuse
+                        * light_dot_normal
+                        * self.diffuse_factor(lightv, eyev, normalv);
+
+                // reflect_dot_eye represents the cosine of the angle between
+                // the reflection vector and the eye vector. A negative number
+                // means the light reflects away from the eye. Matte surfaces
+                // have no highlight, so they don't need it at all.
+                let reflect_dot_eye = if self.specular == 0.0 {
+                    0.0
+                } else {
+                    (-lightv.reflect(normalv)).dot(&eyev)
+                };
+                if reflect_dot_eye > 0.0 {
+                    #[cfg(test)]
+                    SPECULAR_EVALUATIONS.with(|count| count.set(count.get() + 1));
+
+                    // Compute the specular contribution
+                    let factor = reflect_dot_eye.powf(self.shininess);
+                    specular = specular + intensity * self.specular_color * self.specular * factor;
+                }
             }
+
+            let scale = visibility / samples.len() as f64;
+            diffuse = diffuse * scale;
+            specular = specular * scale;
         }
 
-        ambient + diffuse + specular
+        LightingComponents {
+            ambient,
+            diffuse,
+            specular,
+        }
+    }
+
+    // Scale applied on top of the Lambertian diffuse term
+    fn diffuse_factor(&self, lightv: Vector, eyev: Vector, normalv: Vector) -> f64 {
+        let roughness = match self.diffuse_model {
+            DiffuseModel::Lambertian => return 1.0,
+            DiffuseModel::OrenNayar { roughness } => roughness,
+        };
+
+        let sigma2 = roughness.powi(2);
+        let a = 1.0 - 0.5 * sigma2 / (sigma2 + 0.33);
+        let b = 0.45 * sigma2 / (sigma2 + 0.09);
+
+        let light_dot_normal = lightv.dot(&normalv);
+        let eye_dot_normal = eyev.dot(&normalv).clamp(-1.0, 1.0);
+        let theta_i = light_dot_normal.clamp(-1.0, 1.0).acos();
+        let theta_r = eye_dot_normal.acos();
+        let alpha = theta_i.max(theta_r);
+        let beta = theta_i.min(theta_r);
+
+        // Cosine of the azimuth between the light and eye, measured in the
+        // plane of the surface
+        let light_tangent = lightv - normalv * light_dot_normal;
+        let eye_tangent = eyev - normalv * eye_dot_normal;
+        let lengths = light_tangent.magnitude() * eye_tangent.magnitude();
+        let cos_phi = if lengths < EPSILON {
+            0.0
+        } else {
+            (light_tangent.dot(&eye_tangent) / lengths).max(0.0)
+        };
+
+        a + b * cos_phi * alpha.sin() * beta.tan()
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::new()
     }
 }
 
@@ -98,12 +391,18 @@ impl Clone for Material {
         Self {
             ambient: self.ambient,
             diffuse: self.diffuse,
+            diffuse_model: self.diffuse_model,
             specular: self.specular,
+            specular_color: self.specular_color,
             shininess: self.shininess,
             reflective: self.reflective,
             transparency: self.transparency,
             refractive_index: self.refractive_index,
             pattern: self.pattern.clone_box(),
+            reflective_map: self.reflective_map.as_ref().map(|m| m.clone_box()),
+            cap_material: self.cap_material.clone(),
+            back_material: self.back_material.clone(),
+            shadow_catcher: self.shadow_catcher,
         }
     }
 }
@@ -118,17 +417,100 @@ mod tests {
 
         assert_eq!(m.ambient, 0.1);
         assert_eq!(m.diffuse, 0.9);
+        assert_eq!(m.diffuse_model, DiffuseModel::Lambertian);
         assert_eq!(m.specular, 0.9);
         assert_eq!(m.shininess, 200.0);
         assert_eq!(m.reflective, 0.0);
         assert_eq!(m.transparency, 0.0);
         assert_eq!(m.refractive_index, 1.0);
         assert!(m.pattern.equals(&Solid::new(Color::white())));
+        assert!(m.reflective_map.is_none());
+        assert!(m.cap_material.is_none());
+        assert!(m.back_material.is_none());
+    }
+
+    #[test]
+    fn materials_differing_in_one_field_are_not_equal() {
+        let edits: [fn(&mut Material); 4] = [
+            |m| m.cap_material = Some(Box::new(Material::new())),
+            |m| m.reflective_map = Some(Box::new(Solid::new(Color::white()))),
+            |m| m.shadow_catcher = true,
+            |m| m.back_material = Some(Box::new(Material::new())),
+        ];
+
+        for edit in edits {
+            let mut m = Material::new();
+            edit(&mut m);
+
+            assert_ne!(m, Material::new());
+        }
+
+        let mut a = Material::new();
+        a.reflective_map = Some(Box::new(Solid::new(Color::white())));
+        let mut b = Material::new();
+        b.reflective_map = Some(Box::new(Solid::new(Color::black())));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn default_trait_matches_new() {
+        #[derive(Default)]
+        struct Holder {
+            material: Material,
+            color: Color,
+            point: Point,
+            vector: Vector,
+        }
+
+        let holder = Holder::default();
+
+        assert_eq!(Material::default(), Material::new());
+        assert_eq!(holder.material, Material::new());
+        assert_eq!(holder.color, Color::black());
+        assert_eq!(holder.point, Point::origin());
+        assert_eq!(holder.vector, Vector::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn setting_shininess_through_specular_roughness() {
+        let mut m = Material::new();
+
+        m.set_specular_roughness(0.0);
+        assert!(m.shininess > 1e6);
+        assert!(m.specular_roughness() < 0.05);
+
+        m.set_specular_roughness(1.0);
+        assert_eq!(m.shininess, 0.0);
+        assert!((m.specular_roughness() - 1.0).abs() < EPSILON);
+
+        m.set_specular_roughness(0.5);
+        assert_eq!(m.shininess, 30.0);
+        assert!((m.specular_roughness() - 0.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn selecting_material_for_part() {
+        let mut cap = Material::new();
+        cap.pattern = Box::new(Solid::new(Color(0.0, 0.0, 1.0)));
+
+        let mut m = Material::new();
+        assert_eq!(m.for_part(Some(Part::Cap)), &m);
+
+        m.cap_material = Some(Box::new(cap.clone()));
+        assert_eq!(m.for_part(None), &m);
+        assert_eq!(m.for_part(Some(Part::Side)), &m);
+        assert_eq!(m.for_part(Some(Part::Cap)), &cap);
     }
 
     mod lighting {
+        use std::f64::consts::FRAC_1_SQRT_2;
+
         use super::*;
-        use crate::{lights::PointLight, patterns::stripe::Stripe, shapes::sphere::Sphere};
+        use crate::{
+            lights::{AreaLight, Falloff, Light, PointLight},
+            patterns::{solid::Solid, stripe::Stripe},
+            shapes::sphere::Sphere,
+        };
 
         const POSITION: Point = Point(0.0, 0.0, 0.0, 1.0);
 
@@ -163,6 +545,77 @@ mod tests {
             assert_eq!(result, Color(0.1, 0.1, 0.1));
         }
 
+        #[test]
+        fn lighting_through_light_trait_object() {
+            let m: Material = Material::new();
+
+            let eyev = Vector::new(0.0, 0.0, -1.0);
+            let normalv = Vector::new(0.0, 0.0, -1.0);
+            let light: Box<dyn Light> =
+                Box::new(PointLight::new(Point::new(0.0, 0.0, -10.0), Color::white()));
+            let sphere = Sphere::new();
+
+            let lit = m.lighting(light.as_ref(), POSITION, eyev, normalv, false, &sphere);
+            let half_lit =
+                m.lighting_with_intensity(light.as_ref(), POSITION, eyev, normalv, 0.5, &sphere);
+
+            assert_eq!(lit, Color(1.9, 1.9, 1.9));
+            assert_eq!(half_lit, Color(1.0, 1.0, 1.0));
+        }
+
+        #[test]
+        fn lighting_samples_the_area_light() {
+            let light = AreaLight::new(
+                Point::new(-0.5, -0.5, -5.0),
+                Vector::new(1.0, 0.0, 0.0),
+                2,
+                Vector::new(0.0, 1.0, 0.0),
+                2,
+                Color::white(),
+            );
+            let shape = Sphere::new();
+            let mut m = Material::new();
+            m.ambient = 0.1;
+            m.diffuse = 0.9;
+            m.specular = 0.0;
+            m.pattern = Box::new(Solid::new(Color::white()));
+            let eye = Point::new(0.0, 0.0, -5.0);
+            let cases = [
+                (Point::new(0.0, 0.0, -1.0), Color(0.9965, 0.9965, 0.9965)),
+                (
+                    Point::new(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+                    Color(0.62318, 0.62318, 0.62318),
+                ),
+            ];
+
+            for (point, expected) in cases {
+                let eyev = (eye - point).normalize();
+                let normalv = point - Point::origin();
+
+                let result = m.lighting_with_intensity(&light, point, eyev, normalv, 1.0, &shape);
+
+                assert!((result.0 - expected.0).abs() < 1e-4);
+                assert!((result.1 - expected.1).abs() < 1e-4);
+                assert!((result.2 - expected.2).abs() < 1e-4);
+            }
+        }
+
+        #[test]
+        fn lighting_with_quadratic_falloff() {
+            let m: Material = Material::new();
+
+            let eyev = Vector::new(0.0, 0.0, -1.0);
+            let normalv = Vector::new(0.0, 0.0, -1.0);
+            let mut light = PointLight::new(Point::new(0.0, 0.0, -2.0), Color::white());
+            light.falloff = Falloff::Quadratic;
+            let sphere = Sphere::new();
+
+            let result = m.lighting(&light, POSITION, eyev, normalv, false, &sphere);
+
+            // A quarter of the light reaches two units away
+            assert_eq!(result, Color(0.475, 0.475, 0.475));
+        }
+
         #[test]
         fn lighting_with_eye_opposite_surface_with_light_offset_45deg() {
             let m: Material = Material::new();
@@ -194,6 +647,44 @@ mod tests {
             assert_eq!(result, Color(1.6364, 1.6364, 1.6364));
         }
 
+        #[test]
+        fn matte_material_skips_specular() {
+            let mut m: Material = Material::new();
+            m.specular = 0.0;
+
+            let eyev = Vector::new(0.0, -2.0_f64.sqrt() / 2.0, -2.0_f64.sqrt() / 2.0);
+            let normalv = Vector::new(0.0, 0.0, -1.0);
+            let light = PointLight::new(Point::new(0.0, 10.0, -10.0), Color::white());
+            let sphere = Sphere::new();
+
+            let before = SPECULAR_EVALUATIONS.with(|count| count.get());
+            let result = m.lighting(&light, POSITION, eyev, normalv, false, &sphere);
+
+            // Same as the full strength highlight above without its 0.9
+            assert_eq!(result, Color(0.7364, 0.7364, 0.7364));
+            assert_eq!(SPECULAR_EVALUATIONS.with(|count| count.get()), before);
+
+            m.specular = 0.9;
+            m.lighting(&light, POSITION, eyev, normalv, false, &sphere);
+            assert_eq!(SPECULAR_EVALUATIONS.with(|count| count.get()), before + 1);
+        }
+
+        #[test]
+        fn specular_color_tints_the_highlight() {
+            let mut m: Material = Material::new();
+            m.specular_color = Color(1.0, 0.0, 0.0);
+
+            let eyev = Vector::new(0.0, -2.0_f64.sqrt() / 2.0, -2.0_f64.sqrt() / 2.0);
+            let normalv = Vector::new(0.0, 0.0, -1.0);
+            let light = PointLight::new(Point::new(0.0, 10.0, -10.0), Color::white());
+            let sphere = Sphere::new();
+
+            let result = m.lighting(&light, POSITION, eyev, normalv, false, &sphere);
+
+            // The full 0.9 highlight only lands on the red channel
+            assert_eq!(result, Color(1.6364, 0.7364, 0.7364));
+        }
+
         #[test]
         fn lighting_with_light_behind_the_surface() {
             let m: Material = Material::new();
@@ -209,6 +700,51 @@ mod tests {
             assert_eq!(result, Color(0.1, 0.1, 0.1));
         }
 
+        // Diffuse-only lighting with the light and eye at the same angle off
+        // the normal
+        fn diffuse_at_angle(m: &Material, angle: f64) -> f64 {
+            let normalv = Vector::new(0.0, 0.0, -1.0);
+            let direction = Vector::new(angle.sin(), 0.0, -angle.cos());
+            let light = PointLight::new(Point::origin() + direction * 10.0, Color::white());
+
+            m.lighting(&light, POSITION, direction, normalv, false, &Sphere::new())
+                .0
+        }
+
+        fn diffuse_only(model: DiffuseModel) -> Material {
+            let mut m = Material::new();
+            m.ambient = 0.0;
+            m.specular = 0.0;
+            m.diffuse_model = model;
+            m
+        }
+
+        #[test]
+        fn oren_nayar_with_zero_roughness_is_lambertian() {
+            let lambert = diffuse_only(DiffuseModel::Lambertian);
+            let smooth = diffuse_only(DiffuseModel::OrenNayar { roughness: 0.0 });
+
+            for angle in [0.0, 0.4, 0.8, 1.2] {
+                assert!(
+                    (diffuse_at_angle(&lambert, angle) - diffuse_at_angle(&smooth, angle)).abs()
+                        < EPSILON
+                );
+            }
+        }
+
+        #[test]
+        fn oren_nayar_roughness_flattens_grazing_falloff() {
+            let grazing = 1.3;
+            let falloff = |m: &Material| diffuse_at_angle(m, grazing) / diffuse_at_angle(m, 0.0);
+
+            let lambert = diffuse_only(DiffuseModel::Lambertian);
+            let rough = diffuse_only(DiffuseModel::OrenNayar { roughness: 0.5 });
+            let rougher = diffuse_only(DiffuseModel::OrenNayar { roughness: 1.0 });
+
+            assert!(falloff(&rough) > falloff(&lambert));
+            assert!(falloff(&rougher) > falloff(&rough));
+        }
+
         #[test]
         fn lighting_with_pattern_applied() {
             let mut m = Material::new();
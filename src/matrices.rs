@@ -1,8 +1,8 @@
-use std::error::Error;
-
 use crate::{
     constants::EPSILON,
+    error::Error,
     tuples::{Point, Tuple, Vector},
+    utils::StableHasher,
 };
 
 #[derive(Debug, Clone)]
@@ -14,22 +14,84 @@ pub struct Matrix {
 
 impl Matrix {
     pub fn from_vec(data: Vec<Vec<f64>>) -> Self {
+        match Matrix::try_from_vec(data) {
+            Ok(m) => m,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn try_from_vec(data: Vec<Vec<f64>>) -> Result<Self, Error> {
         let rows = data.len();
-        let cols = data[0].len();
+        let cols = match data.first() {
+            Some(row) if !row.is_empty() => row.len(),
+            _ => return Err(Error::EmptyMatrix),
+        };
 
-        for row in &data {
-            if row.len() != cols {
-                panic!("The number of columns should equal in all rows.");
-            }
+        if data.iter().any(|row| row.len() != cols) {
+            return Err(Error::RaggedMatrix);
         }
 
-        Matrix { rows, cols, data }
+        Ok(Matrix { rows, cols, data })
     }
 
     pub fn at(&self, row: usize, col: usize) -> f64 {
         self.data[row][col]
     }
 
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    pub fn hash_into(&self, h: &mut StableHasher) {
+        h.write_usize(self.rows);
+        h.write_usize(self.cols);
+        for row in &self.data {
+            h.write_f64s(row);
+        }
+    }
+
+    // Returns the first (row, col) whose values differ by more than tol
+    pub fn first_difference(&self, other: &Matrix, tol: f64) -> Option<(usize, usize)> {
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                if (self.at(i, j) - other.at(i, j)).abs() > tol {
+                    return Some((i, j));
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn approx_eq(&self, other: &Matrix, tol: f64) -> bool {
+        self.dimensions() == other.dimensions() && self.first_difference(other, tol).is_none()
+    }
+
+    pub fn is_symmetric(&self, tol: f64) -> bool {
+        self.approx_eq(&self.clone().transpose(), tol)
+    }
+
+    // True when the columns are mutually perpendicular unit vectors, which is
+    // what a pure rotation should stay no matter how many are composed
+    pub fn is_orthogonal(&self, tol: f64) -> bool {
+        if self.rows != self.cols {
+            return false;
+        }
+
+        for a in 0..self.cols {
+            for b in a..self.cols {
+                let dot: f64 = (0..self.rows).map(|i| self.at(i, a) * self.at(i, b)).sum();
+                let expected = if a == b { 1.0 } else { 0.0 };
+
+                if (dot - expected).abs() > tol {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
     pub fn transpose(self) -> Self {
         let mut data = Vec::new();
         for j in 0..self.cols {
@@ -41,8 +103,8 @@ impl Matrix {
         }
 
         Matrix {
-            rows: self.rows,
-            cols: self.cols,
+            rows: self.cols,
+            cols: self.rows,
             data,
         }
     }
@@ -103,10 +165,10 @@ impl Matrix {
     }
 
     fn cofactor(&self, row: usize, col: usize) -> f64 {
-        if (row + col) % 2 == 0 {
+        if (row + col).is_multiple_of(2) {
             return self.minor(row, col);
         }
-        return -self.minor(row, col);
+        -self.minor(row, col)
     }
 
     fn is_invertible(&self) -> bool {
@@ -114,11 +176,20 @@ impl Matrix {
     }
 
     pub fn inverse(&self) -> Self {
-        if !self.is_invertible() {
-            panic!("The following matrix is not invertible: {:#?}", self);
+        match self.try_inverse() {
+            Some(inverse) => inverse,
+            None => panic!("The following matrix is not invertible: {:#?}", self),
         }
+    }
 
+    // Returns None instead of panicking when the matrix is singular. The
+    // determinant is only computed once and reused for every element.
+    pub fn try_inverse(&self) -> Option<Self> {
         let det = self.determinant();
+        if det == 0.0 {
+            return None;
+        }
+
         let mut data = Vec::new();
         for j in 0..self.cols {
             let mut row = Vec::new();
@@ -128,11 +199,16 @@ impl Matrix {
             data.push(row);
         }
 
-        Matrix {
+        Some(Matrix {
             rows: self.rows,
             cols: self.cols,
             data,
-        }
+        })
+    }
+
+    // Like try_inverse, for callers that report errors with the crate's Error type
+    pub fn checked_inverse(&self) -> Result<Self, Error> {
+        self.try_inverse().ok_or(Error::NonInvertibleMatrix)
     }
 
     pub fn identity() -> Self {
@@ -151,22 +227,44 @@ impl Matrix {
 
 impl PartialEq for Matrix {
     fn eq(&self, other: &Self) -> bool {
-        if self.cols != other.cols || self.rows != other.rows {
-            return false;
-        }
+        self.approx_eq(other, EPSILON)
+    }
+}
 
-        for i in 0..self.rows {
-            for j in 0..self.cols {
-                if (self.at(i, j) - other.at(i, j)) > EPSILON {
-                    return false;
-                }
-            }
+// Asserts two matrices are equal within a tolerance (EPSILON by default),
+// reporting the first differing cell instead of dumping both matrices
+#[cfg(test)]
+macro_rules! assert_matrix_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::matrices::assert_matrix_eq!($left, $right, $crate::constants::EPSILON)
+    };
+    ($left:expr, $right:expr, $tol:expr $(,)?) => {{
+        let left: &$crate::matrices::Matrix = &$left;
+        let right: &$crate::matrices::Matrix = &$right;
+
+        if left.dimensions() != right.dimensions() {
+            panic!(
+                "matrix dimensions differ: left is {:?}, right is {:?}",
+                left.dimensions(),
+                right.dimensions()
+            );
         }
 
-        return true;
-    }
+        if let Some((row, col)) = left.first_difference(right, $tol) {
+            panic!(
+                "matrices differ at ({}, {}): left = {}, right = {}",
+                row,
+                col,
+                left.at(row, col),
+                right.at(row, col)
+            );
+        }
+    }};
 }
 
+#[cfg(test)]
+pub(crate) use assert_matrix_eq;
+
 impl std::ops::Mul<Matrix> for Matrix {
     type Output = Self;
 
@@ -330,7 +428,7 @@ mod tests {
             vec![5.0, 4.0, 3.0, 2.0],
         ]);
 
-        assert_eq!(A, B);
+        assert_matrix_eq!(A, B);
     }
 
     #[test]
@@ -375,7 +473,7 @@ mod tests {
             vec![16.0, 26.0, 46.0, 42.0],
         ]);
 
-        assert_eq!(A * B, expected);
+        assert_matrix_eq!(A * B, expected);
     }
 
     #[test]
@@ -404,7 +502,7 @@ mod tests {
         let a = Point(1.0, 2.0, 3.0, 1.0);
         let identity_matrix = Matrix::identity();
 
-        assert_eq!(A.clone() * &identity_matrix, A);
+        assert_matrix_eq!(A.clone() * &identity_matrix, A);
         assert_eq!(identity_matrix * a, a);
     }
 
@@ -423,14 +521,14 @@ mod tests {
             vec![0.0, 8.0, 3.0, 8.0],
         ]);
 
-        assert_eq!(A.transpose(), expected);
+        assert_matrix_eq!(A.transpose(), expected);
     }
 
     #[test]
     fn transposing_identity_matrix() {
         let A = Matrix::identity().transpose();
 
-        assert_eq!(A, Matrix::identity());
+        assert_matrix_eq!(A, Matrix::identity());
     }
 
     #[test]
@@ -479,7 +577,7 @@ mod tests {
         ]);
         let expected = Matrix::from_vec(vec![vec![-3.0, 2.0], vec![0.0, 6.0]]);
 
-        assert_eq!(A.submatrix(0, 2), expected);
+        assert_matrix_eq!(A.submatrix(0, 2), expected);
     }
 
     #[test]
@@ -496,7 +594,7 @@ mod tests {
             vec![-7.0, -1.0, 1.0],
         ]);
 
-        assert_eq!(A.submatrix(2, 1), expected);
+        assert_matrix_eq!(A.submatrix(2, 1), expected);
     }
 
     #[test]
@@ -536,7 +634,7 @@ mod tests {
         ]);
 
         assert_eq!(A.determinant(), -2120.0);
-        assert_eq!(A.is_invertible(), true);
+        assert!(A.is_invertible());
     }
 
     #[test]
@@ -549,7 +647,67 @@ mod tests {
         ]);
 
         assert_eq!(A.determinant(), 0.0);
-        assert_eq!(A.is_invertible(), false);
+        assert!(!A.is_invertible());
+    }
+
+    #[test]
+    fn trying_to_invert_noninvertible_matrix() {
+        let A = Matrix::from_vec(vec![
+            vec![-4.0, 2.0, -2.0, -3.0],
+            vec![9.0, 6.0, 2.0, 6.0],
+            vec![0.0, -5.0, 1.0, -5.0],
+            vec![0.0, 0.0, 0.0, 0.0],
+        ]);
+
+        assert!(A.try_inverse().is_none());
+        assert_eq!(A.checked_inverse().unwrap_err(), Error::NonInvertibleMatrix);
+    }
+
+    #[test]
+    fn trying_to_build_matrix_from_empty_data() {
+        assert_eq!(
+            Matrix::try_from_vec(vec![]).unwrap_err(),
+            Error::EmptyMatrix
+        );
+        assert_eq!(
+            Matrix::try_from_vec(vec![vec![]]).unwrap_err(),
+            Error::EmptyMatrix
+        );
+    }
+
+    #[test]
+    fn trying_to_build_matrix_from_ragged_rows() {
+        let data = vec![vec![1.0, 2.0], vec![3.0]];
+
+        assert_eq!(Matrix::try_from_vec(data).unwrap_err(), Error::RaggedMatrix);
+    }
+
+    #[test]
+    #[should_panic(expected = "The following matrix is not invertible")]
+    fn inverting_noninvertible_matrix_panics() {
+        let A = Matrix::from_vec(vec![vec![1.0, 2.0], vec![2.0, 4.0]]);
+
+        A.inverse();
+    }
+
+    #[test]
+    fn trying_to_invert_invertible_matrix() {
+        let A = Matrix::from_vec(vec![
+            vec![-5.0, 2.0, 6.0, -8.0],
+            vec![1.0, -5.0, 1.0, 8.0],
+            vec![7.0, 7.0, -6.0, -7.0],
+            vec![1.0, -3.0, 7.0, 4.0],
+        ]);
+
+        assert_matrix_eq!(
+            A.try_inverse().unwrap(),
+            Matrix::from_vec(vec![
+                vec![0.21805, 0.45113, 0.24060, -0.04511],
+                vec![-0.80827, -1.45677, -0.44361, 0.52068],
+                vec![-0.07895, -0.22368, -0.05263, 0.19737],
+                vec![-0.52256, -0.81391, -0.30075, 0.30639],
+            ])
+        );
     }
 
     #[test]
@@ -567,7 +725,7 @@ mod tests {
         assert_eq!(B.at(3, 2), -160.0 / 532.0);
         assert_eq!(A.cofactor(3, 2), 105.0);
         assert_eq!(B.at(2, 3), 105.0 / 532.0);
-        assert_eq!(
+        assert_matrix_eq!(
             B,
             Matrix::from_vec(vec![
                 vec![0.21805, 0.45113, 0.24060, -0.04511],
@@ -584,7 +742,7 @@ mod tests {
             vec![-3.0, 0.0, -9.0, -4.0],
         ]);
 
-        assert_eq!(
+        assert_matrix_eq!(
             A.inverse(),
             Matrix::from_vec(vec![
                 vec![-0.15385, -0.15385, -0.28205, -0.53846],
@@ -601,7 +759,7 @@ mod tests {
             vec![-7.0, 6.0, 6.0, 2.0],
         ]);
 
-        assert_eq!(
+        assert_matrix_eq!(
             A.inverse(),
             Matrix::from_vec(vec![
                 vec![-0.04074, -0.07778, 0.14444, -0.22222],
@@ -628,6 +786,51 @@ mod tests {
         ]);
         let C = A.clone() * &B;
 
-        assert_eq!(C * B.inverse(), A);
+        assert_matrix_eq!(C * B.inverse(), A);
+    }
+
+    #[test]
+    fn identity_is_orthogonal_and_symmetric() {
+        let I = Matrix::identity();
+
+        assert!(I.is_orthogonal(EPSILON));
+        assert!(I.is_symmetric(EPSILON));
+    }
+
+    #[test]
+    fn non_square_matrix_is_not_orthogonal() {
+        let A = Matrix::from_vec(vec![vec![1.0, 0.0, 0.0], vec![0.0, 1.0, 0.0]]);
+
+        assert!(!A.is_orthogonal(EPSILON));
+        assert!(!A.is_symmetric(EPSILON));
+    }
+
+    #[test]
+    fn approximate_equality_with_explicit_tolerance() {
+        let A = Matrix::identity();
+        let B = Matrix::from_vec(vec![
+            vec![1.0, 0.0, 0.0, 0.0],
+            vec![0.0, 1.0, 0.001, 0.0],
+            vec![0.0, 0.0, 1.0, 0.0],
+            vec![0.0, 0.0, 0.0, 1.0],
+        ]);
+
+        assert!(!A.approx_eq(&B, EPSILON));
+        assert!(A.approx_eq(&B, 0.01));
+        assert_eq!(A.first_difference(&B, EPSILON), Some((1, 2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "matrices differ at (1, 2): left = 0, right = 0.001")]
+    fn assert_matrix_eq_reports_first_differing_cell() {
+        let A = Matrix::identity();
+        let B = Matrix::from_vec(vec![
+            vec![1.0, 0.0, 0.0, 0.0],
+            vec![0.0, 1.0, 0.001, 0.0],
+            vec![0.0, 0.0, 1.0, 0.5],
+            vec![0.0, 0.0, 0.0, 1.0],
+        ]);
+
+        assert_matrix_eq!(A, B);
     }
 }
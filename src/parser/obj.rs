@@ -0,0 +1,592 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+};
+
+use crate::{
+    constants::EPSILON,
+    shapes::{group::Group, smooth_triangle::SmoothTriangle, triangle::Triangle},
+    tuples::{Point, Vector},
+};
+
+// Geometry read from a Wavefront OBJ file. Only vertices, texture
+// coordinates, vertex normals and faces are understood, every other line is
+// skipped and counted.
+#[derive(Debug)]
+pub struct ParsedObj {
+    // OBJ indices start at 1, see ParsedObj::vertex and ParsedObj::normal
+    pub vertices: Vec<Point>,
+    pub texture_coords: Vec<(f64, f64)>,
+    pub normals: Vec<Vector>,
+    // Every face in the order they were read. Faces with more than three
+    // corners are split up, see fan_triangulate.
+    pub faces: Vec<[CornerIndices; 3]>,
+    // The same faces as shapes, split by kind. Faces giving a normal for
+    // every corner become smooth triangles.
+    pub triangles: Vec<Triangle>,
+    pub smooth_triangles: Vec<SmoothTriangle>,
+    pub ignored_lines: usize,
+}
+
+// The 1-based indices a face corner gives for its vertex and, if it has
+// them, its texture coordinates and normal
+pub type CornerIndices = (usize, Option<usize>, Option<usize>);
+
+enum FaceTriangle {
+    Flat(Triangle),
+    Smooth(SmoothTriangle),
+}
+
+impl ParsedObj {
+    // The vertex with the given 1-based index, as faces refer to it
+    pub fn vertex(&self, index: usize) -> Point {
+        self.vertices[index - 1]
+    }
+
+    // The texture coordinates with the given 1-based index
+    pub fn texture_coord(&self, index: usize) -> (f64, f64) {
+        self.texture_coords[index - 1]
+    }
+
+    // The vertex normal with the given 1-based index
+    pub fn normal(&self, index: usize) -> Vector {
+        self.normals[index - 1]
+    }
+
+    // Replaces the normals with one per vertex, the average of the faces
+    // around it weighted by their area, and makes every face smooth with them.
+    // Faces touching a vertex that ends up without a normal stay flat.
+    pub fn compute_normals(&mut self) {
+        let mut sums = vec![Vector::new(0.0, 0.0, 0.0); self.vertices.len()];
+        for face in &self.faces {
+            let [p1, p2, p3] = face.map(|(v, _, _)| self.vertex(v));
+            // Same winding as Triangle::new, as long as its area
+            let normal = (p3 - p1).cross(&(p2 - p1));
+
+            for &(v, _, _) in face {
+                sums[v - 1] = sums[v - 1] + normal;
+            }
+        }
+
+        // Vertices outside every face, or only on faces without area, have no
+        // normal to speak of
+        self.normals = sums
+            .into_iter()
+            .map(|n| {
+                if n.magnitude() < EPSILON {
+                    n
+                } else {
+                    n.normalize()
+                }
+            })
+            .collect();
+
+        self.triangles.clear();
+        self.smooth_triangles.clear();
+        for face in std::mem::take(&mut self.faces) {
+            let face = face.map(|(v, vt, _)| {
+                let vn = (self.normal(v).magnitude() >= EPSILON).then_some(v);
+
+                (v, vt, vn)
+            });
+            self.add_face(face);
+        }
+    }
+
+    pub fn write_obj(&self, path: &str) -> io::Result<()> {
+        let mut file = File::create(path)?;
+
+        write!(file, "{}", self.construct_obj())?;
+
+        Ok(())
+    }
+
+    // Every face in a single group in the order they were read, ready to add
+    // to a World
+    pub fn to_group(&self) -> Group {
+        let mut group = Group::new();
+        for face in &self.faces {
+            match self.face_triangle(face) {
+                FaceTriangle::Flat(triangle) => group.add_child(Box::new(triangle)),
+                FaceTriangle::Smooth(triangle) => group.add_child(Box::new(triangle)),
+            }
+        }
+
+        group
+    }
+
+    // Handles one line, returning false when it isn't a record we know how
+    // to read
+    fn parse_line(&mut self, line: &str) -> bool {
+        let mut fields = line.split_whitespace();
+
+        match fields.next() {
+            Some("v") => match parse_floats(fields).as_deref() {
+                Some(&[x, y, z]) => {
+                    self.vertices.push(Point::new(x, y, z));
+                    true
+                }
+                _ => false,
+            },
+            // An optional third w coordinate is only used by 3D textures
+            Some("vt") => match parse_floats(fields).as_deref() {
+                Some(&[u, v]) | Some(&[u, v, _]) => {
+                    self.texture_coords.push((u, v));
+                    true
+                }
+                _ => false,
+            },
+            Some("vn") => match parse_floats(fields).as_deref() {
+                Some(&[x, y, z]) => {
+                    self.normals.push(Vector::new(x, y, z));
+                    true
+                }
+                _ => false,
+            },
+            Some("f") => match self.face_corners(fields) {
+                Some(corners) if corners.len() >= 3 => {
+                    self.fan_triangulate(&corners);
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    // The corners of a face, each written as v, v/vt, v//vn or v/vt/vn.
+    // A vt index that names no texture coordinates only leaves the corner
+    // untextured, since plenty of files carry vt indices without vt lines.
+    fn face_corners<'a>(
+        &self,
+        fields: impl Iterator<Item = &'a str>,
+    ) -> Option<Vec<CornerIndices>> {
+        fields
+            .map(|field| {
+                let mut indices = field.split('/');
+                let v = check_index(&self.vertices, indices.next()?)?;
+                let vt = indices
+                    .next()
+                    .and_then(|index| check_index(&self.texture_coords, index));
+                let vn = match indices.next() {
+                    Some(index) => Some(check_index(&self.normals, index)?),
+                    None => None,
+                };
+
+                Some((v, vt, vn))
+            })
+            .collect()
+    }
+
+    // Splits a convex polygon into triangles that all share its first corner
+    fn fan_triangulate(&mut self, corners: &[CornerIndices]) {
+        for pair in corners[1..].windows(2) {
+            self.add_face([corners[0], pair[0], pair[1]]);
+        }
+    }
+
+    // Adds a face whose indices have all been checked
+    fn add_face(&mut self, face: [CornerIndices; 3]) {
+        match self.face_triangle(&face) {
+            FaceTriangle::Flat(triangle) => self.triangles.push(triangle),
+            FaceTriangle::Smooth(triangle) => self.smooth_triangles.push(triangle),
+        }
+        self.faces.push(face);
+    }
+
+    // The triangle a face stands for. Triangles only get texture coordinates
+    // if all three corners have them.
+    fn face_triangle(&self, face: &[CornerIndices; 3]) -> FaceTriangle {
+        let [p1, p2, p3] = face.map(|(v, _, _)| self.vertex(v));
+        let texture_uvs = match face.map(|(_, vt, _)| vt) {
+            [Some(vt1), Some(vt2), Some(vt3)] => {
+                Some([vt1, vt2, vt3].map(|i| self.texture_coord(i)))
+            }
+            _ => None,
+        };
+
+        match face.map(|(_, _, vn)| vn) {
+            [Some(vn1), Some(vn2), Some(vn3)] => {
+                let [n1, n2, n3] = [vn1, vn2, vn3].map(|i| self.normal(i));
+                let mut triangle = SmoothTriangle::new(p1, p2, p3, n1, n2, n3);
+                triangle.texture_uvs = texture_uvs;
+                FaceTriangle::Smooth(triangle)
+            }
+            _ => {
+                let mut triangle = Triangle::new(p1, p2, p3);
+                triangle.texture_uvs = texture_uvs;
+                FaceTriangle::Flat(triangle)
+            }
+        }
+    }
+
+    // The mesh as OBJ text, with each face written as a triangle
+    fn construct_obj(&self) -> String {
+        let mut obj = String::new();
+
+        for p in &self.vertices {
+            obj += &format!("v {} {} {}\n", p.0, p.1, p.2);
+        }
+        for (u, v) in &self.texture_coords {
+            obj += &format!("vt {} {}\n", u, v);
+        }
+        for n in &self.normals {
+            obj += &format!("vn {} {} {}\n", n.0, n.1, n.2);
+        }
+        for face in &self.faces {
+            let corners = face.map(|corner| match corner {
+                (v, None, None) => format!("{}", v),
+                (v, Some(vt), None) => format!("{}/{}", v, vt),
+                (v, None, Some(vn)) => format!("{}//{}", v, vn),
+                (v, Some(vt), Some(vn)) => format!("{}/{}/{}", v, vt, vn),
+            });
+            obj += &format!("f {}\n", corners.join(" "));
+        }
+
+        obj
+    }
+}
+
+// Parses OBJ text. Lines that aren't vertex or face records, or that are but
+// can't be read, are left out and counted in ignored_lines rather than
+// failing the whole file.
+pub fn parse_obj(text: &str) -> ParsedObj {
+    let mut parsed = ParsedObj {
+        vertices: Vec::new(),
+        texture_coords: Vec::new(),
+        normals: Vec::new(),
+        faces: Vec::new(),
+        triangles: Vec::new(),
+        smooth_triangles: Vec::new(),
+        ignored_lines: 0,
+    };
+
+    // Blank lines are just spacing, not records that were left out
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        if !parsed.parse_line(line) {
+            parsed.ignored_lines += 1;
+        }
+    }
+
+    parsed
+}
+
+// The 1-based index, if it names an element of the list
+fn check_index<T>(list: &[T], index: &str) -> Option<usize> {
+    let index: usize = index.parse().ok()?;
+
+    (1..=list.len()).contains(&index).then_some(index)
+}
+
+fn parse_floats<'a>(fields: impl Iterator<Item = &'a str>) -> Option<Vec<f64>> {
+    fields.map(|field| field.parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{constants::EPSILON, rays::Ray, shapes::Shape};
+
+    use super::*;
+
+    #[test]
+    fn ignoring_unrecognized_lines() {
+        let gibberish = "There was a young lady named Bright
+who traveled much faster than light.
+She set out one day
+in a relative way,
+and came back the previous night.";
+
+        let parsed = parse_obj(gibberish);
+
+        assert_eq!(parsed.ignored_lines, 5);
+        assert!(parsed.vertices.is_empty());
+        assert!(parsed.triangles.is_empty());
+    }
+
+    #[test]
+    fn vertex_records() {
+        let file = "v -1 1 0
+v -1.0000 0.5000 0.0000
+v 1 0 0
+v 1 1 0";
+
+        let parsed = parse_obj(file);
+
+        assert_eq!(parsed.ignored_lines, 0);
+        assert_eq!(parsed.vertex(1), Point::new(-1.0, 1.0, 0.0));
+        assert_eq!(parsed.vertex(2), Point::new(-1.0, 0.5, 0.0));
+        assert_eq!(parsed.vertex(3), Point::new(1.0, 0.0, 0.0));
+        assert_eq!(parsed.vertex(4), Point::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn parsing_triangle_faces() {
+        let file = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+
+f 1 2 3
+f 1 3 4";
+
+        let parsed = parse_obj(file);
+        let t1 = &parsed.triangles[0];
+        let t2 = &parsed.triangles[1];
+
+        assert_eq!(parsed.triangles.len(), 2);
+        assert_eq!(t1.p1, parsed.vertex(1));
+        assert_eq!(t1.p2, parsed.vertex(2));
+        assert_eq!(t1.p3, parsed.vertex(3));
+        assert_eq!(t2.p1, parsed.vertex(1));
+        assert_eq!(t2.p2, parsed.vertex(3));
+        assert_eq!(t2.p3, parsed.vertex(4));
+    }
+
+    #[test]
+    fn triangulating_polygons() {
+        let file = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+v 0 2 0
+
+f 1 2 3 4 5";
+
+        let parsed = parse_obj(file);
+        let t1 = &parsed.triangles[0];
+        let t2 = &parsed.triangles[1];
+        let t3 = &parsed.triangles[2];
+
+        assert_eq!(parsed.triangles.len(), 3);
+        assert_eq!(t1.p1, parsed.vertex(1));
+        assert_eq!(t1.p2, parsed.vertex(2));
+        assert_eq!(t1.p3, parsed.vertex(3));
+        assert_eq!(t2.p1, parsed.vertex(1));
+        assert_eq!(t2.p2, parsed.vertex(3));
+        assert_eq!(t2.p3, parsed.vertex(4));
+        assert_eq!(t3.p1, parsed.vertex(1));
+        assert_eq!(t3.p2, parsed.vertex(4));
+        assert_eq!(t3.p3, parsed.vertex(5));
+    }
+
+    #[test]
+    fn faces_with_unknown_vertices_are_ignored() {
+        let file = "v 0 0 0
+v 1 0 0
+v 0 1 0
+f 1 2 4
+f 0 1 2
+f 1 2 3";
+
+        let parsed = parse_obj(file);
+
+        assert_eq!(parsed.ignored_lines, 2);
+        assert_eq!(parsed.triangles.len(), 1);
+    }
+
+    #[test]
+    fn converting_parsed_obj_to_group() {
+        let file = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+f 1 2 3
+f 1 3 4";
+
+        let parsed = parse_obj(file);
+        let g = parsed.to_group();
+
+        assert_eq!(g.children.len(), 2);
+        assert!(g.children[0].equals(&parsed.triangles[0]));
+        assert!(g.children[1].equals(&parsed.triangles[1]));
+    }
+
+    #[test]
+    fn vertex_normal_records() {
+        let file = "vn 0 0 1
+vn 0.707 0 -0.707
+vn 1 2 3";
+
+        let parsed = parse_obj(file);
+
+        assert_eq!(parsed.ignored_lines, 0);
+        assert_eq!(parsed.normal(1), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(parsed.normal(2), Vector::new(0.707, 0.0, -0.707));
+        assert_eq!(parsed.normal(3), Vector::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn faces_with_normals() {
+        let file = "v 0 1 0
+v -1 0 0
+v 1 0 0
+
+vn -1 0 0
+vn 1 0 0
+vn 0 1 0
+
+f 1//3 2//1 3//2
+f 1/0/3 2/102/1 3/14/2";
+
+        let parsed = parse_obj(file);
+
+        assert!(parsed.triangles.is_empty());
+        assert_eq!(parsed.smooth_triangles.len(), 2);
+        for t in &parsed.smooth_triangles {
+            assert_eq!(t.p1, parsed.vertex(1));
+            assert_eq!(t.p2, parsed.vertex(2));
+            assert_eq!(t.p3, parsed.vertex(3));
+            assert_eq!(t.n1, parsed.normal(3));
+            assert_eq!(t.n2, parsed.normal(1));
+            assert_eq!(t.n3, parsed.normal(2));
+        }
+        assert_eq!(parsed.to_group().children.len(), 2);
+    }
+
+    #[test]
+    fn faces_with_unknown_normals_are_ignored() {
+        let file = "v 0 1 0
+v -1 0 0
+v 1 0 0
+vn 0 0 -1
+f 1//1 2//1 3//2
+f 1/1 2/1 3/1";
+
+        let parsed = parse_obj(file);
+
+        assert_eq!(parsed.ignored_lines, 1);
+        assert!(parsed.smooth_triangles.is_empty());
+        // Texture coordinates alone don't make a face smooth
+        assert_eq!(parsed.triangles.len(), 1);
+    }
+
+    #[test]
+    fn texture_coordinate_records() {
+        let file = "vt 0 0
+vt 0.5 1 0
+vt 1 0.25";
+
+        let parsed = parse_obj(file);
+
+        assert_eq!(parsed.ignored_lines, 0);
+        assert_eq!(parsed.texture_coord(1), (0.0, 0.0));
+        assert_eq!(parsed.texture_coord(2), (0.5, 1.0));
+        assert_eq!(parsed.texture_coord(3), (1.0, 0.25));
+    }
+
+    #[test]
+    fn faces_with_texture_coordinates_interpolate_them() {
+        let file = "v 0 1 0
+v -1 0 0
+v 1 0 0
+vt 0 0
+vt 0.5 1
+vt 1 0.25
+vn 0 0 -1
+f 1/1 2/2 3/3
+f 1/1/1 2/2/1 3/3/1";
+
+        let parsed = parse_obj(file);
+        let flat = &parsed.triangles[0];
+        let smooth = &parsed.smooth_triangles[0];
+
+        let centroid = Point::new(0.0, 1.0 / 3.0, 0.0);
+        let r = Ray::new(
+            centroid - Vector::new(0.0, 0.0, 2.0),
+            Vector::new(0.0, 0.0, 1.0),
+        );
+        let average = (0.5, 1.25 / 3.0);
+
+        for shape in [flat as &dyn Shape, smooth] {
+            let xs = shape.intersect(r);
+            let (u, v) = shape.texture_uv(xs[0].uv).unwrap();
+
+            assert!((u - average.0).abs() < EPSILON);
+            assert!((v - average.1).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn writing_computed_normals_back_out() {
+        let file = "v 0 1 0
+v -1 0 0
+v 1 0 0
+v 0 0 1
+f 1 2 3
+f 1 3 4";
+
+        let mut parsed = parse_obj(file);
+        parsed.compute_normals();
+
+        assert!(parsed.triangles.is_empty());
+        assert_eq!(parsed.smooth_triangles.len(), 2);
+        // Shared by both faces, so it leans towards each of them
+        assert_eq!(parsed.normal(1), Vector::new(1.0, 1.0, -1.0).normalize());
+
+        let written = parsed.construct_obj();
+        let reparsed = parse_obj(&written);
+
+        assert!(written.lines().any(|line| line == "f 1//1 3//3 4//4"));
+        assert_eq!(reparsed.ignored_lines, 0);
+        assert_eq!(reparsed.normals, parsed.normals);
+        assert_eq!(reparsed.faces, parsed.faces);
+        assert_eq!(reparsed.smooth_triangles, parsed.smooth_triangles);
+    }
+
+    #[test]
+    fn faces_on_vertices_without_normals_stay_flat() {
+        let file = "v 0 1 0
+v -1 0 0
+v 1 0 0
+v 2 0 0
+v 3 0 0
+f 1 2 3
+f 3 4 5";
+
+        let mut parsed = parse_obj(file);
+        parsed.compute_normals();
+
+        // The second face is a line, so it adds nothing to its vertices
+        assert_eq!(parsed.normal(4), Vector::new(0.0, 0.0, 0.0));
+        assert_eq!(parsed.smooth_triangles.len(), 1);
+        assert_eq!(parsed.triangles.len(), 1);
+        assert_eq!(
+            parsed.faces[1],
+            [(3, None, Some(3)), (4, None, None), (5, None, None)]
+        );
+    }
+
+    #[test]
+    fn blank_lines_are_not_counted_as_ignored() {
+        let file = "v 0 1 0
+
+v -1 0 0
+\t
+v 1 0 0
+";
+
+        let parsed = parse_obj(file);
+
+        assert_eq!(parsed.ignored_lines, 0);
+        assert_eq!(parsed.vertices.len(), 3);
+    }
+
+    #[test]
+    fn group_keeps_flat_and_smooth_faces_in_file_order() {
+        let file = "v 0 1 0
+v -1 0 0
+v 1 0 0
+vn 0 0 -1
+f 1//1 2//1 3//1
+f 1 2 3
+f 1//1 3//1 2//1";
+
+        let parsed = parse_obj(file);
+        let g = parsed.to_group();
+
+        assert_eq!(g.children.len(), 3);
+        assert!(g.children[0].equals(&parsed.smooth_triangles[0]));
+        assert!(g.children[1].equals(&parsed.triangles[0]));
+        assert!(g.children[2].equals(&parsed.smooth_triangles[1]));
+    }
+}
@@ -1,28 +1,93 @@
-use crate::{color::Color, matrices::Matrix, shapes::Shape, tuples::Point};
+use crate::{
+    canvas::Canvas,
+    color::Color,
+    constants::EPSILON,
+    matrices::Matrix,
+    shapes::Shape,
+    tuples::{Point, Vector},
+    utils::StableHasher,
+};
 use std::{any::Any, fmt::Debug};
 
 pub mod blended;
 pub mod checker;
+pub mod coordinate;
 pub mod gradient;
 pub mod radial_gradient;
 pub mod ring;
 pub mod solid;
 pub mod stripe;
 
-pub trait Pattern: Debug {
+pub trait Pattern: Debug + Send + Sync {
+    // The color at a point already in this pattern's space. Callers apply
+    // the pattern's transformation first, so combinators like Stripe or
+    // Blended transform the point into each sub-pattern's space before
+    // sampling it.
     fn at(&self, point: Point) -> Color;
 
     fn at_object(&self, object: &dyn Shape, world_point: Point) -> Color {
-        let object_point = object.transformation().inverse() * world_point;
+        let object_point = object.world_to_object(world_point);
         let pattern_point = self.transformation().inverse() * object_point;
 
-        self.at(pattern_point)
+        // Transforms leave values like 1.0000000002 that would otherwise land
+        // on the wrong side of a pattern boundary
+        self.at(pattern_point.snap(EPSILON))
+    }
+
+    // Renders the pattern as seen from above on an xz plane centered at the
+    // origin, without any lighting. `scale` is the size of a pixel in world
+    // units.
+    fn preview(&self, width: usize, height: usize, scale: f64) -> Canvas {
+        let mut canvas = Canvas::new(width, height);
+        let inverse = self.transformation().inverse();
+
+        for y in 0..height {
+            for x in 0..width {
+                let world_x = (x as f64 + 0.5 - width as f64 / 2.0) * scale;
+                let world_z = (height as f64 / 2.0 - y as f64 - 0.5) * scale;
+                let point = inverse.clone() * Point::new(world_x, 0.0, world_z);
+
+                canvas.write_pixel(x, y, self.at(point));
+            }
+        }
+
+        canvas
+    }
+
+    // Like at_object, but told roughly how wide an area of the surface the
+    // sample stands for (in world units), so patterns can blur out detail
+    // that is too fine to see
+    fn at_hit(&self, object: &dyn Shape, world_point: Point, footprint: f64) -> Color {
+        let inverse = self.transformation().inverse() * object.world_transformation().inverse();
+        let pattern_point = inverse.clone() * world_point;
+
+        // Scale the footprint into pattern space by how much the transforms
+        // stretch each axis on average
+        let stretch = [
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+            Vector::new(0.0, 0.0, 1.0),
+        ]
+        .iter()
+        .map(|&axis| (inverse.clone() * axis).magnitude())
+        .sum::<f64>()
+            / 3.0;
+
+        self.at_footprint(pattern_point.snap(EPSILON), footprint * stretch)
+    }
+
+    // The color at a pattern space point, averaged over footprint
+    fn at_footprint(&self, point: Point, _footprint: f64) -> Color {
+        self.at(point)
     }
 
     fn transformation(&self) -> Matrix;
 
     fn set_transformation(&mut self, m: Matrix);
 
+    // Feeds the pattern's type and every field into h, for World::scene_hash
+    fn hash_into(&self, h: &mut StableHasher);
+
     fn as_any(&self) -> &dyn Any;
 
     fn equals(&self, other: &dyn Pattern) -> bool;
@@ -54,6 +119,10 @@ impl Pattern for TestPattern {
         self.transformation = m;
     }
 
+    fn hash_into(&self, _: &mut StableHasher) {
+        todo!()
+    }
+
     fn as_any(&self) -> &dyn Any {
         todo!()
     }
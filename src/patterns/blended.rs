@@ -1,6 +1,11 @@
 use std::any::Any;
 
-use crate::{color::Color, constants::EPSILON, matrices::Matrix, utils::zero_if_trivial};
+use crate::{
+    color::Color,
+    constants::EPSILON,
+    matrices::Matrix,
+    utils::{zero_if_trivial, StableHasher},
+};
 
 use super::Pattern;
 
@@ -27,6 +32,13 @@ impl Pattern for Blended {
         self.transformation = m;
     }
 
+    fn hash_into(&self, h: &mut StableHasher) {
+        h.write_str("Blended");
+        self.transformation.hash_into(h);
+        self.a.hash_into(h);
+        self.b.hash_into(h);
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -65,3 +77,55 @@ impl Blended {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        patterns::stripe::Stripe,
+        patterns::{checker::Checker, solid::Solid},
+        shapes::sphere::Sphere,
+        transformation::scaling,
+        tuples::Point,
+    };
+
+    use super::*;
+
+    #[test]
+    fn nested_patterns_keep_their_own_transformation() {
+        let mut checker = Checker::new(
+            Box::new(Solid::new(Color::white())),
+            Box::new(Solid::new(Color::black())),
+        );
+        checker.set_transformation(scaling(2.0, 2.0, 2.0));
+        let blended = Blended::new(Box::new(checker), Box::new(Solid::new(Color::white())));
+
+        // An unscaled checker would turn black past x = 1
+        assert_eq!(blended.at(Point::new(1.5, 0.0, 0.0)), Color::white());
+        assert_eq!(blended.at(Point::new(2.5, 0.0, 0.0)), Color::black());
+        assert_eq!(
+            blended.at_object(&Sphere::new(), Point::new(1.5, 0.0, 0.0)),
+            Color::white()
+        );
+    }
+
+    #[test]
+    fn scaling_blended_pattern_changes_its_output() {
+        let stripes = || {
+            Box::new(Stripe::new(
+                Box::new(Solid::new(Color::white())),
+                Box::new(Solid::new(Color(0.5, 0.5, 0.5))),
+            ))
+        };
+        let unscaled = Blended::new(stripes(), stripes());
+        let mut scaled = Blended::new(stripes(), stripes());
+        scaled.set_transformation(scaling(2.0, 2.0, 2.0));
+        let object = Sphere::new();
+        let point = Point::new(1.5, 0.0, 0.0);
+
+        // Like any pattern, its own transformation is applied by the caller
+        // before at, so it only shows up when sampling through an object
+        assert_eq!(unscaled.at_object(&object, point), Color(0.25, 0.25, 0.25));
+        assert_eq!(scaled.at_object(&object, point), Color::white());
+        assert_eq!(scaled.at(point), unscaled.at(point));
+    }
+}
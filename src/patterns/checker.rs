@@ -1,6 +1,8 @@
 use std::any::Any;
 
-use crate::{color::Color, constants::EPSILON, matrices::Matrix, utils::zero_if_trivial};
+use crate::{
+    color::Color, constants::EPSILON, matrices::Matrix, tuples::Point, utils::StableHasher,
+};
 
 use super::Pattern;
 
@@ -9,15 +11,14 @@ pub struct Checker {
     a: Box<dyn Pattern>,
     b: Box<dyn Pattern>,
     transformation: Matrix,
+    // Fade towards the average of both patterns as the footprint of a sample
+    // grows past half a square, like a mipmap would
+    fade: bool,
 }
 
 impl Pattern for Checker {
-    fn at(&self, point: crate::tuples::Point) -> Color {
-        let (x, y, z) = (
-            zero_if_trivial(point.0),
-            zero_if_trivial(point.1),
-            zero_if_trivial(point.2),
-        );
+    fn at(&self, point: Point) -> Color {
+        let Point(x, y, z, _) = point.snap(EPSILON);
 
         if (x.floor() as i32 + y.floor() as i32 + z.floor() as i32) % 2 == 0 {
             let local_pattern_point = self.a.transformation().inverse() * point;
@@ -28,6 +29,25 @@ impl Pattern for Checker {
         self.b.at(local_pattern_point)
     }
 
+    fn at_footprint(&self, point: Point, footprint: f64) -> Color {
+        let color = self.at(point);
+        if !self.fade {
+            return color;
+        }
+
+        // Full contrast up to half a square, fully averaged at a whole square
+        let fade = ((footprint - 0.5) / 0.5).clamp(0.0, 1.0);
+        if fade == 0.0 {
+            return color;
+        }
+
+        let average = (self.a.at(self.a.transformation().inverse() * point)
+            + self.b.at(self.b.transformation().inverse() * point))
+            * 0.5;
+
+        color * (1.0 - fade) + average * fade
+    }
+
     fn transformation(&self) -> Matrix {
         self.transformation.clone()
     }
@@ -36,6 +56,14 @@ impl Pattern for Checker {
         self.transformation = m;
     }
 
+    fn hash_into(&self, h: &mut StableHasher) {
+        h.write_str("Checker");
+        self.transformation.hash_into(h);
+        self.a.hash_into(h);
+        self.b.hash_into(h);
+        h.write_bool(self.fade);
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -45,6 +73,7 @@ impl Pattern for Checker {
             self.a.equals(other.a.as_ref())
                 && self.b.equals(other.b.as_ref())
                 && self.transformation == other.transformation
+                && self.fade == other.fade
         } else {
             false
         }
@@ -61,6 +90,7 @@ impl Clone for Checker {
             a: self.a.clone(),
             b: self.b.clone(),
             transformation: self.transformation.clone(),
+            fade: self.fade,
         }
     }
 }
@@ -71,16 +101,55 @@ impl Checker {
             a,
             b,
             transformation: Matrix::identity(),
+            fade: false,
         }
     }
+
+    pub fn set_fade(&mut self, fade: bool) {
+        self.fade = fade;
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{patterns::solid::Solid, tuples::Point};
+    use crate::{patterns::solid::Solid, shapes::sphere::Sphere};
 
     use super::*;
 
+    #[test]
+    fn fading_checker_averages_large_footprints() {
+        let mut checker = Checker::new(
+            Box::new(Solid::new(Color::white())),
+            Box::new(Solid::new(Color::black())),
+        );
+        let object = Sphere::new();
+        let point = Point::new(0.5, 0.0, 0.5);
+
+        // Without fading the footprint is ignored
+        assert_eq!(checker.at_hit(&object, point, 5.0), Color::white());
+
+        checker.set_fade(true);
+        assert_eq!(checker.at_hit(&object, point, 0.01), Color::white());
+        assert_eq!(checker.at_hit(&object, point, 5.0), Color(0.5, 0.5, 0.5));
+        assert_eq!(
+            checker.at_hit(&object, point, 0.75),
+            Color(0.75, 0.75, 0.75)
+        );
+    }
+
+    #[test]
+    fn checker_is_stable_across_nearly_integer_points() {
+        let checker = Checker::new(
+            Box::new(Solid::new(Color::white())),
+            Box::new(Solid::new(Color::black())),
+        );
+
+        assert_eq!(
+            checker.at(Point::new(0.9999999999, 0.0, 2.0000000001)),
+            checker.at(Point::new(1.0, 0.0, 2.0))
+        );
+    }
+
     #[test]
     fn checker_should_repeat_in_x() {
         let checker = Checker::new(
@@ -0,0 +1,77 @@
+use std::any::Any;
+
+use crate::{
+    color::Color, constants::EPSILON, matrices::Matrix, tuples::Point, utils::StableHasher,
+};
+
+use super::Pattern;
+
+// Debugging pattern showing where on a surface each point is, by using the
+// fractional parts of its x, y and z as red, green and blue
+#[derive(Debug, Clone)]
+pub struct Coordinate {
+    transformation: Matrix,
+}
+
+impl Pattern for Coordinate {
+    fn at(&self, point: Point) -> Color {
+        let p = point.snap(EPSILON);
+        let fraction = |v: f64| v - v.floor();
+
+        Color(fraction(p.0), fraction(p.1), fraction(p.2))
+    }
+
+    fn transformation(&self) -> Matrix {
+        self.transformation.clone()
+    }
+
+    fn set_transformation(&mut self, m: Matrix) {
+        self.transformation = m;
+    }
+
+    fn clone_box(&self) -> Box<dyn Pattern> {
+        Box::new(self.clone())
+    }
+
+    fn hash_into(&self, h: &mut StableHasher) {
+        h.write_str("Coordinate");
+        self.transformation.hash_into(h);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn equals(&self, other: &dyn Pattern) -> bool {
+        if let Some(other) = other.as_any().downcast_ref::<Coordinate>() {
+            self.transformation == other.transformation
+        } else {
+            false
+        }
+    }
+}
+
+impl Coordinate {
+    pub fn new() -> Self {
+        Self {
+            transformation: Matrix::identity(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coordinate_maps_fractional_position_to_color() {
+        let pattern = Coordinate::new();
+
+        assert_eq!(pattern.at(Point::origin()), Color::black());
+        assert_eq!(pattern.at(Point::new(0.5, 0.0, 1.0)), Color(0.5, 0.0, 0.0));
+        assert_eq!(
+            pattern.at(Point::new(-0.25, 2.75, 0.1)),
+            Color(0.75, 0.75, 0.1)
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use std::any::Any;
 
-use crate::{color::Color, matrices::Matrix, utils::zero_if_trivial};
+use crate::{color::Color, constants::EPSILON, matrices::Matrix, utils::StableHasher};
 
 use super::Pattern;
 
@@ -14,7 +14,8 @@ pub struct Gradient {
 impl Pattern for Gradient {
     fn at(&self, point: crate::tuples::Point) -> Color {
         let distance = self.b - self.a;
-        let fraction = point.0 - zero_if_trivial(point.0).floor();
+        let x = point.snap(EPSILON).0;
+        let fraction = x - x.floor();
 
         self.a + distance * fraction
     }
@@ -31,6 +32,13 @@ impl Pattern for Gradient {
         Box::new(self.clone())
     }
 
+    fn hash_into(&self, h: &mut StableHasher) {
+        h.write_str("Gradient");
+        self.transformation.hash_into(h);
+        let (a, b) = (self.a, self.b);
+        h.write_f64s(&[a.0, a.1, a.2, b.0, b.1, b.2]);
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
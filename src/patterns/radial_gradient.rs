@@ -1,6 +1,10 @@
 use std::any::Any;
 
-use crate::{color::Color, matrices::Matrix, utils::zero_if_trivial};
+use crate::{
+    color::Color,
+    matrices::Matrix,
+    utils::{zero_if_trivial, StableHasher},
+};
 
 use super::Pattern;
 
@@ -33,6 +37,13 @@ impl Pattern for RadialGradient {
         Box::new(self.clone())
     }
 
+    fn hash_into(&self, h: &mut StableHasher) {
+        h.write_str("RadialGradient");
+        self.transformation.hash_into(h);
+        let (a, b) = (self.a, self.b);
+        h.write_f64s(&[a.0, a.1, a.2, b.0, b.1, b.2]);
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
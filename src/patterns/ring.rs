@@ -1,6 +1,10 @@
 use std::any::Any;
 
-use crate::{color::Color, matrices::Matrix, utils::zero_if_trivial};
+use crate::{
+    color::Color,
+    matrices::Matrix,
+    utils::{zero_if_trivial, StableHasher},
+};
 
 use super::Pattern;
 
@@ -34,6 +38,13 @@ impl Pattern for Ring {
         Box::new(self.clone())
     }
 
+    fn hash_into(&self, h: &mut StableHasher) {
+        h.write_str("Ring");
+        self.transformation.hash_into(h);
+        self.a.hash_into(h);
+        self.b.hash_into(h);
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -1,6 +1,10 @@
 use std::any::Any;
 
-use crate::{color::Color, matrices::Matrix, utils::zero_if_trivial};
+use crate::{
+    color::Color,
+    matrices::Matrix,
+    utils::{zero_if_trivial, StableHasher},
+};
 
 use super::Pattern;
 
@@ -27,6 +31,13 @@ impl Pattern for Solid {
         Box::new(self.clone())
     }
 
+    fn hash_into(&self, h: &mut StableHasher) {
+        h.write_str("Solid");
+        self.transformation.hash_into(h);
+        let c = self.color;
+        h.write_f64s(&[c.0, c.1, c.2]);
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -1,6 +1,8 @@
 use std::any::Any;
 
-use crate::{color::Color, matrices::Matrix, tuples::Point};
+use crate::{
+    color::Color, constants::EPSILON, matrices::Matrix, tuples::Point, utils::StableHasher,
+};
 
 use super::Pattern;
 
@@ -9,11 +11,14 @@ pub struct Stripe {
     a: Box<dyn Pattern>,
     b: Box<dyn Pattern>,
     transformation: Matrix,
+    // Box filter the stripes over the footprint of a sample so they blend
+    // towards the average instead of turning into moiré at grazing angles
+    fade: bool,
 }
 
 impl Pattern for Stripe {
     fn at(&self, point: Point) -> Color {
-        if point.0.floor() % 2.0 == 0.0 {
+        if point.snap(EPSILON).0.floor() % 2.0 == 0.0 {
             let local_pattern_point = self.a.transformation().inverse() * point;
             return self.a.at(local_pattern_point);
         }
@@ -22,6 +27,22 @@ impl Pattern for Stripe {
         self.b.at(local_pattern_point)
     }
 
+    fn at_footprint(&self, point: Point, footprint: f64) -> Color {
+        if !self.fade || footprint <= 0.0 {
+            return self.at(point);
+        }
+
+        // How much of [x - footprint / 2, x + footprint / 2] lies on a stripes
+        let x = point.snap(EPSILON).0;
+        let half = footprint / 2.0;
+        let coverage = (a_stripe_length(x + half) - a_stripe_length(x - half)) / footprint;
+
+        let a = self.a.at(self.a.transformation().inverse() * point);
+        let b = self.b.at(self.b.transformation().inverse() * point);
+
+        a * coverage + b * (1.0 - coverage)
+    }
+
     fn transformation(&self) -> Matrix {
         self.transformation.clone()
     }
@@ -34,6 +55,14 @@ impl Pattern for Stripe {
         Box::new(self.clone())
     }
 
+    fn hash_into(&self, h: &mut StableHasher) {
+        h.write_str("Stripe");
+        self.transformation.hash_into(h);
+        self.a.hash_into(h);
+        self.b.hash_into(h);
+        h.write_bool(self.fade);
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -43,6 +72,7 @@ impl Pattern for Stripe {
             self.a.equals(other.a.as_ref())
                 && self.b.equals(other.b.as_ref())
                 && self.transformation == other.transformation
+                && self.fade == other.fade
         } else {
             false
         }
@@ -55,6 +85,7 @@ impl Clone for Stripe {
             a: self.a.clone(),
             b: self.b.clone(),
             transformation: self.transformation.clone(),
+            fade: self.fade,
         }
     }
 }
@@ -65,16 +96,30 @@ impl Stripe {
             a,
             b,
             transformation: Matrix::identity(),
+            fade: false,
         }
     }
+
+    pub fn set_fade(&mut self, fade: bool) {
+        self.fade = fade;
+    }
+}
+
+// Total length of a stripes between 0 and x. Each period of 2 starts with a
+// unit long a stripe.
+fn a_stripe_length(x: f64) -> f64 {
+    let periods = (x / 2.0).floor();
+    periods + (x - periods * 2.0).clamp(0.0, 1.0)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::f64::consts::PI;
+
     use crate::{
         patterns::solid::Solid,
         shapes::{sphere::Sphere, Shape},
-        transformation::{scaling, translation},
+        transformation::{rotation_y, scaling, translation},
     };
 
     use super::*;
@@ -129,6 +174,41 @@ mod tests {
         assert_eq!(stripe.at(Point::new(-1.1, 0.0, 0.0)), Color::white());
     }
 
+    #[test]
+    fn fading_stripes_average_large_footprints() {
+        let mut stripe = Stripe::new(
+            Box::new(Solid::new(Color::white())),
+            Box::new(Solid::new(Color::black())),
+        );
+        let object = Sphere::new();
+
+        // Without fading the footprint is ignored
+        assert_eq!(
+            stripe.at_hit(&object, Point::new(0.5, 0.0, 0.0), 5.0),
+            Color::white()
+        );
+
+        stripe.set_fade(true);
+
+        // A head-on sample covers a sliver of one stripe
+        assert_eq!(
+            stripe.at_hit(&object, Point::new(0.5, 0.0, 0.0), 0.01),
+            Color::white()
+        );
+        assert_eq!(
+            stripe.at_hit(&object, Point::new(1.5, 0.0, 0.0), 0.01),
+            Color::black()
+        );
+
+        // A grazing sample spans many periods and lands near the average
+        let c = stripe.at_hit(&object, Point::new(0.5, 0.0, 0.0), 9.0);
+        assert!((c.0 - 0.5).abs() < 0.1);
+        assert_eq!(
+            stripe.at_hit(&object, Point::new(0.5, 0.0, 0.0), 10.0),
+            Color(0.5, 0.5, 0.5)
+        );
+    }
+
     #[test]
     fn stripes_with_object_transformation() {
         let mut object = Sphere::new();
@@ -172,4 +252,46 @@ mod tests {
 
         assert_eq!(c, Color::white());
     }
+
+    #[test]
+    fn stripes_with_rotated_object_and_pattern_transformation() {
+        let mut object = Sphere::new();
+        object.set_transformation(rotation_y(PI / 2.0) * scaling(2.0, 2.0, 2.0));
+
+        let mut stripe = Stripe::new(
+            Box::new(Solid::new(Color::white())),
+            Box::new(Solid::new(Color::black())),
+        );
+        stripe.set_transformation(translation(0.5, 0.0, 0.0));
+
+        // Undoing the object transform lands at x = -0.75, then the pattern
+        // transform moves it to -1.25. Applying them the other way round
+        // would give -0.75 and a black stripe.
+        let c = stripe.at_object(&object, Point::new(0.0, 0.0, 1.5));
+
+        assert_eq!(c, Color::white());
+    }
+
+    #[test]
+    fn previewing_stripes_alternates_columns() {
+        let stripe = Stripe::new(
+            Box::new(Solid::new(Color::white())),
+            Box::new(Solid::new(Color::black())),
+        );
+
+        // Two pixels per unit wide stripe
+        let preview = stripe.preview(8, 3, 0.5);
+
+        assert_eq!((preview.width, preview.height), (8, 3));
+        for y in 0..3 {
+            for x in 0..8 {
+                let expected = if (x / 2) % 2 == 0 {
+                    Color::white()
+                } else {
+                    Color::black()
+                };
+                assert_eq!(preview.pixel_at(x, y), expected);
+            }
+        }
+    }
 }
@@ -1,4 +1,5 @@
 use crate::{
+    error::Error,
     matrices::Matrix,
     shapes::sphere::Sphere,
     tuples::{Point, Tuple, Vector},
@@ -12,11 +13,21 @@ pub struct Ray {
 
 impl Ray {
     pub fn new(origin: Point, direction: Vector) -> Self {
+        match Ray::try_new(origin, direction) {
+            Ok(r) => r,
+            Err(e) => panic!(
+                "{} Received origin: {:#?} and direction: {:#?}",
+                e, origin, direction
+            ),
+        }
+    }
+
+    pub fn try_new(origin: Point, direction: Vector) -> Result<Self, Error> {
         if origin.3 != 1.0 || direction.3 != 0.0 {
-            panic!("The origin of ray should be a point and direction should be a vector. Received origin: {:#?} and direction: {:#?}", origin, direction)
+            return Err(Error::InvalidRay);
         }
 
-        Ray { origin, direction }
+        Ok(Ray { origin, direction })
     }
 
     pub fn position(&self, t: f64) -> Point {
@@ -48,6 +59,22 @@ mod tests {
         assert_eq!(r.direction, direction);
     }
 
+    #[test]
+    fn trying_to_create_ray_with_swapped_origin_and_direction() {
+        let origin = Point::new(1.0, 2.0, 3.0);
+        let direction = Vector::new(4.0, 5.0, 6.0);
+
+        assert!(Ray::try_new(origin, direction).is_ok());
+        assert_eq!(
+            Ray::try_new(Point(1.0, 2.0, 3.0, 0.0), direction).unwrap_err(),
+            Error::InvalidRay
+        );
+        assert_eq!(
+            Ray::try_new(origin, Vector(4.0, 5.0, 6.0, 1.0)).unwrap_err(),
+            Error::InvalidRay
+        );
+    }
+
     #[test]
     fn computing_point_from_distance() {
         let r = Ray {
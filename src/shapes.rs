@@ -1,18 +1,35 @@
 use crate::{
+    bounds::BoundingBox,
     intersection::Intersection,
     materials::Material,
     matrices::Matrix,
     rays::Ray,
+    transformation::Motion,
     tuples::{Point, Vector},
+    utils::StableHasher,
 };
 use std::{any::Any, fmt::Debug};
 
+pub mod cone;
+pub mod csg;
+pub mod cylinder;
+pub mod group;
 pub mod plane;
+pub mod smooth_triangle;
 pub mod sphere;
+pub mod triangle;
 
-pub trait Shape: Debug {
+pub trait Shape: Debug + Send + Sync {
     fn as_any(&self) -> &dyn Any;
 
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    // Short name of the concrete shape type, like "Sphere"
+    fn type_name(&self) -> &'static str {
+        let path = std::any::type_name::<Self>();
+        path.rsplit("::").next().unwrap_or(path)
+    }
+
     fn equals(&self, other: &dyn Shape) -> bool;
 
     fn material(&self) -> Material;
@@ -23,30 +40,153 @@ pub trait Shape: Debug {
 
     fn set_transformation(&mut self, m: Matrix);
 
-    fn local_intersect(&self, local_ray: Ray) -> Vec<Intersection>;
+    // Applies m on top of the shape's own transformation, as if the shape
+    // had been put in a group transformed by m
+    fn pre_transform(&mut self, m: &Matrix) {
+        self.set_transformation(m.clone() * self.transformation());
+    }
+
+    // Combined transformation of every group the shape sits in, outermost
+    // first. Groups keep it up to date for their children, so it stays the
+    // identity for shapes added straight to the World.
+    fn parent_transformation(&self) -> Matrix;
+
+    fn set_parent_transformation(&mut self, m: Matrix);
+
+    // Transformation from object space all the way out to world space
+    fn world_transformation(&self) -> Matrix {
+        self.parent_transformation() * self.transformation()
+    }
+
+    fn world_to_object(&self, point: Point) -> Point {
+        self.world_transformation().inverse() * point
+    }
+
+    fn normal_to_world(&self, normal: Vector) -> Vector {
+        let mut world_normal = self.world_transformation().inverse().transpose() * normal;
+        world_normal.3 = 0.0;
+
+        world_normal.normalize()
+    }
+
+    // Time-varying transformation that replaces transformation() when
+    // intersecting at a particular time
+    fn motion(&self) -> Option<&Motion> {
+        None
+    }
 
-    fn intersect(&self, ray: Ray) -> Vec<Intersection> {
+    fn transformation_at(&self, time: f64) -> Matrix {
+        match self.motion() {
+            Some(motion) => motion.at(time),
+            None => self.transformation(),
+        }
+    }
+
+    // Pushes the intersections with the ray in object space onto xs, so
+    // callers can collect intersections from many shapes in one buffer
+    fn local_intersect_into<'a>(&'a self, local_ray: Ray, xs: &mut Vec<Intersection<'a>>);
+
+    fn local_intersect(&self, local_ray: Ray) -> Vec<Intersection<'_>> {
+        let mut xs = Vec::new();
+        self.local_intersect_into(local_ray, &mut xs);
+
+        xs
+    }
+
+    fn intersect_into<'a>(&'a self, ray: Ray, xs: &mut Vec<Intersection<'a>>) {
         let local_ray = ray.transform(self.transformation().inverse());
 
+        self.local_intersect_into(local_ray, xs);
+    }
+
+    fn intersect(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        let mut xs = Vec::new();
+        self.intersect_into(ray, &mut xs);
+
+        xs
+    }
+
+    // Intersects the shape where it is at the given normalized time. Only
+    // the intersection moves: normal_at and shading still use the shape's
+    // static transformation, and World and Camera don't call this yet.
+    fn intersect_at(&self, ray: Ray, time: f64) -> Vec<Intersection<'_>> {
+        let local_ray = ray.transform(self.transformation_at(time).inverse());
+
         self.local_intersect(local_ray)
     }
 
     fn local_normal_at(&self, local_point: Point) -> Vector;
 
+    // Like local_normal_at, for a hit with the given barycentric
+    // coordinates. Only shapes that interpolate normals need them.
+    fn local_normal_at_uv(&self, local_point: Point, _uv: Option<(f64, f64)>) -> Vector {
+        self.local_normal_at(local_point)
+    }
+
+    // Texture coordinates at a hit with the given barycentric coordinates,
+    // for shapes that were given them at each vertex
+    fn texture_uv(&self, _uv: Option<(f64, f64)>) -> Option<(f64, f64)> {
+        None
+    }
+
     fn normal_at(&self, point: Point) -> Vector {
-        let local_point = self.transformation().inverse() * point;
-        let local_normal = self.local_normal_at(local_point);
+        self.normal_at_uv(point, None)
+    }
 
-        let mut world_normal = self.transformation().inverse().transpose() * local_normal;
-        world_normal.3 = 0.0;
+    fn normal_at_uv(&self, point: Point, uv: Option<(f64, f64)>) -> Vector {
+        let local_point = self.world_to_object(point);
+        let local_normal = self.local_normal_at_uv(local_point, uv);
 
-        world_normal.normalize()
+        self.normal_to_world(local_normal)
+    }
+
+    // Whether the point is strictly inside the shape. Only closed shapes have
+    // an inside, so this is always false for planes, triangles and open
+    // cylinders and cones.
+    fn contains_point(&self, world_point: Point) -> bool {
+        self.local_contains_point(self.transformation().inverse() * world_point)
+    }
+
+    fn local_contains_point(&self, _local_point: Point) -> bool {
+        false
+    }
+
+    // Whether other is this very shape or, for shapes made of others like
+    // groups, one of its descendants
+    fn includes(&self, other: &dyn Shape) -> bool {
+        std::ptr::addr_eq(self as *const Self, other as *const dyn Shape)
+    }
+
+    // Bounds of the untransformed shape, in object space
+    fn bounds(&self) -> BoundingBox;
+
+    fn parent_space_bounds(&self) -> BoundingBox {
+        self.bounds().transform(&self.transformation())
+    }
+
+    // Feeds everything about the shape that changes how it renders into h,
+    // for World::scene_hash. The parent transformation is left out since it
+    // follows from the groups the shape is in.
+    fn hash_into(&self, h: &mut StableHasher) {
+        h.write_str(self.type_name());
+        self.transformation().hash_into(h);
+        self.material().hash_into(h);
+        h.write_bool(self.motion().is_some());
+        if let Some(motion) = self.motion() {
+            motion.hash_into(h);
+        }
+        self.hash_geometry_into(h);
     }
+
+    // Adds whatever hash_into doesn't cover, like a cylinder's extent or a
+    // group's children
+    fn hash_geometry_into(&self, _h: &mut StableHasher) {}
 }
 
 #[derive(Debug)]
 struct TestShape {
     transformation: Matrix,
+    parent_transformation: Matrix,
     material: Material,
 }
 
@@ -55,6 +195,10 @@ impl Shape for TestShape {
         self
     }
 
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
     fn equals(&self, other: &dyn Shape) -> bool {
         if let Some(other) = other.as_any().downcast_ref::<TestShape>() {
             self.transformation == other.transformation && self.material == other.material
@@ -79,19 +223,32 @@ impl Shape for TestShape {
         self.transformation = m;
     }
 
-    fn local_intersect(&self, local_ray: Ray) -> Vec<Intersection> {
+    fn parent_transformation(&self) -> Matrix {
+        self.parent_transformation.clone()
+    }
+
+    fn set_parent_transformation(&mut self, m: Matrix) {
+        self.parent_transformation = m;
+    }
+
+    fn local_intersect_into<'a>(&'a self, local_ray: Ray, _: &mut Vec<Intersection<'a>>) {
         panic!("{:?}", local_ray);
     }
 
     fn local_normal_at(&self, local_point: Point) -> Vector {
         Vector::new(local_point.0, local_point.1, local_point.2)
     }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0))
+    }
 }
 
 impl TestShape {
     fn new() -> Self {
         Self {
             transformation: Matrix::identity(),
+            parent_transformation: Matrix::identity(),
             material: Material::new(),
         }
     }
@@ -172,6 +329,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn computing_normal_on_translated_shape() {
         let mut s = TestShape::new();
 
@@ -191,4 +349,15 @@ mod tests {
 
         assert_eq!(n, Vector::new(0.0, 0.97014, -0.24254));
     }
+
+    #[test]
+    fn querying_shape_bounds_in_parent_space() {
+        let mut s = TestShape::new();
+        s.set_transformation(translation(1.0, -3.0, 5.0) * scaling(0.5, 2.0, 4.0));
+
+        let b = s.parent_space_bounds();
+
+        assert_eq!(b.min, Point::new(0.5, -5.0, 1.0));
+        assert_eq!(b.max, Point::new(1.5, -1.0, 9.0));
+    }
 }
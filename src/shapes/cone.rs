@@ -0,0 +1,373 @@
+use std::any::Any;
+
+use crate::{
+    bounds::BoundingBox,
+    constants::EPSILON,
+    intersection::{Intersection, Part},
+    materials::Material,
+    matrices::Matrix,
+    rays::Ray,
+    transformation::Motion,
+    tuples::{Point, Vector},
+    utils::StableHasher,
+};
+
+use super::Shape;
+
+// A double-napped cone around the y axis with its tips meeting at the
+// origin, whose radius at any y is |y|. Like Cylinder, it is infinite unless
+// minimum and maximum cut it down.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cone {
+    pub transformation: Matrix,
+    pub parent_transformation: Matrix,
+    pub material: Material,
+    pub motion: Option<Motion>,
+    pub minimum: f64,
+    pub maximum: f64,
+    // Whether the ends of a truncated cone are capped
+    pub closed: bool,
+}
+
+impl Shape for Cone {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn equals(&self, other: &dyn Shape) -> bool {
+        if let Some(other) = other.as_any().downcast_ref::<Cone>() {
+            self.transformation == other.transformation
+                && self.material == other.material
+                && self.minimum == other.minimum
+                && self.maximum == other.maximum
+                && self.closed == other.closed
+        } else {
+            false
+        }
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn set_material(&mut self, m: Material) {
+        self.material = m;
+    }
+
+    fn transformation(&self) -> Matrix {
+        self.transformation.clone()
+    }
+
+    fn set_transformation(&mut self, m: Matrix) {
+        self.transformation = m;
+    }
+
+    fn parent_transformation(&self) -> Matrix {
+        self.parent_transformation.clone()
+    }
+
+    fn set_parent_transformation(&mut self, m: Matrix) {
+        self.parent_transformation = m;
+    }
+
+    fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    fn local_intersect_into<'a>(&'a self, local_ray: Ray, xs: &mut Vec<Intersection<'a>>) {
+        self.intersect_wall(local_ray, xs);
+        self.intersect_caps(local_ray, xs);
+    }
+
+    fn local_normal_at(&self, local_point: Point) -> Vector {
+        let p = local_point;
+        let radius = (p.0.powi(2) + p.2.powi(2)).sqrt();
+
+        // Same as Cylinder, points near the rim go to the closer surface
+        if self.closed {
+            let to_wall = (radius - p.1.abs()).abs();
+
+            if (self.maximum - p.1).abs() < EPSILON.min(to_wall) {
+                return Vector::new(0.0, 1.0, 0.0);
+            }
+            if (p.1 - self.minimum).abs() < EPSILON.min(to_wall) {
+                return Vector::new(0.0, -1.0, 0.0);
+            }
+        }
+
+        let y = if p.1 > 0.0 { -radius } else { radius };
+
+        Vector::new(p.0, y, p.2)
+    }
+
+    fn local_contains_point(&self, local_point: Point) -> bool {
+        let p = local_point;
+
+        self.closed
+            && p.0.powi(2) + p.2.powi(2) < p.1.powi(2)
+            && self.minimum < p.1
+            && p.1 < self.maximum
+    }
+
+    fn hash_geometry_into(&self, h: &mut StableHasher) {
+        h.write_f64s(&[self.minimum, self.maximum]);
+        h.write_bool(self.closed);
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let limit = self.minimum.abs().max(self.maximum.abs());
+
+        BoundingBox::new(
+            Point::new(-limit, self.minimum, -limit),
+            Point::new(limit, self.maximum, limit),
+        )
+    }
+}
+
+impl Cone {
+    pub fn new() -> Self {
+        Self {
+            transformation: Matrix::identity(),
+            parent_transformation: Matrix::identity(),
+            material: Material::new(),
+            motion: None,
+            minimum: f64::NEG_INFINITY,
+            maximum: f64::INFINITY,
+            closed: false,
+        }
+    }
+
+    fn intersect_wall<'a>(&'a self, local_ray: Ray, xs: &mut Vec<Intersection<'a>>) {
+        let (o, d) = (local_ray.origin, local_ray.direction);
+
+        let a = d.0.powi(2) - d.1.powi(2) + d.2.powi(2);
+        let b = 2.0 * o.0 * d.0 - 2.0 * o.1 * d.1 + 2.0 * o.2 * d.2;
+        let c = o.0.powi(2) - o.1.powi(2) + o.2.powi(2);
+
+        let ts = if a.abs() < EPSILON {
+            // A ray parallel to one of the halves only crosses the other, and
+            // misses entirely if it also passes through the tip
+            if b.abs() < EPSILON {
+                return;
+            }
+
+            vec![-c / (2.0 * b)]
+        } else {
+            let discriminant = b.powi(2) - 4.0 * a * c;
+
+            if discriminant < 0.0 {
+                return;
+            }
+
+            let root = discriminant.sqrt();
+            vec![(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+        };
+
+        for t in ts {
+            let y = o.1 + t * d.1;
+            if self.minimum < y && y < self.maximum {
+                xs.push(Intersection::with_part(t, self, Part::Side));
+            }
+        }
+    }
+
+    fn intersect_caps<'a>(&'a self, local_ray: Ray, xs: &mut Vec<Intersection<'a>>) {
+        // Rays parallel to the caps can't cross them
+        if !self.closed || local_ray.direction.1.abs() < EPSILON {
+            return;
+        }
+
+        for y in [self.minimum, self.maximum] {
+            let t = (y - local_ray.origin.1) / local_ray.direction.1;
+            if check_cap(local_ray, t, y.abs()) {
+                xs.push(Intersection::with_part(t, self, Part::Cap));
+            }
+        }
+    }
+}
+
+// Whether the ray at t is within the given radius of the y axis
+fn check_cap(ray: Ray, t: f64, radius: f64) -> bool {
+    let x = ray.origin.0 + t * ray.direction.0;
+    let z = ray.origin.2 + t * ray.direction.2;
+
+    x.powi(2) + z.powi(2) <= radius.powi(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod shape_default_tests {
+        use crate::transformation::translation;
+
+        use super::*;
+
+        #[test]
+        fn default_transformation() {
+            let s = Cone::new();
+            assert_eq!(s.transformation, Matrix::identity());
+        }
+
+        #[test]
+        fn assigning_transformation() {
+            let mut s = Cone::new();
+            s.set_transformation(translation(2.0, 3.0, 4.0));
+            assert_eq!(s.transformation, translation(2.0, 3.0, 4.0));
+        }
+
+        #[test]
+        fn default_material() {
+            let s = Cone::new();
+            assert_eq!(s.material, Material::new());
+        }
+
+        #[test]
+        fn assigning_material() {
+            let mut s = Cone::new();
+
+            let mut m = Material::new();
+            m.ambient = 1.0;
+
+            s.set_material(m.clone());
+
+            assert_eq!(s.material, m);
+        }
+    }
+
+    #[test]
+    fn intersecting_cone_with_ray() {
+        let shape = Cone::new();
+        let cases = [
+            (
+                Point::new(0.0, 0.0, -5.0),
+                Vector::new(0.0, 0.0, 1.0),
+                5.0,
+                5.0,
+            ),
+            (
+                Point::new(0.0, 0.0, -5.0),
+                Vector::new(1.0, 1.0, 1.0),
+                8.66025,
+                8.66025,
+            ),
+            (
+                Point::new(1.0, 1.0, -5.0),
+                Vector::new(-0.5, -1.0, 1.0),
+                4.55006,
+                49.44994,
+            ),
+        ];
+
+        for (origin, direction, t0, t1) in cases {
+            let r = Ray::new(origin, direction.normalize());
+            let xs = shape.local_intersect(r);
+
+            assert_eq!(xs.len(), 2);
+            assert!((xs[0].t - t0).abs() < EPSILON);
+            assert!((xs[1].t - t1).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn intersecting_cone_with_ray_parallel_to_one_half() {
+        let shape = Cone::new();
+        let r = Ray::new(
+            Point::new(0.0, 0.0, -1.0),
+            Vector::new(0.0, 1.0, 1.0).normalize(),
+        );
+
+        let xs = shape.local_intersect(r);
+
+        assert_eq!(xs.len(), 1);
+        assert!((xs[0].t - 0.35355).abs() < EPSILON);
+    }
+
+    #[test]
+    fn intersecting_caps_of_cone() {
+        let mut shape = Cone::new();
+        shape.minimum = -0.5;
+        shape.maximum = 0.5;
+        shape.closed = true;
+        let cases = [
+            (Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0), 0),
+            (Point::new(0.0, 0.0, -0.25), Vector::new(0.0, 1.0, 1.0), 2),
+            (Point::new(0.0, 0.0, -0.25), Vector::new(0.0, 1.0, 0.0), 4),
+        ];
+
+        for (origin, direction, count) in cases {
+            let r = Ray::new(origin, direction.normalize());
+            assert_eq!(shape.local_intersect(r).len(), count);
+        }
+    }
+
+    #[test]
+    fn computing_normal_on_cone() {
+        let shape = Cone::new();
+        let cases = [
+            (Point::origin(), Vector::new(0.0, 0.0, 0.0)),
+            (
+                Point::new(1.0, 1.0, 1.0),
+                Vector::new(1.0, -2.0_f64.sqrt(), 1.0),
+            ),
+            (Point::new(-1.0, -1.0, 0.0), Vector::new(-1.0, 1.0, 0.0)),
+        ];
+
+        for (point, normal) in cases {
+            assert_eq!(shape.local_normal_at(point), normal);
+        }
+    }
+
+    #[test]
+    fn computing_normal_on_cone_caps() {
+        let mut shape = Cone::new();
+        shape.minimum = -1.0;
+        shape.maximum = 2.0;
+        shape.closed = true;
+        let cases = [
+            (Point::new(0.0, 2.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+            (Point::new(1.5, 2.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+            (Point::new(0.0, -1.0, 0.5), Vector::new(0.0, -1.0, 0.0)),
+            // On the wall just under the top rim
+            (
+                Point::new(2.0 - EPSILON / 2.0, 2.0 - EPSILON / 2.0, 0.0),
+                Vector::new(2.0 - EPSILON / 2.0, -(2.0 - EPSILON / 2.0), 0.0),
+            ),
+        ];
+
+        for (point, normal) in cases {
+            assert_eq!(shape.local_normal_at(point), normal);
+        }
+    }
+
+    #[test]
+    fn truncated_cone_has_bounding_box() {
+        let mut shape = Cone::new();
+        shape.minimum = -5.0;
+        shape.maximum = 3.0;
+
+        assert_eq!(
+            shape.bounds(),
+            BoundingBox::new(Point::new(-5.0, -5.0, -5.0), Point::new(5.0, 3.0, 5.0))
+        );
+    }
+
+    #[test]
+    fn only_closed_cones_contain_points() {
+        let mut shape = Cone::new();
+        shape.minimum = -1.0;
+        shape.maximum = 1.0;
+        shape.closed = true;
+
+        assert!(shape.contains_point(Point::new(0.25, 0.5, 0.0)));
+        assert!(shape.contains_point(Point::new(0.0, -0.5, 0.25)));
+        assert!(!shape.contains_point(Point::new(0.75, 0.5, 0.0)));
+
+        shape.closed = false;
+        assert!(!shape.contains_point(Point::new(0.25, 0.5, 0.0)));
+    }
+}
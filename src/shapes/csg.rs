@@ -0,0 +1,377 @@
+use std::any::Any;
+
+use crate::{
+    bounds::BoundingBox,
+    intersection::{compare_t, Intersection},
+    materials::Material,
+    matrices::Matrix,
+    rays::Ray,
+    transformation::Motion,
+    tuples::{Point, Vector},
+    utils::StableHasher,
+};
+
+use super::Shape;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    // Left with right carved out of it
+    Difference,
+}
+
+// Constructive solid geometry: two shapes combined into one by keeping only
+// the parts of their surfaces the operation allows. Like Group, it owns its
+// children and hits report the child that was hit.
+#[derive(Debug)]
+pub struct Csg {
+    pub transformation: Matrix,
+    pub parent_transformation: Matrix,
+    pub material: Material,
+    pub motion: Option<Motion>,
+    pub operation: CsgOperation,
+    pub left: Box<dyn Shape>,
+    pub right: Box<dyn Shape>,
+}
+
+impl Shape for Csg {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn equals(&self, other: &dyn Shape) -> bool {
+        if let Some(other) = other.as_any().downcast_ref::<Csg>() {
+            self.transformation == other.transformation
+                && self.operation == other.operation
+                && self.left.equals(other.left.as_ref())
+                && self.right.equals(other.right.as_ref())
+        } else {
+            false
+        }
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    // Same as Group, the material is handed down to both sides
+    fn set_material(&mut self, m: Material) {
+        self.left.set_material(m.clone());
+        self.right.set_material(m.clone());
+        self.material = m;
+    }
+
+    fn transformation(&self) -> Matrix {
+        self.transformation.clone()
+    }
+
+    fn set_transformation(&mut self, m: Matrix) {
+        self.transformation = m;
+        self.update_children();
+    }
+
+    fn parent_transformation(&self) -> Matrix {
+        self.parent_transformation.clone()
+    }
+
+    fn set_parent_transformation(&mut self, m: Matrix) {
+        self.parent_transformation = m;
+        self.update_children();
+    }
+
+    fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    fn local_intersect_into<'a>(&'a self, local_ray: Ray, xs: &mut Vec<Intersection<'a>>) {
+        if self.bounds().intersect(local_ray).is_none() {
+            return;
+        }
+
+        let mut hits = Vec::new();
+        self.left.intersect_into(local_ray, &mut hits);
+        self.right.intersect_into(local_ray, &mut hits);
+        hits.sort_by(compare_t);
+
+        xs.extend(self.filter_intersections(&hits));
+    }
+
+    // Intersections always point at a child, so this is never used for
+    // shading
+    fn local_normal_at(&self, _local_point: Point) -> Vector {
+        panic!("CSG shapes have no surface of their own to take a normal of")
+    }
+
+    fn local_contains_point(&self, local_point: Point) -> bool {
+        let in_left = self.left.contains_point(local_point);
+        let in_right = self.right.contains_point(local_point);
+
+        match self.operation {
+            CsgOperation::Union => in_left || in_right,
+            CsgOperation::Intersection => in_left && in_right,
+            CsgOperation::Difference => in_left && !in_right,
+        }
+    }
+
+    fn includes(&self, other: &dyn Shape) -> bool {
+        std::ptr::addr_eq(self as *const Self, other as *const dyn Shape)
+            || self.left.includes(other)
+            || self.right.includes(other)
+    }
+
+    fn hash_geometry_into(&self, h: &mut StableHasher) {
+        h.write_u64(self.operation as u64);
+        self.left.hash_into(h);
+        self.right.hash_into(h);
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut bounds = self.left.parent_space_bounds();
+        bounds.add_box(&self.right.parent_space_bounds());
+
+        bounds
+    }
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, left: Box<dyn Shape>, right: Box<dyn Shape>) -> Self {
+        let mut csg = Self {
+            transformation: Matrix::identity(),
+            parent_transformation: Matrix::identity(),
+            material: Material::new(),
+            motion: None,
+            operation,
+            left,
+            right,
+        };
+        csg.update_children();
+
+        csg
+    }
+
+    // Keeps the hits, sorted by t, that lie on the surface of the combined
+    // shape. Walks along the ray tracking whether it is currently inside
+    // each side.
+    pub fn filter_intersections<'a>(&self, xs: &[Intersection<'a>]) -> Vec<Intersection<'a>> {
+        let mut in_left = false;
+        let mut in_right = false;
+        let mut result = Vec::new();
+
+        for &i in xs {
+            let left_hit = self.left.includes(i.object);
+
+            if intersection_allowed(self.operation, left_hit, in_left, in_right) {
+                result.push(i);
+            }
+
+            if left_hit {
+                in_left = !in_left;
+            } else {
+                in_right = !in_right;
+            }
+        }
+
+        result
+    }
+
+    fn update_children(&mut self) {
+        let world_transformation = self.world_transformation();
+        self.left
+            .set_parent_transformation(world_transformation.clone());
+        self.right.set_parent_transformation(world_transformation);
+    }
+}
+
+// Whether a hit on one side of a CSG shape is on the combined surface, given
+// which side was hit and whether the ray is inside each side at the time
+pub fn intersection_allowed(
+    operation: CsgOperation,
+    left_hit: bool,
+    in_left: bool,
+    in_right: bool,
+) -> bool {
+    match operation {
+        CsgOperation::Union => (left_hit && !in_right) || (!left_hit && !in_left),
+        CsgOperation::Intersection => (left_hit && in_right) || (!left_hit && in_left),
+        CsgOperation::Difference => (left_hit && !in_right) || (!left_hit && in_left),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{shapes::sphere::Sphere, transformation::translation};
+
+    use super::*;
+
+    fn two_spheres(operation: CsgOperation) -> Csg {
+        Csg::new(operation, Box::new(Sphere::new()), Box::new(Sphere::new()))
+    }
+
+    mod shape_default_tests {
+        use crate::transformation::translation;
+
+        use super::*;
+
+        #[test]
+        fn default_transformation() {
+            let s = two_spheres(CsgOperation::Union);
+            assert_eq!(s.transformation, Matrix::identity());
+        }
+
+        #[test]
+        fn assigning_transformation() {
+            let mut s = two_spheres(CsgOperation::Union);
+            s.set_transformation(translation(2.0, 3.0, 4.0));
+            assert_eq!(s.transformation, translation(2.0, 3.0, 4.0));
+        }
+
+        #[test]
+        fn default_material() {
+            let s = two_spheres(CsgOperation::Union);
+            assert_eq!(s.material, Material::new());
+        }
+
+        #[test]
+        fn assigning_material() {
+            let mut s = two_spheres(CsgOperation::Union);
+
+            let mut m = Material::new();
+            m.ambient = 1.0;
+
+            s.set_material(m.clone());
+
+            assert_eq!(s.material, m);
+            assert_eq!(s.left.material(), m);
+            assert_eq!(s.right.material(), m);
+        }
+    }
+
+    #[test]
+    fn evaluating_rule_for_csg_operations() {
+        // (operation, left_hit, in_left, in_right, allowed)
+        let cases = [
+            (CsgOperation::Union, true, true, true, false),
+            (CsgOperation::Union, true, true, false, true),
+            (CsgOperation::Union, true, false, true, false),
+            (CsgOperation::Union, true, false, false, true),
+            (CsgOperation::Union, false, true, true, false),
+            (CsgOperation::Union, false, true, false, false),
+            (CsgOperation::Union, false, false, true, true),
+            (CsgOperation::Union, false, false, false, true),
+            (CsgOperation::Intersection, true, true, true, true),
+            (CsgOperation::Intersection, true, true, false, false),
+            (CsgOperation::Intersection, true, false, true, true),
+            (CsgOperation::Intersection, true, false, false, false),
+            (CsgOperation::Intersection, false, true, true, true),
+            (CsgOperation::Intersection, false, true, false, true),
+            (CsgOperation::Intersection, false, false, true, false),
+            (CsgOperation::Intersection, false, false, false, false),
+            (CsgOperation::Difference, true, true, true, false),
+            (CsgOperation::Difference, true, true, false, true),
+            (CsgOperation::Difference, true, false, true, false),
+            (CsgOperation::Difference, true, false, false, true),
+            (CsgOperation::Difference, false, true, true, true),
+            (CsgOperation::Difference, false, true, false, true),
+            (CsgOperation::Difference, false, false, true, false),
+            (CsgOperation::Difference, false, false, false, false),
+        ];
+
+        for (operation, left_hit, in_left, in_right, allowed) in cases {
+            assert_eq!(
+                intersection_allowed(operation, left_hit, in_left, in_right),
+                allowed,
+                "{:?} {} {} {}",
+                operation,
+                left_hit,
+                in_left,
+                in_right
+            );
+        }
+    }
+
+    #[test]
+    fn filtering_list_of_intersections() {
+        // Indices into xs of the hits each operation keeps
+        let cases = [
+            (CsgOperation::Union, 0, 3),
+            (CsgOperation::Intersection, 1, 2),
+            (CsgOperation::Difference, 0, 1),
+        ];
+
+        for (operation, x0, x1) in cases {
+            let c = two_spheres(operation);
+            let xs = [
+                Intersection::new(1.0, c.left.as_ref()),
+                Intersection::new(2.0, c.right.as_ref()),
+                Intersection::new(3.0, c.left.as_ref()),
+                Intersection::new(4.0, c.right.as_ref()),
+            ];
+
+            let result = c.filter_intersections(&xs);
+
+            assert_eq!(result.len(), 2);
+            assert_eq!(result[0].t, xs[x0].t);
+            assert_eq!(result[1].t, xs[x1].t);
+        }
+    }
+
+    #[test]
+    fn ray_misses_csg_object() {
+        let c = two_spheres(CsgOperation::Union);
+        let r = Ray::new(Point::new(0.0, 2.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(c.local_intersect(r).is_empty());
+    }
+
+    #[test]
+    fn ray_hits_csg_object() {
+        let mut s2 = Sphere::new();
+        s2.set_transformation(translation(0.0, 0.0, 0.5));
+        let c = Csg::new(
+            CsgOperation::Union,
+            Box::new(Sphere::new()),
+            Box::new(s2.clone()),
+        );
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let xs = c.local_intersect(r);
+
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.0);
+        assert!(xs[0].object.equals(&Sphere::new()));
+        assert_eq!(xs[1].t, 6.5);
+        assert!(xs[1].object.equals(&s2));
+    }
+
+    #[test]
+    fn hits_know_which_side_they_belong_to() {
+        let c = two_spheres(CsgOperation::Difference);
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        // The two spheres are equal, so only identity tells the sides apart
+        for i in c.intersect(r) {
+            assert!(c.includes(i.object));
+            assert_ne!(c.left.includes(i.object), c.right.includes(i.object));
+        }
+    }
+
+    #[test]
+    fn difference_contains_points_only_outside_right() {
+        let mut right = Sphere::new();
+        right.set_transformation(translation(1.0, 0.0, 0.0));
+        let c = Csg::new(
+            CsgOperation::Difference,
+            Box::new(Sphere::new()),
+            Box::new(right),
+        );
+
+        assert!(c.contains_point(Point::new(-0.5, 0.0, 0.0)));
+        assert!(!c.contains_point(Point::new(0.5, 0.0, 0.0)));
+        assert!(!c.contains_point(Point::new(1.5, 0.0, 0.0)));
+    }
+}
@@ -0,0 +1,472 @@
+use std::any::Any;
+
+use crate::{
+    bounds::BoundingBox,
+    constants::EPSILON,
+    intersection::{Intersection, Part},
+    materials::Material,
+    matrices::Matrix,
+    rays::Ray,
+    transformation::Motion,
+    tuples::{Point, Vector},
+    utils::StableHasher,
+};
+
+use super::Shape;
+
+// A cylinder of radius 1 around the y axis. It is infinitely long unless
+// minimum and maximum cut it down, and those limits are exclusive.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cylinder {
+    pub transformation: Matrix,
+    pub parent_transformation: Matrix,
+    pub material: Material,
+    pub motion: Option<Motion>,
+    pub minimum: f64,
+    pub maximum: f64,
+    // Whether the ends of a truncated cylinder are capped
+    pub closed: bool,
+}
+
+impl Shape for Cylinder {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn equals(&self, other: &dyn Shape) -> bool {
+        if let Some(other) = other.as_any().downcast_ref::<Cylinder>() {
+            self.transformation == other.transformation
+                && self.material == other.material
+                && self.minimum == other.minimum
+                && self.maximum == other.maximum
+                && self.closed == other.closed
+        } else {
+            false
+        }
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn set_material(&mut self, m: Material) {
+        self.material = m;
+    }
+
+    fn transformation(&self) -> Matrix {
+        self.transformation.clone()
+    }
+
+    fn set_transformation(&mut self, m: Matrix) {
+        self.transformation = m;
+    }
+
+    fn parent_transformation(&self) -> Matrix {
+        self.parent_transformation.clone()
+    }
+
+    fn set_parent_transformation(&mut self, m: Matrix) {
+        self.parent_transformation = m;
+    }
+
+    fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    fn local_intersect_into<'a>(&'a self, local_ray: Ray, xs: &mut Vec<Intersection<'a>>) {
+        self.intersect_wall(local_ray, xs);
+        self.intersect_caps(local_ray, xs);
+    }
+
+    fn local_normal_at(&self, local_point: Point) -> Vector {
+        let p = local_point;
+
+        // Near the rim a point is within EPSILON of both the cap and the
+        // wall, so pick whichever surface it is actually closer to
+        if self.closed {
+            let to_wall = ((p.0.powi(2) + p.2.powi(2)).sqrt() - 1.0).abs();
+
+            if (self.maximum - p.1).abs() < EPSILON.min(to_wall) {
+                return Vector::new(0.0, 1.0, 0.0);
+            }
+            if (p.1 - self.minimum).abs() < EPSILON.min(to_wall) {
+                return Vector::new(0.0, -1.0, 0.0);
+            }
+        }
+
+        Vector::new(p.0, 0.0, p.2)
+    }
+
+    fn local_contains_point(&self, local_point: Point) -> bool {
+        let p = local_point;
+
+        self.closed && p.0.powi(2) + p.2.powi(2) < 1.0 && self.minimum < p.1 && p.1 < self.maximum
+    }
+
+    fn hash_geometry_into(&self, h: &mut StableHasher) {
+        h.write_f64s(&[self.minimum, self.maximum]);
+        h.write_bool(self.closed);
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Point::new(-1.0, self.minimum, -1.0),
+            Point::new(1.0, self.maximum, 1.0),
+        )
+    }
+}
+
+impl Cylinder {
+    pub fn new() -> Self {
+        Self {
+            transformation: Matrix::identity(),
+            parent_transformation: Matrix::identity(),
+            material: Material::new(),
+            motion: None,
+            minimum: f64::NEG_INFINITY,
+            maximum: f64::INFINITY,
+            closed: false,
+        }
+    }
+
+    fn intersect_wall<'a>(&'a self, local_ray: Ray, xs: &mut Vec<Intersection<'a>>) {
+        let (o, d) = (local_ray.origin, local_ray.direction);
+
+        let a = d.0.powi(2) + d.2.powi(2);
+
+        // Rays parallel to the y axis never cross the wall
+        if a.abs() < EPSILON {
+            return;
+        }
+
+        let b = 2.0 * o.0 * d.0 + 2.0 * o.2 * d.2;
+        let c = o.0.powi(2) + o.2.powi(2) - 1.0;
+
+        let discriminant = b.powi(2) - 4.0 * a * c;
+
+        if discriminant < 0.0 {
+            return;
+        }
+
+        let root = discriminant.sqrt();
+        let t0 = (-b - root) / (2.0 * a);
+        let t1 = (-b + root) / (2.0 * a);
+
+        for t in [t0, t1] {
+            let y = o.1 + t * d.1;
+            if self.minimum < y && y < self.maximum {
+                xs.push(Intersection::with_part(t, self, Part::Side));
+            }
+        }
+    }
+
+    fn intersect_caps<'a>(&'a self, local_ray: Ray, xs: &mut Vec<Intersection<'a>>) {
+        // Rays parallel to the caps can't cross them
+        if !self.closed || local_ray.direction.1.abs() < EPSILON {
+            return;
+        }
+
+        for y in [self.minimum, self.maximum] {
+            let t = (y - local_ray.origin.1) / local_ray.direction.1;
+            if check_cap(local_ray, t) {
+                xs.push(Intersection::with_part(t, self, Part::Cap));
+            }
+        }
+    }
+}
+
+// Whether the ray at t is within the unit radius of the cylinder
+fn check_cap(ray: Ray, t: f64) -> bool {
+    let x = ray.origin.0 + t * ray.direction.0;
+    let z = ray.origin.2 + t * ray.direction.2;
+
+    x.powi(2) + z.powi(2) <= 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod shape_default_tests {
+        use crate::transformation::translation;
+
+        use super::*;
+
+        #[test]
+        fn default_transformation() {
+            let s = Cylinder::new();
+            assert_eq!(s.transformation, Matrix::identity());
+        }
+
+        #[test]
+        fn assigning_transformation() {
+            let mut s = Cylinder::new();
+            s.set_transformation(translation(2.0, 3.0, 4.0));
+            assert_eq!(s.transformation, translation(2.0, 3.0, 4.0));
+        }
+
+        #[test]
+        fn default_material() {
+            let s = Cylinder::new();
+            assert_eq!(s.material, Material::new());
+        }
+
+        #[test]
+        fn assigning_material() {
+            let mut s = Cylinder::new();
+
+            let mut m = Material::new();
+            m.ambient = 1.0;
+
+            s.set_material(m.clone());
+
+            assert_eq!(s.material, m);
+        }
+    }
+
+    #[test]
+    fn ray_misses_cylinder() {
+        let cyl = Cylinder::new();
+        let cases = [
+            (Point::new(1.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+            (Point::origin(), Vector::new(0.0, 1.0, 0.0)),
+            (Point::new(0.0, 0.0, -5.0), Vector::new(1.0, 1.0, 1.0)),
+        ];
+
+        for (origin, direction) in cases {
+            let r = Ray::new(origin, direction.normalize());
+            assert_eq!(cyl.local_intersect(r).len(), 0);
+        }
+    }
+
+    #[test]
+    fn ray_strikes_cylinder() {
+        let cyl = Cylinder::new();
+        let cases = [
+            (
+                Point::new(1.0, 0.0, -5.0),
+                Vector::new(0.0, 0.0, 1.0),
+                5.0,
+                5.0,
+            ),
+            (
+                Point::new(0.0, 0.0, -5.0),
+                Vector::new(0.0, 0.0, 1.0),
+                4.0,
+                6.0,
+            ),
+            (
+                Point::new(0.5, 0.0, -5.0),
+                Vector::new(0.1, 1.0, 1.0),
+                6.80798,
+                7.08872,
+            ),
+        ];
+
+        for (origin, direction, t0, t1) in cases {
+            let r = Ray::new(origin, direction.normalize());
+            let xs = cyl.local_intersect(r);
+
+            assert_eq!(xs.len(), 2);
+            assert!((xs[0].t - t0).abs() < EPSILON);
+            assert!((xs[1].t - t1).abs() < EPSILON);
+            assert_eq!(xs[0].part, Some(Part::Side));
+        }
+    }
+
+    #[test]
+    fn normal_vector_on_cylinder() {
+        let cyl = Cylinder::new();
+        let cases = [
+            (Point::new(1.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0)),
+            (Point::new(0.0, 5.0, -1.0), Vector::new(0.0, 0.0, -1.0)),
+            (Point::new(0.0, -2.0, 1.0), Vector::new(0.0, 0.0, 1.0)),
+            (Point::new(-1.0, 1.0, 0.0), Vector::new(-1.0, 0.0, 0.0)),
+        ];
+
+        for (point, normal) in cases {
+            assert_eq!(cyl.local_normal_at(point), normal);
+        }
+    }
+
+    #[test]
+    fn default_cylinder_is_infinite_and_open() {
+        let cyl = Cylinder::new();
+
+        assert_eq!(cyl.minimum, f64::NEG_INFINITY);
+        assert_eq!(cyl.maximum, f64::INFINITY);
+        assert!(!cyl.closed);
+    }
+
+    #[test]
+    fn intersecting_constrained_cylinder() {
+        let mut cyl = Cylinder::new();
+        cyl.minimum = 1.0;
+        cyl.maximum = 2.0;
+        let cases = [
+            (Point::new(0.0, 1.5, 0.0), Vector::new(0.1, 1.0, 0.0), 0),
+            (Point::new(0.0, 3.0, -5.0), Vector::new(0.0, 0.0, 1.0), 0),
+            (Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), 0),
+            // The limits themselves are outside the cylinder
+            (Point::new(0.0, 2.0, -5.0), Vector::new(0.0, 0.0, 1.0), 0),
+            (Point::new(0.0, 1.0, -5.0), Vector::new(0.0, 0.0, 1.0), 0),
+            (Point::new(0.0, 1.5, -2.0), Vector::new(0.0, 0.0, 1.0), 2),
+        ];
+
+        for (origin, direction, count) in cases {
+            let r = Ray::new(origin, direction.normalize());
+            assert_eq!(cyl.local_intersect(r).len(), count);
+        }
+    }
+
+    #[test]
+    fn truncation_skips_only_the_out_of_range_root() {
+        let mut cyl = Cylinder::new();
+        cyl.maximum = 1.0;
+
+        // Enters the wall at y = 0.5 and would leave it above the top
+        let r = Ray::new(
+            Point::new(-2.0, 0.0, 0.0),
+            Vector::new(1.0, 0.5, 0.0).normalize(),
+        );
+        let xs = cyl.local_intersect(r);
+
+        assert_eq!(xs.len(), 1);
+        assert!((r.position(xs[0].t).1 - 0.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn truncated_cylinder_has_bounding_box() {
+        let mut cyl = Cylinder::new();
+        cyl.minimum = -3.0;
+        cyl.maximum = 2.0;
+
+        assert_eq!(
+            cyl.bounds(),
+            BoundingBox::new(Point::new(-1.0, -3.0, -1.0), Point::new(1.0, 2.0, 1.0))
+        );
+    }
+
+    fn capped_cylinder() -> Cylinder {
+        let mut cyl = Cylinder::new();
+        cyl.minimum = 1.0;
+        cyl.maximum = 2.0;
+        cyl.closed = true;
+
+        cyl
+    }
+
+    #[test]
+    fn intersecting_caps_of_closed_cylinder() {
+        let cyl = capped_cylinder();
+        // Expected numbers of cap and wall hits
+        let cases = [
+            // Straight down the middle through both caps
+            (Point::new(0.0, 3.0, 0.0), Vector::new(0.0, -1.0, 0.0), 2, 0),
+            // Through one cap, leaving through the wall
+            (
+                Point::new(0.0, 3.0, -2.0),
+                Vector::new(0.0, -1.0, 2.0),
+                1,
+                1,
+            ),
+            (Point::new(0.0, 0.0, -2.0), Vector::new(0.0, 1.0, 2.0), 1, 1),
+            // Through one cap and grazing the edge of the other
+            (
+                Point::new(0.0, 4.0, -2.0),
+                Vector::new(0.0, -1.0, 1.0),
+                2,
+                0,
+            ),
+            (
+                Point::new(0.0, -1.0, -2.0),
+                Vector::new(0.0, 1.0, 1.0),
+                2,
+                0,
+            ),
+        ];
+
+        for (origin, direction, caps, walls) in cases {
+            let r = Ray::new(origin, direction.normalize());
+            let xs = cyl.local_intersect(r);
+            let count = |part| xs.iter().filter(|x| x.part == Some(part)).count();
+
+            assert_eq!(xs.len(), caps + walls);
+            assert_eq!(count(Part::Cap), caps);
+            assert_eq!(count(Part::Side), walls);
+        }
+    }
+
+    #[test]
+    fn open_cylinder_has_no_caps() {
+        let mut cyl = capped_cylinder();
+        cyl.closed = false;
+
+        let down = Ray::new(Point::new(0.0, 3.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        assert_eq!(cyl.local_intersect(down).len(), 0);
+
+        let slanted = Ray::new(
+            Point::new(0.0, 1.2, -2.0),
+            Vector::new(0.0, 1.0, 2.0).normalize(),
+        );
+        let xs = cyl.local_intersect(slanted);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].part, Some(Part::Side));
+    }
+
+    #[test]
+    fn normal_vector_on_caps() {
+        let cyl = capped_cylinder();
+        let cases = [
+            (Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0)),
+            (Point::new(0.5, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0)),
+            (Point::new(0.0, 1.0, 0.5), Vector::new(0.0, -1.0, 0.0)),
+            (Point::new(0.0, 2.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+            (Point::new(0.5, 2.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+            (Point::new(0.0, 2.0, 0.5), Vector::new(0.0, 1.0, 0.0)),
+        ];
+
+        for (point, normal) in cases {
+            assert_eq!(cyl.local_normal_at(point), normal);
+        }
+    }
+
+    #[test]
+    fn normals_near_the_rim_pick_the_closer_surface() {
+        let cyl = capped_cylinder();
+        let angle = 0.3_f64;
+        let near_top = 2.0 - EPSILON / 2.0;
+
+        // Just inside the top cap, close to its edge
+        let on_cap = Point::new(0.999 * angle.cos(), near_top, 0.999 * angle.sin());
+        assert_eq!(cyl.local_normal_at(on_cap), Vector::new(0.0, 1.0, 0.0));
+
+        // On the wall just below the rim, where rounding can leave
+        // x² + z² a hair under 1
+        let on_wall = Point::new(angle.cos(), near_top, angle.sin());
+        assert_eq!(
+            cyl.local_normal_at(on_wall),
+            Vector::new(angle.cos(), 0.0, angle.sin())
+        );
+
+        // Going down the wall past the band doesn't change the normal
+        let below = Point::new(angle.cos(), 2.0 - 2.0 * EPSILON, angle.sin());
+        assert_eq!(cyl.local_normal_at(below), cyl.local_normal_at(on_wall));
+    }
+
+    #[test]
+    fn only_closed_cylinders_contain_points() {
+        let mut cyl = capped_cylinder();
+
+        assert!(cyl.contains_point(Point::new(0.5, 1.5, 0.0)));
+        assert!(!cyl.contains_point(Point::new(0.5, 2.5, 0.0)));
+        assert!(!cyl.contains_point(Point::new(1.5, 1.5, 0.0)));
+
+        cyl.closed = false;
+        assert!(!cyl.contains_point(Point::new(0.5, 1.5, 0.0)));
+    }
+}
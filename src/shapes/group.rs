@@ -0,0 +1,468 @@
+use std::any::Any;
+
+use crate::{
+    bounds::BoundingBox,
+    intersection::{compare_t, Intersection},
+    materials::Material,
+    matrices::Matrix,
+    rays::Ray,
+    transformation::Motion,
+    tuples::{Point, Vector},
+    utils::StableHasher,
+};
+
+use super::Shape;
+
+// A collection of shapes that is transformed as a unit. Children are owned by
+// the group, so intersections with them borrow the group for as long as they
+// live, the same way intersections with top level shapes borrow the World.
+// Hits report the child that was hit rather than the group.
+//
+// Children don't point back at their parent. Instead each one stores the
+// combined transformation of the groups above it, which is why the group's
+// transformation should be changed through set_transformation rather than
+// the field.
+#[derive(Debug)]
+pub struct Group {
+    pub transformation: Matrix,
+    pub parent_transformation: Matrix,
+    pub material: Material,
+    pub motion: Option<Motion>,
+    pub children: Vec<Box<dyn Shape>>,
+}
+
+impl Shape for Group {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn equals(&self, other: &dyn Shape) -> bool {
+        if let Some(other) = other.as_any().downcast_ref::<Group>() {
+            self.transformation == other.transformation
+                && self.children.len() == other.children.len()
+                && self
+                    .children
+                    .iter()
+                    .zip(&other.children)
+                    .all(|(a, b)| a.equals(b.as_ref()))
+        } else {
+            false
+        }
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    // Groups are never hit themselves, so their material is handed down to
+    // every child
+    fn set_material(&mut self, m: Material) {
+        for child in &mut self.children {
+            child.set_material(m.clone());
+        }
+        self.material = m;
+    }
+
+    fn transformation(&self) -> Matrix {
+        self.transformation.clone()
+    }
+
+    fn set_transformation(&mut self, m: Matrix) {
+        self.transformation = m;
+        self.update_children();
+    }
+
+    fn parent_transformation(&self) -> Matrix {
+        self.parent_transformation.clone()
+    }
+
+    fn set_parent_transformation(&mut self, m: Matrix) {
+        self.parent_transformation = m;
+        self.update_children();
+    }
+
+    fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    fn local_intersect_into<'a>(&'a self, local_ray: Ray, xs: &mut Vec<Intersection<'a>>) {
+        // Skip every child at once when the ray misses all of them
+        if self.bounds().intersect(local_ray).is_none() {
+            return;
+        }
+
+        let start = xs.len();
+        for child in &self.children {
+            child.intersect_into(local_ray, xs);
+        }
+
+        xs[start..].sort_by(compare_t);
+    }
+
+    // Intersections always point at a child, so this is never used for
+    // shading
+    fn local_normal_at(&self, _local_point: Point) -> Vector {
+        panic!("Groups have no surface of their own to take a normal of")
+    }
+
+    fn local_contains_point(&self, local_point: Point) -> bool {
+        self.children
+            .iter()
+            .any(|child| child.contains_point(local_point))
+    }
+
+    fn includes(&self, other: &dyn Shape) -> bool {
+        std::ptr::addr_eq(self as *const Self, other as *const dyn Shape)
+            || self.children.iter().any(|child| child.includes(other))
+    }
+
+    fn hash_geometry_into(&self, h: &mut StableHasher) {
+        h.write_usize(self.children.len());
+        for child in &self.children {
+            child.hash_into(h);
+        }
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::empty();
+        for child in &self.children {
+            bounds.add_box(&child.parent_space_bounds());
+        }
+
+        bounds
+    }
+}
+
+impl Group {
+    pub fn new() -> Self {
+        Self {
+            transformation: Matrix::identity(),
+            parent_transformation: Matrix::identity(),
+            material: Material::new(),
+            motion: None,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn add_child(&mut self, mut child: Box<dyn Shape>) {
+        child.set_parent_transformation(self.world_transformation());
+        self.children.push(child);
+    }
+
+    // Pushes the group's transformation down into its children and lifts
+    // the children of nested groups up into this one, so intersecting the
+    // group takes a single transformation per shape. Groups lose their
+    // bounding box test in the process, which is left to World::build_grid.
+    // Shapes with motion keep their own animated transformations, so this
+    // is meant for static scenes.
+    pub fn bake(&mut self) {
+        let transformation = std::mem::replace(&mut self.transformation, Matrix::identity());
+        let mut baked = Vec::with_capacity(self.children.len());
+
+        for mut child in std::mem::take(&mut self.children) {
+            child.pre_transform(&transformation);
+
+            match child.as_any_mut().downcast_mut::<Group>() {
+                Some(group) => {
+                    group.bake();
+                    baked.append(&mut group.children);
+                }
+                None => baked.push(child),
+            }
+        }
+
+        self.children = baked;
+        self.update_children();
+    }
+
+    // Every shape in the group that isn't a group itself, in the order they
+    // were added. Unlike bake this leaves the group as it is. Each leaf's
+    // world_transformation already includes the groups above it.
+    pub fn flatten(&self) -> Vec<&dyn Shape> {
+        let mut leaves = Vec::new();
+        for child in &self.children {
+            match child.as_any().downcast_ref::<Group>() {
+                Some(group) => leaves.append(&mut group.flatten()),
+                None => leaves.push(child.as_ref()),
+            }
+        }
+
+        leaves
+    }
+
+    // Hands the group's transformation down to every descendant, so their
+    // normals can be taken straight from world space
+    fn update_children(&mut self) {
+        let world_transformation = self.world_transformation();
+        for child in &mut self.children {
+            child.set_parent_transformation(world_transformation.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::{
+        constants::EPSILON,
+        shapes::sphere::Sphere,
+        transformation::{rotation_y, scaling, translation},
+    };
+
+    use super::*;
+
+    mod shape_default_tests {
+        use crate::transformation::translation;
+
+        use super::*;
+
+        #[test]
+        fn default_transformation() {
+            let s = Group::new();
+            assert_eq!(s.transformation, Matrix::identity());
+        }
+
+        #[test]
+        fn assigning_transformation() {
+            let mut s = Group::new();
+            s.set_transformation(translation(2.0, 3.0, 4.0));
+            assert_eq!(s.transformation, translation(2.0, 3.0, 4.0));
+        }
+
+        #[test]
+        fn default_material() {
+            let s = Group::new();
+            assert_eq!(s.material, Material::new());
+        }
+
+        #[test]
+        fn assigning_material() {
+            let mut s = Group::new();
+            s.add_child(Box::new(Sphere::new()));
+
+            let mut m = Material::new();
+            m.ambient = 1.0;
+
+            s.set_material(m.clone());
+
+            assert_eq!(s.material, m);
+            assert_eq!(s.children[0].material(), m);
+        }
+    }
+
+    #[test]
+    fn intersecting_ray_with_empty_group() {
+        let g = Group::new();
+        let r = Ray::new(Point::origin(), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(g.children.is_empty());
+        assert_eq!(g.local_intersect(r).len(), 0);
+    }
+
+    #[test]
+    fn intersecting_ray_with_nonempty_group() {
+        let mut g = Group::new();
+        let s1 = Sphere::new();
+        let mut s2 = Sphere::new();
+        s2.set_transformation(translation(0.0, 0.0, -3.0));
+        let mut s3 = Sphere::new();
+        s3.set_transformation(translation(5.0, 0.0, 0.0));
+        g.add_child(Box::new(s1.clone()));
+        g.add_child(Box::new(s2.clone()));
+        g.add_child(Box::new(s3));
+
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = g.local_intersect(r);
+
+        assert_eq!(xs.len(), 4);
+        assert!(xs[0].object.equals(&s2));
+        assert!(xs[1].object.equals(&s2));
+        assert!(xs[2].object.equals(&s1));
+        assert!(xs[3].object.equals(&s1));
+    }
+
+    #[test]
+    fn intersecting_transformed_group() {
+        let mut g = Group::new();
+        g.set_transformation(scaling(2.0, 2.0, 2.0));
+        let mut s = Sphere::new();
+        s.set_transformation(translation(5.0, 0.0, 0.0));
+        g.add_child(Box::new(s));
+
+        let r = Ray::new(Point::new(10.0, 0.0, -10.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = g.intersect(r);
+
+        assert_eq!(xs.len(), 2);
+    }
+
+    #[test]
+    fn group_bounds_contain_its_children() {
+        let mut g = Group::new();
+        let mut s1 = Sphere::new();
+        s1.set_transformation(translation(2.0, 5.0, -3.0) * scaling(2.0, 2.0, 2.0));
+        let mut s2 = Sphere::new();
+        s2.set_transformation(translation(-4.0, 0.0, 0.0));
+        g.add_child(Box::new(s1));
+        g.add_child(Box::new(s2));
+
+        assert_eq!(
+            g.bounds(),
+            BoundingBox::new(Point::new(-5.0, -1.0, -5.0), Point::new(4.0, 7.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn group_contains_points_inside_its_children() {
+        let mut g = Group::new();
+        g.set_transformation(translation(0.0, 10.0, 0.0));
+        g.add_child(Box::new(Sphere::new()));
+
+        assert!(g.contains_point(Point::new(0.0, 10.0, 0.5)));
+        assert!(!g.contains_point(Point::origin()));
+    }
+
+    // Two nested groups, rotated and then scaled, around a translated sphere.
+    // Returns the outer group, whose only descendant is the sphere.
+    fn nested_groups(inner_scaling: Matrix) -> Group {
+        let mut g1 = Group::new();
+        g1.set_transformation(rotation_y(PI / 2.0));
+        let mut g2 = Group::new();
+        g2.set_transformation(inner_scaling);
+        let mut s = Sphere::new();
+        s.set_transformation(translation(5.0, 0.0, 0.0));
+        g2.add_child(Box::new(s));
+        g1.add_child(Box::new(g2));
+
+        g1
+    }
+
+    fn only_grandchild(g: &Group) -> &dyn Shape {
+        let inner = g.children[0].as_any().downcast_ref::<Group>().unwrap();
+        inner.children[0].as_ref()
+    }
+
+    #[test]
+    fn converting_point_from_world_to_object_space() {
+        let g = nested_groups(scaling(2.0, 2.0, 2.0));
+        let s = only_grandchild(&g);
+
+        let p = s.world_to_object(Point::new(-2.0, 0.0, -10.0));
+
+        assert_eq!(p, Point::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn converting_normal_from_object_to_world_space() {
+        let g = nested_groups(scaling(1.0, 2.0, 3.0));
+        let s = only_grandchild(&g);
+
+        let third = 3.0_f64.sqrt() / 3.0;
+        let n = s.normal_to_world(Vector::new(third, third, third));
+
+        assert_eq!(n, Vector::new(2.0 / 7.0, 3.0 / 7.0, -6.0 / 7.0));
+    }
+
+    #[test]
+    fn finding_normal_on_child_object() {
+        let g = nested_groups(scaling(1.0, 2.0, 3.0));
+        let s = only_grandchild(&g);
+
+        let root3 = 3.0_f64.sqrt();
+        let n = s.normal_at(Point::new(root3, 2.0 / root3, -5.0 - 1.0 / root3));
+
+        assert_eq!(n, Vector::new(2.0 / 7.0, 3.0 / 7.0, -6.0 / 7.0));
+    }
+
+    #[test]
+    fn transforming_group_after_adding_children_updates_them() {
+        let mut g = Group::new();
+        g.add_child(Box::new(Sphere::new()));
+        g.set_transformation(translation(0.0, 3.0, 0.0));
+
+        assert_eq!(
+            g.children[0].world_to_object(Point::new(0.0, 4.0, 0.0)),
+            Point::new(0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn baked_group_intersects_like_unbaked_group() {
+        let build = || {
+            let mut g = nested_groups(scaling(1.0, 2.0, 3.0));
+            let mut s = Sphere::new();
+            s.set_transformation(translation(0.0, -2.0, 0.0) * scaling(0.5, 0.5, 0.5));
+            g.add_child(Box::new(s));
+            g.set_transformation(translation(1.0, 0.0, 0.0) * rotation_y(PI / 2.0));
+            g
+        };
+        let unbaked = build();
+        let mut baked = build();
+        baked.bake();
+
+        assert_eq!(baked.transformation, Matrix::identity());
+        assert_eq!(baked.children.len(), 2);
+
+        let probes = [
+            Ray::new(Point::new(-10.0, 0.0, -5.0), Vector::new(1.0, 0.0, 0.0)),
+            Ray::new(Point::new(1.0, 1.0, -20.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(1.0, -5.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+            Ray::new(
+                Point::new(-3.0, 2.0, -8.0),
+                Vector::new(0.5, -0.2, 0.6).normalize(),
+            ),
+        ];
+
+        for r in probes {
+            let expected = unbaked.intersect(r);
+            let actual = baked.intersect(r);
+
+            assert_eq!(actual.len(), expected.len());
+            for (a, e) in actual.iter().zip(&expected) {
+                assert!((a.t - e.t).abs() < EPSILON);
+
+                let point = r.position(a.t);
+                assert_eq!(a.object.normal_at(point), e.object.normal_at(point));
+            }
+        }
+    }
+
+    #[test]
+    fn flattening_nested_groups() {
+        let mut inner = Group::new();
+        inner.set_transformation(scaling(2.0, 2.0, 2.0));
+        let mut s1 = Sphere::new();
+        s1.set_transformation(translation(1.0, 0.0, 0.0));
+        inner.add_child(Box::new(s1));
+        inner.add_child(Box::new(Sphere::new()));
+
+        let mut outer = Group::new();
+        outer.add_child(Box::new(inner));
+        let mut s3 = Sphere::new();
+        s3.set_transformation(translation(0.0, 0.0, 5.0));
+        outer.add_child(Box::new(s3));
+        outer.set_transformation(rotation_y(PI / 2.0));
+
+        let leaves = outer.flatten();
+
+        assert_eq!(leaves.len(), 3);
+        assert_eq!(
+            leaves[0].world_transformation(),
+            rotation_y(PI / 2.0) * scaling(2.0, 2.0, 2.0) * translation(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            leaves[1].world_transformation(),
+            rotation_y(PI / 2.0) * scaling(2.0, 2.0, 2.0)
+        );
+        assert_eq!(
+            leaves[2].world_transformation(),
+            rotation_y(PI / 2.0) * translation(0.0, 0.0, 5.0)
+        );
+        // The group itself is left untouched
+        assert_eq!(outer.children.len(), 2);
+    }
+}
@@ -1,11 +1,13 @@
 use std::any::Any;
 
 use crate::{
+    bounds::BoundingBox,
     constants::EPSILON,
     intersection::Intersection,
     materials::Material,
     matrices::Matrix,
     rays::Ray,
+    transformation::Motion,
     tuples::{Point, Vector},
 };
 
@@ -14,7 +16,9 @@ use super::Shape;
 #[derive(Debug)]
 pub struct Plane {
     pub transformation: Matrix,
+    pub parent_transformation: Matrix,
     pub material: Material,
+    pub motion: Option<Motion>,
 }
 
 impl Shape for Plane {
@@ -22,6 +26,10 @@ impl Shape for Plane {
         self
     }
 
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
     fn equals(&self, other: &dyn Shape) -> bool {
         if let Some(other) = other.as_any().downcast_ref::<Plane>() {
             self.transformation == other.transformation && self.material == other.material
@@ -46,25 +54,46 @@ impl Shape for Plane {
         self.transformation = m;
     }
 
-    fn local_intersect(&self, local_ray: Ray) -> Vec<Intersection> {
+    fn parent_transformation(&self) -> Matrix {
+        self.parent_transformation.clone()
+    }
+
+    fn set_parent_transformation(&mut self, m: Matrix) {
+        self.parent_transformation = m;
+    }
+
+    fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    fn local_intersect_into<'a>(&'a self, local_ray: Ray, xs: &mut Vec<Intersection<'a>>) {
         if local_ray.direction.1.abs() < EPSILON {
-            return Vec::new();
+            return;
         }
 
         let t = -local_ray.origin.1 / local_ray.direction.1;
-        vec![Intersection::new(t, self)]
+        xs.push(Intersection::new(t, self));
     }
 
     fn local_normal_at(&self, _local_point: Point) -> Vector {
         Vector::new(0.0, 1.0, 0.0)
     }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Point::new(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+            Point::new(f64::INFINITY, 0.0, f64::INFINITY),
+        )
+    }
 }
 
 impl Plane {
     pub fn new() -> Self {
         Self {
             transformation: Matrix::identity(),
+            parent_transformation: Matrix::identity(),
             material: Material::new(),
+            motion: None,
         }
     }
 }
@@ -125,6 +154,18 @@ mod tests {
         assert_eq!(n3, Vector::new(0.0, 1.0, 0.0));
     }
 
+    #[test]
+    fn plane_has_bounding_box() {
+        let p = Plane::new();
+
+        let b = p.bounds();
+
+        assert_eq!(b.min.1, 0.0);
+        assert_eq!(b.max.1, 0.0);
+        assert_eq!(b.min.0, f64::NEG_INFINITY);
+        assert_eq!(b.max.2, f64::INFINITY);
+    }
+
     #[test]
     fn intersect_with_ray_parallel_to_plane() {
         let p = Plane::new();
@@ -168,4 +209,13 @@ mod tests {
         assert_eq!(xs[0].t, 1.0);
         assert!(xs[0].object.equals(&p));
     }
+
+    #[test]
+    fn plane_contains_no_points() {
+        let p = Plane::new();
+
+        assert!(!p.contains_point(Point::origin()));
+        assert!(!p.contains_point(Point::new(0.0, -1.0, 0.0)));
+        assert!(!p.contains_point(Point::new(3.0, 1.0, -2.0)));
+    }
 }
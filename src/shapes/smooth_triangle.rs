@@ -0,0 +1,253 @@
+use std::any::Any;
+
+use crate::{
+    bounds::BoundingBox,
+    intersection::Intersection,
+    materials::Material,
+    matrices::Matrix,
+    rays::Ray,
+    transformation::Motion,
+    tuples::{Point, Vector},
+    utils::StableHasher,
+};
+
+use super::{
+    triangle::{barycentric_hit, interpolate_uv},
+    Shape,
+};
+
+// A triangle with a normal at each corner, blended across the face so meshes
+// look smoothly curved instead of faceted
+#[derive(Debug, PartialEq, Clone)]
+pub struct SmoothTriangle {
+    pub transformation: Matrix,
+    pub parent_transformation: Matrix,
+    pub material: Material,
+    pub motion: Option<Motion>,
+    pub p1: Point,
+    pub p2: Point,
+    pub p3: Point,
+    pub n1: Vector,
+    pub n2: Vector,
+    pub n3: Vector,
+    pub texture_uvs: Option<[(f64, f64); 3]>,
+    pub e1: Vector,
+    pub e2: Vector,
+}
+
+impl Shape for SmoothTriangle {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn equals(&self, other: &dyn Shape) -> bool {
+        if let Some(other) = other.as_any().downcast_ref::<SmoothTriangle>() {
+            self.transformation == other.transformation
+                && self.material == other.material
+                && self.p1 == other.p1
+                && self.p2 == other.p2
+                && self.p3 == other.p3
+                && self.n1 == other.n1
+                && self.n2 == other.n2
+                && self.n3 == other.n3
+        } else {
+            false
+        }
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn set_material(&mut self, m: Material) {
+        self.material = m;
+    }
+
+    fn transformation(&self) -> Matrix {
+        self.transformation.clone()
+    }
+
+    fn set_transformation(&mut self, m: Matrix) {
+        self.transformation = m;
+    }
+
+    fn parent_transformation(&self) -> Matrix {
+        self.parent_transformation.clone()
+    }
+
+    fn set_parent_transformation(&mut self, m: Matrix) {
+        self.parent_transformation = m;
+    }
+
+    fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    fn local_intersect_into<'a>(&'a self, local_ray: Ray, xs: &mut Vec<Intersection<'a>>) {
+        if let Some((t, u, v)) = barycentric_hit(local_ray, self.p1, self.e1, self.e2) {
+            xs.push(Intersection::with_uv(t, self, u, v));
+        }
+    }
+
+    // Without a hit to interpolate at, the flat face normal
+    fn local_normal_at(&self, _local_point: Point) -> Vector {
+        self.e2.cross(&self.e1).normalize()
+    }
+
+    fn local_normal_at_uv(&self, local_point: Point, uv: Option<(f64, f64)>) -> Vector {
+        match uv {
+            Some((u, v)) => self.n2 * u + self.n3 * v + self.n1 * (1.0 - u - v),
+            None => self.local_normal_at(local_point),
+        }
+    }
+
+    fn texture_uv(&self, uv: Option<(f64, f64)>) -> Option<(f64, f64)> {
+        Some(interpolate_uv(self.texture_uvs?, uv?))
+    }
+
+    fn hash_geometry_into(&self, h: &mut StableHasher) {
+        for p in [self.p1, self.p2, self.p3] {
+            h.write_f64s(&[p.0, p.1, p.2]);
+        }
+        for n in [self.n1, self.n2, self.n3] {
+            h.write_f64s(&[n.0, n.1, n.2]);
+        }
+        h.write_bool(self.texture_uvs.is_some());
+        for (u, v) in self.texture_uvs.iter().flatten() {
+            h.write_f64s(&[*u, *v]);
+        }
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::empty();
+        for p in [self.p1, self.p2, self.p3] {
+            bounds.add_point(p);
+        }
+
+        bounds
+    }
+}
+
+impl SmoothTriangle {
+    pub fn new(p1: Point, p2: Point, p3: Point, n1: Vector, n2: Vector, n3: Vector) -> Self {
+        Self {
+            transformation: Matrix::identity(),
+            parent_transformation: Matrix::identity(),
+            material: Material::new(),
+            motion: None,
+            p1,
+            p2,
+            p3,
+            n1,
+            n2,
+            n3,
+            texture_uvs: None,
+            e1: p2 - p1,
+            e2: p3 - p1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{constants::EPSILON, shapes::sphere::Sphere};
+
+    use super::*;
+
+    fn smooth_triangle() -> SmoothTriangle {
+        SmoothTriangle::new(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+            Vector::new(-1.0, 0.0, 0.0),
+            Vector::new(1.0, 0.0, 0.0),
+        )
+    }
+
+    mod shape_default_tests {
+        use crate::transformation::translation;
+
+        use super::*;
+
+        #[test]
+        fn default_transformation() {
+            let s = smooth_triangle();
+            assert_eq!(s.transformation, Matrix::identity());
+        }
+
+        #[test]
+        fn assigning_transformation() {
+            let mut s = smooth_triangle();
+            s.set_transformation(translation(2.0, 3.0, 4.0));
+            assert_eq!(s.transformation, translation(2.0, 3.0, 4.0));
+        }
+
+        #[test]
+        fn default_material() {
+            let s = smooth_triangle();
+            assert_eq!(s.material, Material::new());
+        }
+
+        #[test]
+        fn assigning_material() {
+            let mut s = smooth_triangle();
+
+            let mut m = Material::new();
+            m.ambient = 1.0;
+
+            s.set_material(m.clone());
+
+            assert_eq!(s.material, m);
+        }
+    }
+
+    #[test]
+    fn intersection_stores_barycentric_coordinates() {
+        let tri = smooth_triangle();
+        let r = Ray::new(Point::new(-0.2, 0.3, -2.0), Vector::new(0.0, 0.0, 1.0));
+
+        let xs = tri.local_intersect(r);
+
+        let (u, v) = xs[0].uv.unwrap();
+        assert!((u - 0.45).abs() < EPSILON);
+        assert!((v - 0.25).abs() < EPSILON);
+    }
+
+    #[test]
+    fn other_shapes_leave_barycentric_coordinates_empty() {
+        let s = Sphere::new();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(s.intersect(r).iter().all(|x| x.uv.is_none()));
+    }
+
+    #[test]
+    fn smooth_triangle_interpolates_normal() {
+        let tri = smooth_triangle();
+        let i = Intersection::with_uv(1.0, &tri, 0.45, 0.25);
+
+        let n = tri.normal_at_uv(Point::origin(), i.uv);
+
+        assert_eq!(n, Vector::new(-0.5547, 0.83205, 0.0));
+    }
+
+    #[test]
+    fn interpolated_normal_varies_across_the_face() {
+        let tri = smooth_triangle();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        // Each corner gets its own normal back
+        assert_eq!(tri.normal_at_uv(Point::origin(), Some((0.0, 0.0))), tri.n1);
+        assert_eq!(tri.normal_at_uv(Point::origin(), Some((1.0, 0.0))), tri.n2);
+        assert_eq!(tri.normal_at_uv(Point::origin(), Some((0.0, 1.0))), tri.n3);
+
+        // And prepare_computations picks up the interpolated one
+        let comps = Intersection::with_uv(5.0, &tri, 0.45, 0.25).prepare_computations(r);
+        assert_eq!(comps.normalv, Vector::new(-0.5547, 0.83205, 0.0));
+    }
+}
@@ -1,11 +1,13 @@
 use std::any::Any;
 
 use crate::{
+    bounds::BoundingBox,
     constants::EPSILON,
     intersection::Intersection,
     materials::Material,
     matrices::Matrix,
     rays::Ray,
+    transformation::Motion,
     tuples::{Point, Vector},
 };
 
@@ -14,7 +16,9 @@ use super::Shape;
 #[derive(Debug, PartialEq, Clone)]
 pub struct Sphere {
     pub transformation: Matrix,
+    pub parent_transformation: Matrix,
     pub material: Material,
+    pub motion: Option<Motion>,
 }
 
 impl Shape for Sphere {
@@ -22,6 +26,10 @@ impl Shape for Sphere {
         self
     }
 
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
     fn equals(&self, other: &dyn Shape) -> bool {
         if let Some(other) = other.as_any().downcast_ref::<Sphere>() {
             self.transformation == other.transformation && self.material == other.material
@@ -46,7 +54,19 @@ impl Shape for Sphere {
         self.transformation = m;
     }
 
-    fn local_intersect(&self, local_ray: Ray) -> Vec<Intersection> {
+    fn parent_transformation(&self) -> Matrix {
+        self.parent_transformation.clone()
+    }
+
+    fn set_parent_transformation(&mut self, m: Matrix) {
+        self.parent_transformation = m;
+    }
+
+    fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    fn local_intersect_into<'a>(&'a self, local_ray: Ray, xs: &mut Vec<Intersection<'a>>) {
         // Vector from the sphere's center to the ray origin
         let sphere_to_ray = local_ray.origin - Point::origin();
 
@@ -57,37 +77,48 @@ impl Shape for Sphere {
         let discriminant = b.powi(2) - 4.0 * a * c;
 
         if discriminant < 0.0 {
-            return Vec::new();
+            return;
         }
 
-        let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
-        let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
+        // A zero discriminant (a tangent ray) gives the same t twice
+        let root = discriminant.sqrt();
+        let t1 = (-b - root) / (2.0 * a);
+        let t2 = (-b + root) / (2.0 * a);
 
-        vec![Intersection::new(t1, self), Intersection::new(t2, self)]
+        xs.push(Intersection::new(t1, self));
+        xs.push(Intersection::new(t2, self));
     }
 
     fn local_normal_at(&self, local_point: Point) -> Vector {
         Vector::new(local_point.0, local_point.1, local_point.2)
     }
+
+    fn local_contains_point(&self, local_point: Point) -> bool {
+        (local_point - Point::origin()).magnitude() < 1.0
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0))
+    }
 }
 
 impl Sphere {
     pub fn new() -> Self {
         Self {
             transformation: Matrix::identity(),
+            parent_transformation: Matrix::identity(),
             material: Material::new(),
+            motion: None,
         }
     }
 
+    // A sphere of solid glass with every other setting left at its default
     pub fn glass() -> Self {
-        let mut material = Material::new();
-        material.transparency = 1.0;
-        material.refractive_index = 1.5;
+        let mut sphere = Self::new();
+        sphere.material.transparency = 1.0;
+        sphere.material.refractive_index = 1.5;
 
-        Self {
-            transformation: Matrix::identity(),
-            material,
-        }
+        sphere
     }
 }
 
@@ -97,6 +128,7 @@ mod tests {
 
     use crate::{
         color::Color,
+        constants::EPSILON,
         rays::Ray,
         transformation::{rotation_z, scaling, translation},
     };
@@ -147,6 +179,11 @@ mod tests {
         assert_eq!(s.transformation, Matrix::identity());
         assert_eq!(s.material.transparency, 1.0);
         assert_eq!(s.material.refractive_index, 1.5);
+
+        let mut plain = Material::new();
+        plain.transparency = 1.0;
+        plain.refractive_index = 1.5;
+        assert_eq!(s.material, plain);
     }
 
     #[test]
@@ -171,6 +208,59 @@ mod tests {
         assert_eq!(xs.len(), 2);
         assert_eq!(xs[0].t, 5.0);
         assert_eq!(xs[1].t, 5.0);
+        assert!(xs.iter().all(|i| !i.t.is_nan()));
+    }
+
+    #[test]
+    fn intersecting_moving_sphere_at_different_times() {
+        // Moves right at a steady pace while bobbing up along a sine wave
+        let mut s = Sphere::new();
+        s.motion = Some(Motion::new(|t| {
+            translation(4.0 * t, 2.0 * (PI * t).sin(), 0.0)
+        }));
+
+        assert_eq!(s.transformation_at(0.0), Matrix::identity());
+        assert_eq!(s.transformation_at(0.5), translation(2.0, 2.0, 0.0));
+
+        let at_start = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let at_middle = Ray::new(Point::new(2.0, 2.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert_eq!(s.intersect_at(at_start, 0.0).len(), 2);
+        assert_eq!(s.intersect_at(at_middle, 0.0).len(), 0);
+        assert_eq!(s.intersect_at(at_start, 0.5).len(), 0);
+        assert_eq!(s.intersect_at(at_middle, 0.5)[0].t, 4.0);
+    }
+
+    #[test]
+    fn sphere_without_motion_stays_put() {
+        let mut s = Sphere::new();
+        s.set_transformation(translation(1.0, 0.0, 0.0));
+
+        assert_eq!(s.transformation_at(0.7), translation(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn ray_originates_on_sphere_surface_pointing_inward() {
+        let r = Ray::new(Point::new(0.0, 0.0, -1.0), Vector::new(0.0, 0.0, 1.0));
+        let s = Sphere::new();
+
+        let xs = s.intersect(r);
+
+        assert_eq!(xs.len(), 2);
+        assert!(xs[0].t.abs() < EPSILON);
+        assert_eq!(xs[1].t, 2.0);
+    }
+
+    #[test]
+    fn ray_originates_on_sphere_surface_pointing_outward() {
+        let r = Ray::new(Point::new(0.0, 0.0, 1.0), Vector::new(0.0, 0.0, 1.0));
+        let s = Sphere::new();
+
+        let xs = s.intersect(r);
+
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, -2.0);
+        assert!(xs[1].t.abs() < EPSILON);
     }
 
     #[test]
@@ -302,6 +392,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn computing_normal_on_translated_sphere() {
         let mut s = Sphere::new();
         s.set_transformation(translation(0.0, 1.0, 0.0));
@@ -322,10 +413,35 @@ mod tests {
         assert_eq!(n, Vector::new(0.0, 0.97014, -0.24254));
     }
 
+    #[test]
+    fn sphere_has_bounding_box() {
+        let s = Sphere::new();
+
+        let b = s.bounds();
+
+        assert_eq!(b.min, Point::new(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, Point::new(1.0, 1.0, 1.0));
+    }
+
     #[test]
     fn sphere_has_default_material() {
         let s = Sphere::new();
 
         assert_eq!(s.material, Material::new());
     }
+
+    #[test]
+    fn sphere_contains_points_inside_it() {
+        let mut s = Sphere::new();
+
+        assert!(s.contains_point(Point::origin()));
+        assert!(s.contains_point(Point::new(0.0, 0.5, 0.5)));
+        assert!(!s.contains_point(Point::new(0.0, 0.0, 5.0)));
+        // Points on the surface aren't inside
+        assert!(!s.contains_point(Point::new(1.0, 0.0, 0.0)));
+
+        s.set_transformation(translation(5.0, 0.0, 0.0));
+        assert!(s.contains_point(Point::new(5.0, 0.0, 0.0)));
+        assert!(!s.contains_point(Point::origin()));
+    }
 }
@@ -0,0 +1,299 @@
+use std::any::Any;
+
+use crate::{
+    bounds::BoundingBox,
+    constants::EPSILON,
+    intersection::Intersection,
+    materials::Material,
+    matrices::Matrix,
+    rays::Ray,
+    transformation::Motion,
+    tuples::{Point, Vector},
+    utils::StableHasher,
+};
+
+use super::Shape;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Triangle {
+    pub transformation: Matrix,
+    pub parent_transformation: Matrix,
+    pub material: Material,
+    pub motion: Option<Motion>,
+    pub p1: Point,
+    pub p2: Point,
+    pub p3: Point,
+    // Texture coordinates at p1, p2 and p3, if the mesh had any
+    pub texture_uvs: Option<[(f64, f64); 3]>,
+    // Edges from p1 to the other two corners, and the face normal
+    pub e1: Vector,
+    pub e2: Vector,
+    pub normal: Vector,
+}
+
+impl Shape for Triangle {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn equals(&self, other: &dyn Shape) -> bool {
+        if let Some(other) = other.as_any().downcast_ref::<Triangle>() {
+            self.transformation == other.transformation
+                && self.material == other.material
+                && self.p1 == other.p1
+                && self.p2 == other.p2
+                && self.p3 == other.p3
+        } else {
+            false
+        }
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn set_material(&mut self, m: Material) {
+        self.material = m;
+    }
+
+    fn transformation(&self) -> Matrix {
+        self.transformation.clone()
+    }
+
+    fn set_transformation(&mut self, m: Matrix) {
+        self.transformation = m;
+    }
+
+    fn parent_transformation(&self) -> Matrix {
+        self.parent_transformation.clone()
+    }
+
+    fn set_parent_transformation(&mut self, m: Matrix) {
+        self.parent_transformation = m;
+    }
+
+    fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    fn local_intersect_into<'a>(&'a self, local_ray: Ray, xs: &mut Vec<Intersection<'a>>) {
+        if let Some((t, u, v)) = barycentric_hit(local_ray, self.p1, self.e1, self.e2) {
+            xs.push(Intersection::with_uv(t, self, u, v));
+        }
+    }
+
+    fn local_normal_at(&self, _local_point: Point) -> Vector {
+        self.normal
+    }
+
+    fn texture_uv(&self, uv: Option<(f64, f64)>) -> Option<(f64, f64)> {
+        Some(interpolate_uv(self.texture_uvs?, uv?))
+    }
+
+    fn hash_geometry_into(&self, h: &mut StableHasher) {
+        for p in [self.p1, self.p2, self.p3] {
+            h.write_f64s(&[p.0, p.1, p.2]);
+        }
+        h.write_bool(self.texture_uvs.is_some());
+        for (u, v) in self.texture_uvs.iter().flatten() {
+            h.write_f64s(&[*u, *v]);
+        }
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::empty();
+        for p in [self.p1, self.p2, self.p3] {
+            bounds.add_point(p);
+        }
+
+        bounds
+    }
+}
+
+impl Triangle {
+    pub fn new(p1: Point, p2: Point, p3: Point) -> Self {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+
+        Self {
+            transformation: Matrix::identity(),
+            parent_transformation: Matrix::identity(),
+            material: Material::new(),
+            motion: None,
+            p1,
+            p2,
+            p3,
+            texture_uvs: None,
+            e1,
+            e2,
+            normal: e2.cross(&e1).normalize(),
+        }
+    }
+}
+
+// Möller–Trumbore intersection of a ray with the triangle at p1 spanned by
+// the edges e1 and e2, which finds t along with the barycentric coordinates
+// u and v of the hit without computing the plane first
+pub(super) fn barycentric_hit(
+    ray: Ray,
+    p1: Point,
+    e1: Vector,
+    e2: Vector,
+) -> Option<(f64, f64, f64)> {
+    let dir_cross_e2 = ray.direction.cross(&e2);
+    let det = e1.dot(&dir_cross_e2);
+
+    // The ray is parallel to the triangle
+    if det.abs() < EPSILON {
+        return None;
+    }
+
+    let f = 1.0 / det;
+
+    let p1_to_origin = ray.origin - p1;
+    let u = f * p1_to_origin.dot(&dir_cross_e2);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let origin_cross_e1 = p1_to_origin.cross(&e1);
+    let v = f * ray.direction.dot(&origin_cross_e1);
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    Some((f * e2.dot(&origin_cross_e1), u, v))
+}
+
+// Blends the texture coordinates at the three corners of a triangle by the
+// barycentric coordinates of a hit, weighting them the same way as
+// SmoothTriangle weights its normals
+pub(super) fn interpolate_uv(corners: [(f64, f64); 3], (u, v): (f64, f64)) -> (f64, f64) {
+    let [(u1, v1), (u2, v2), (u3, v3)] = corners;
+    let w = 1.0 - u - v;
+
+    (u2 * u + u3 * v + u1 * w, v2 * u + v3 * v + v1 * w)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        )
+    }
+
+    mod shape_default_tests {
+        use crate::transformation::translation;
+
+        use super::*;
+
+        #[test]
+        fn default_transformation() {
+            let s = triangle();
+            assert_eq!(s.transformation, Matrix::identity());
+        }
+
+        #[test]
+        fn assigning_transformation() {
+            let mut s = triangle();
+            s.set_transformation(translation(2.0, 3.0, 4.0));
+            assert_eq!(s.transformation, translation(2.0, 3.0, 4.0));
+        }
+
+        #[test]
+        fn default_material() {
+            let s = triangle();
+            assert_eq!(s.material, Material::new());
+        }
+
+        #[test]
+        fn assigning_material() {
+            let mut s = triangle();
+
+            let mut m = Material::new();
+            m.ambient = 1.0;
+
+            s.set_material(m.clone());
+
+            assert_eq!(s.material, m);
+        }
+    }
+
+    #[test]
+    fn constructing_triangle() {
+        let t = triangle();
+
+        assert_eq!(t.e1, Vector::new(-1.0, -1.0, 0.0));
+        assert_eq!(t.e2, Vector::new(1.0, -1.0, 0.0));
+        assert_eq!(t.normal, Vector::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn normal_of_triangle_is_constant() {
+        let t = triangle();
+
+        assert_eq!(t.local_normal_at(Point::new(0.0, 0.5, 0.0)), t.normal);
+        assert_eq!(t.local_normal_at(Point::new(-0.5, 0.75, 0.0)), t.normal);
+        assert_eq!(t.local_normal_at(Point::new(0.5, 0.25, 0.0)), t.normal);
+    }
+
+    #[test]
+    fn intersecting_ray_parallel_to_triangle() {
+        let t = triangle();
+        let r = Ray::new(Point::new(0.0, -1.0, -2.0), Vector::new(0.0, 1.0, 0.0));
+
+        assert_eq!(t.local_intersect(r).len(), 0);
+    }
+
+    #[test]
+    fn ray_misses_each_edge() {
+        let t = triangle();
+        let origins = [
+            // Past the p1-p3 edge
+            Point::new(1.0, 1.0, -2.0),
+            // Past the p1-p2 edge
+            Point::new(-1.0, 1.0, -2.0),
+            // Past the p2-p3 edge
+            Point::new(0.0, -1.0, -2.0),
+        ];
+
+        for origin in origins {
+            let r = Ray::new(origin, Vector::new(0.0, 0.0, 1.0));
+            assert_eq!(t.local_intersect(r).len(), 0);
+        }
+    }
+
+    #[test]
+    fn ray_strikes_triangle() {
+        let t = triangle();
+        let r = Ray::new(Point::new(0.0, 0.5, -2.0), Vector::new(0.0, 0.0, 1.0));
+
+        let xs = t.local_intersect(r);
+
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 2.0);
+    }
+
+    #[test]
+    fn triangle_has_bounding_box() {
+        let t = Triangle::new(
+            Point::new(-3.0, 7.0, 2.0),
+            Point::new(6.0, 2.0, -4.0),
+            Point::new(2.0, -1.0, -1.0),
+        );
+
+        assert_eq!(
+            t.bounds(),
+            BoundingBox::new(Point::new(-3.0, -1.0, -4.0), Point::new(6.0, 7.0, 2.0))
+        );
+    }
+}
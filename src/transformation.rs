@@ -1,8 +1,49 @@
+use std::{fmt::Debug, sync::Arc};
+
 use crate::{
     matrices::Matrix,
     tuples::{Point, Vector},
+    utils::StableHasher,
 };
 
+// A transformation that changes over time, given as a function from a
+// normalized time (0 at the start of the frame, 1 at the end) to the
+// transformation at that moment
+#[derive(Clone)]
+pub struct Motion(Arc<dyn Fn(f64) -> Matrix + Send + Sync>);
+
+impl Motion {
+    pub fn new(f: impl Fn(f64) -> Matrix + Send + Sync + 'static) -> Self {
+        Motion(Arc::new(f))
+    }
+
+    pub fn at(&self, time: f64) -> Matrix {
+        (self.0)(time)
+    }
+
+    // Functions can't be hashed, so this hashes where the motion puts the
+    // shape at a few times through the frame instead
+    pub fn hash_into(&self, h: &mut StableHasher) {
+        for time in [0.0, 0.25, 0.5, 0.75, 1.0] {
+            self.at(time).hash_into(h);
+        }
+    }
+}
+
+impl Debug for Motion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Motion({:?} -> {:?})", self.at(0.0), self.at(1.0))
+    }
+}
+
+// Functions can't be compared, so motions are only equal if they share the
+// same function
+impl PartialEq for Motion {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
 pub fn translation(x: f64, y: f64, z: f64) -> Matrix {
     let data = vec![
         vec![1.0, 0.0, 0.0, x],
@@ -86,11 +127,23 @@ pub fn view_transform(from: Point, to: Point, up: Vector) -> Matrix {
     orientation * translation(-from.0, -from.1, -from.2)
 }
 
+// Mirrors a point across the plane passing through plane_point with the given normal
+pub fn reflect_point_across_plane(p: Point, plane_point: Point, normal: Vector) -> Point {
+    let n = normal.normalize();
+    let distance = (p - plane_point).dot(&n);
+
+    p - n * 2.0 * distance
+}
+
 #[cfg(test)]
 mod tests {
     use std::f64::consts::PI;
 
-    use crate::tuples::{Point, Vector};
+    use crate::{
+        constants::EPSILON,
+        matrices::assert_matrix_eq,
+        tuples::{Point, Vector},
+    };
 
     use super::*;
 
@@ -244,6 +297,62 @@ mod tests {
         assert_eq!(transform * p, Point::new(2.0, 3.0, 7.0));
     }
 
+    #[test]
+    fn rotations_are_orthogonal() {
+        assert!(rotation_x(PI / 4.0).is_orthogonal(EPSILON));
+        assert!(rotation_y(PI / 3.0).is_orthogonal(EPSILON));
+        assert!((rotation_z(0.1) * rotation_x(2.0) * rotation_y(-1.3)).is_orthogonal(EPSILON));
+    }
+
+    #[test]
+    fn scaling_and_translation_are_not_orthogonal() {
+        assert!(!scaling(2.0, 1.0, 1.0).is_orthogonal(EPSILON));
+        assert!(!translation(1.0, 2.0, 3.0).is_orthogonal(EPSILON));
+    }
+
+    #[test]
+    fn symmetry_of_transformations() {
+        assert!(scaling(2.0, 3.0, 4.0).is_symmetric(EPSILON));
+        assert!(!rotation_x(PI / 4.0).is_symmetric(EPSILON));
+        assert!(!shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0).is_symmetric(EPSILON));
+    }
+
+    mod reflect_point_across_plane {
+        use super::*;
+
+        #[test]
+        fn reflecting_across_xz_plane() {
+            let p = Point::new(1.0, 2.0, 3.0);
+
+            let r = reflect_point_across_plane(p, Point::origin(), Vector::new(0.0, 1.0, 0.0));
+
+            assert_eq!(r, Point::new(1.0, -2.0, 3.0));
+        }
+
+        #[test]
+        fn reflecting_across_offset_plane() {
+            let p = Point::new(1.0, 3.0, 3.0);
+
+            let r = reflect_point_across_plane(
+                p,
+                Point::new(5.0, 1.0, -2.0),
+                Vector::new(0.0, 1.0, 0.0),
+            );
+
+            assert_eq!(r, Point::new(1.0, -1.0, 3.0));
+        }
+
+        #[test]
+        fn reflecting_across_tilted_plane() {
+            // The plane x = y, whose normal doesn't need to be normalized
+            let p = Point::new(3.0, 1.0, 2.0);
+
+            let r = reflect_point_across_plane(p, Point::origin(), Vector::new(1.0, -1.0, 0.0));
+
+            assert_eq!(r, Point::new(1.0, 3.0, 2.0));
+        }
+    }
+
     mod view_transform {
         use super::*;
 
@@ -255,7 +364,7 @@ mod tests {
 
             let t = view_transform(from, to, up);
 
-            assert_eq!(t, Matrix::identity());
+            assert_matrix_eq!(t, Matrix::identity());
         }
 
         #[test]
@@ -266,7 +375,7 @@ mod tests {
 
             let t = view_transform(from, to, up);
 
-            assert_eq!(t, scaling(-1.0, 1.0, -1.0));
+            assert_matrix_eq!(t, scaling(-1.0, 1.0, -1.0));
         }
 
         #[test]
@@ -277,7 +386,7 @@ mod tests {
 
             let t = view_transform(from, to, up);
 
-            assert_eq!(t, translation(0.0, 0.0, -8.0));
+            assert_matrix_eq!(t, translation(0.0, 0.0, -8.0));
         }
 
         #[test]
@@ -288,7 +397,7 @@ mod tests {
 
             let t = view_transform(from, to, up);
 
-            assert_eq!(
+            assert_matrix_eq!(
                 t,
                 Matrix::from_vec(vec![
                     vec![-0.50709, 0.50709, 0.67612, -2.36643],
@@ -1,4 +1,4 @@
-use crate::{constants::EPSILON, transformation};
+use crate::{constants::EPSILON, error::Error, transformation, utils::snap_to_integer};
 
 pub trait Tuple {
     fn translate(self, x: f64, y: f64, z: f64) -> Self;
@@ -49,11 +49,26 @@ impl Vector {
     }
 
     pub fn from_vec(vec: Vec<f64>) -> Self {
-        if vec.len() != 4 {
-            panic!("Invalid length of vector received: {:?}", vec);
+        match Vector::try_from_vec(&vec) {
+            Ok(t) => t,
+            Err(_) => panic!("Invalid length of vector received: {:?}", vec),
         }
+    }
+
+    pub fn try_from_vec(vec: &[f64]) -> Result<Self, Error> {
+        match vec {
+            [x, y, z, w] => Ok(Vector(*x, *y, *z, *w)),
+            _ => Err(Error::InvalidTupleLength(vec.len())),
+        }
+    }
 
-        Vector(vec[0], vec[1], vec[2], vec[3])
+    // Snaps each component that is within tol of an integer onto it
+    pub fn snap(self, tol: f64) -> Self {
+        Vector::new(
+            snap_to_integer(self.0, tol),
+            snap_to_integer(self.1, tol),
+            snap_to_integer(self.2, tol),
+        )
     }
 
     pub fn magnitude(&self) -> f64 {
@@ -78,9 +93,50 @@ impl Vector {
         )
     }
 
+    // Orthonormal (tangent, bitangent, normal) frame around this vector.
+    // Crosses with the axis the vector is least aligned with so the tangent
+    // never degenerates.
+    pub fn build_basis(&self) -> (Vector, Vector, Vector) {
+        let normal = self.normalize();
+
+        let axis = if normal.0.abs() <= normal.1.abs() && normal.0.abs() <= normal.2.abs() {
+            Vector::new(1.0, 0.0, 0.0)
+        } else if normal.1.abs() <= normal.2.abs() {
+            Vector::new(0.0, 1.0, 0.0)
+        } else {
+            Vector::new(0.0, 0.0, 1.0)
+        };
+
+        let tangent = axis.cross(&normal).normalize();
+        let bitangent = normal.cross(&tangent);
+
+        (tangent, bitangent, normal)
+    }
+
     pub fn reflect(self, normal: Self) -> Self {
         self - normal * 2.0 * self.dot(&normal)
     }
+
+    // Component-wise smallest of the two vectors
+    pub fn min(a: Vector, b: Vector) -> Self {
+        Vector::new(a.0.min(b.0), a.1.min(b.1), a.2.min(b.2))
+    }
+
+    // Component-wise largest of the two vectors
+    pub fn max(a: Vector, b: Vector) -> Self {
+        Vector::new(a.0.max(b.0), a.1.max(b.1), a.2.max(b.2))
+    }
+
+    // Linear blend from self at t = 0 to other at t = 1
+    pub fn lerp(self, other: Vector, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Default for Vector {
+    fn default() -> Self {
+        Vector::new(0.0, 0.0, 0.0)
+    }
 }
 
 impl PartialEq for Vector {
@@ -204,11 +260,65 @@ impl Point {
     }
 
     pub fn from_vec(vec: Vec<f64>) -> Self {
-        if vec.len() != 4 {
-            panic!("Invalid length of vector received: {:?}", vec);
+        match Point::try_from_vec(&vec) {
+            Ok(t) => t,
+            Err(_) => panic!("Invalid length of vector received: {:?}", vec),
         }
+    }
+
+    pub fn try_from_vec(vec: &[f64]) -> Result<Self, Error> {
+        match vec {
+            [x, y, z, w] => Ok(Point(*x, *y, *z, *w)),
+            _ => Err(Error::InvalidTupleLength(vec.len())),
+        }
+    }
+
+    // Snaps each component that is within tol of an integer onto it
+    pub fn snap(self, tol: f64) -> Self {
+        Point::new(
+            snap_to_integer(self.0, tol),
+            snap_to_integer(self.1, tol),
+            snap_to_integer(self.2, tol),
+        )
+    }
+
+    // Component-wise smallest of the two points
+    pub fn min(a: Point, b: Point) -> Self {
+        Point::new(a.0.min(b.0), a.1.min(b.1), a.2.min(b.2))
+    }
+
+    // Component-wise largest of the two points
+    pub fn max(a: Point, b: Point) -> Self {
+        Point::new(a.0.max(b.0), a.1.max(b.1), a.2.max(b.2))
+    }
+
+    // Linear blend from self at t = 0 to other at t = 1
+    pub fn lerp(self, other: Point, t: f64) -> Self {
+        self + (other - self) * t
+    }
+
+    pub fn midpoint(a: Point, b: Point) -> Self {
+        Point::new((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0, (a.2 + b.2) / 2.0)
+    }
+
+    // Average position of the given points
+    pub fn centroid(points: &[Point]) -> Self {
+        if points.is_empty() {
+            panic!("Cannot take the centroid of no points");
+        }
+
+        let n = points.len() as f64;
+        let (x, y, z) = points
+            .iter()
+            .fold((0.0, 0.0, 0.0), |(x, y, z), p| (x + p.0, y + p.1, z + p.2));
+
+        Point::new(x / n, y / n, z / n)
+    }
+}
 
-        Point(vec[0], vec[1], vec[2], vec[3])
+impl Default for Point {
+    fn default() -> Self {
+        Point::origin()
     }
 }
 
@@ -314,6 +424,76 @@ mod tests {
         assert_eq!(p - v, Point::new(-2.0, -4.0, -6.0));
     }
 
+    #[test]
+    fn midpoint_of_two_points() {
+        let a = Point::origin();
+        let b = Point::new(2.0, 4.0, 6.0);
+
+        assert_eq!(Point::midpoint(a, b), Point::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn component_wise_min_and_max_of_points() {
+        let a = Point::new(1.0, 5.0, 2.0);
+        let b = Point::new(3.0, 2.0, 4.0);
+
+        assert_eq!(Point::min(a, b), Point::new(1.0, 2.0, 2.0));
+        assert_eq!(Point::max(a, b), Point::new(3.0, 5.0, 4.0));
+    }
+
+    #[test]
+    fn component_wise_min_and_max_of_vectors() {
+        let a = Vector::new(1.0, -5.0, 2.0);
+        let b = Vector::new(-3.0, 2.0, 4.0);
+
+        assert_eq!(Vector::min(a, b), Vector::new(-3.0, -5.0, 2.0));
+        assert_eq!(Vector::max(a, b), Vector::new(1.0, 2.0, 4.0));
+    }
+
+    #[test]
+    fn lerping_points() {
+        let a = Point::origin();
+        let b = Point::new(2.0, 2.0, 2.0);
+
+        assert_eq!(a.lerp(b, 0.0), a);
+        assert_eq!(a.lerp(b, 1.0), b);
+        let mid = a.lerp(b, 0.5);
+        assert_eq!(mid, Point::new(1.0, 1.0, 1.0));
+        assert_eq!(mid.3, 1.0);
+    }
+
+    #[test]
+    fn lerping_vectors() {
+        let a = Vector::new(0.0, 0.0, 0.0);
+        let b = Vector::new(2.0, 2.0, 2.0);
+
+        assert_eq!(a.lerp(b, 0.0), a);
+        assert_eq!(a.lerp(b, 1.0), b);
+        let mid = a.lerp(b, 0.5);
+        assert_eq!(mid, Vector::new(1.0, 1.0, 1.0));
+        assert_eq!(mid.3, 0.0);
+    }
+
+    #[test]
+    fn snapping_nearly_integer_components() {
+        let p = Point::new(0.9999999999, 0.0, 2.0000000001).snap(EPSILON);
+        assert_eq!((p.0, p.1, p.2), (1.0, 0.0, 2.0));
+
+        let v = Vector::new(-0.0000000001, 0.5, 3.0).snap(EPSILON);
+        assert_eq!((v.0, v.1, v.2), (0.0, 0.5, 3.0));
+    }
+
+    #[test]
+    fn centroid_of_triangle_vertices() {
+        let vertices = [
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 3.0),
+            Point::new(4.0, -4.0, 0.0),
+        ];
+
+        assert_eq!(Point::centroid(&vertices), Point::new(1.0, -1.0, 1.0));
+    }
+
     #[test]
     fn subtracting_two_vectors() {
         let v1 = Vector::new(3.0, 2.0, 1.0);
@@ -417,6 +597,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn building_basis_gives_orthonormal_frame() {
+        let normals = [
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+            Vector::new(0.0, 0.0, 1.0),
+            Vector::new(1.0, 1.0, 1.0),
+            Vector::new(-2.0, 0.5, 3.0),
+            Vector::new(0.0, 1.0, 1e-9),
+        ];
+
+        for n in normals {
+            let (t, b, normal) = n.build_basis();
+
+            assert_eq!(normal, n.normalize());
+            for v in [t, b, normal] {
+                assert!((v.magnitude() - 1.0).abs() < EPSILON);
+            }
+            assert!(t.dot(&b).abs() < EPSILON);
+            assert!(t.dot(&normal).abs() < EPSILON);
+            assert!(b.dot(&normal).abs() < EPSILON);
+        }
+    }
+
     #[test]
     fn reflecting_vector_at_45deg() {
         let v = Vector::new(1.0, -1.0, 0.0);
@@ -436,4 +640,20 @@ mod tests {
 
         assert_eq!(r, Vector::new(1.0, 0.0, 0.0));
     }
+
+    #[test]
+    fn trying_to_build_tuples_from_wrong_length_slices() {
+        assert_eq!(
+            Point::try_from_vec(&[1.0, 2.0, 3.0, 1.0]),
+            Ok(Point::new(1.0, 2.0, 3.0))
+        );
+        assert_eq!(
+            Point::try_from_vec(&[1.0, 2.0, 3.0]).unwrap_err(),
+            Error::InvalidTupleLength(3)
+        );
+        assert_eq!(
+            Vector::try_from_vec(&[1.0, 2.0, 3.0, 0.0, 0.0]).unwrap_err(),
+            Error::InvalidTupleLength(5)
+        );
+    }
 }
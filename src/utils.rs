@@ -7,3 +7,105 @@ pub fn zero_if_trivial(n: f64) -> f64 {
         n
     }
 }
+
+// Rounds n to the nearest integer if it is within tol of it, which keeps
+// floor() from jumping a whole step on values like 0.9999999999
+pub fn snap_to_integer(n: f64, tol: f64) -> f64 {
+    let rounded = n.round();
+
+    if (n - rounded).abs() < tol {
+        rounded
+    } else {
+        n
+    }
+}
+
+// Deterministic pseudo-random number in [0, 1) derived from a seed and a
+// set of inputs, so the same inputs always give the same value without any
+// shared generator state
+pub fn seeded_random(seed: u64, inputs: &[f64]) -> f64 {
+    let hash = inputs
+        .iter()
+        .fold(splitmix64(seed), |h, v| splitmix64(h ^ v.to_bits()));
+
+    (hash >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+// The index-th element of the van der Corput sequence in the given base,
+// which mirrors the digits of index around the decimal point. Pairing bases
+// 2 and 3 gives the 2D Halton sequence.
+pub fn halton(index: usize, base: usize) -> f64 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    let mut i = index;
+
+    while i > 0 {
+        fraction /= base as f64;
+        result += fraction * (i % base) as f64;
+        i /= base;
+    }
+
+    result
+}
+
+// 64 bit FNV-1a hash, fed one value at a time. Unlike std's DefaultHasher
+// it is specified to never change, so hashes can be stored between runs.
+pub struct StableHasher(u64);
+
+impl StableHasher {
+    pub fn new() -> Self {
+        StableHasher(0xcbf29ce484222325)
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub fn write_u64(&mut self, x: u64) {
+        self.write(&x.to_le_bytes());
+    }
+
+    pub fn write_usize(&mut self, x: usize) {
+        self.write_u64(x as u64);
+    }
+
+    pub fn write_bool(&mut self, x: bool) {
+        self.write(&[x as u8]);
+    }
+
+    // By bit pattern, which unlike formatting never rounds
+    pub fn write_f64(&mut self, x: f64) {
+        self.write_u64(x.to_bits());
+    }
+
+    pub fn write_f64s(&mut self, xs: &[f64]) {
+        for &x in xs {
+            self.write_f64(x);
+        }
+    }
+
+    // Prefixed with its length, so "ab" then "c" differs from "a" then "bc"
+    pub fn write_str(&mut self, s: &str) {
+        self.write_usize(s.len());
+        self.write(s.as_bytes());
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
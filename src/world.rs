@@ -1,24 +1,213 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
 use crate::{
+    bounds::BoundingBox,
     color::Color,
-    intersection::{hit, Computations, Intersection},
-    lights::PointLight,
+    constants::MAX_REFLECTION_DEPTH,
+    grid::UniformGrid,
+    intersection::{compare_t, hit, Computations, Intersection},
+    lights::{Light, PointLight},
+    materials::LightingComponents,
     patterns::solid::Solid,
     rays::Ray,
-    shapes::{sphere::Sphere, Shape},
+    shapes::{group::Group, sphere::Sphere, Shape},
     transformation::scaling,
     tuples::Point,
+    utils::{seeded_random, StableHasher},
 };
 
+// Probabilistically stops tracing reflection rays whose accumulated
+// throughput (the fraction of their color that reaches the eye) falls below
+// threshold. Surviving rays are scaled up so the expected color is unchanged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RussianRoulette {
+    pub threshold: f64,
+    pub seed: u64,
+}
+
+// Memoizes traced colors by ray, so rays that repeat within a render (like
+// primary rays of overlapping samples or identical reflection rays) are only
+// traced once. Origins and directions are rounded to multiples of resolution
+// before being compared.
+//
+// Cached colors are only dropped by World's add_object, remove_object,
+// clear_objects and bake. Anything else that changes the scene, like editing
+// a material or a light in place, needs a World::clear_cache before the next
+// render or stale colors come back.
+#[derive(Debug)]
+pub struct RayCache {
+    resolution: f64,
+    // Split by key so threads tracing different rays rarely wait on the same
+    // lock. Each ray still takes its shard's lock twice, since the lock can't
+    // be held while tracing the ray fills in the color.
+    shards: Vec<Mutex<HashMap<RayKey, Color>>>,
+    hits: AtomicUsize,
+}
+
+const RAY_CACHE_SHARDS: usize = 16;
+
+// Quantized origin and direction, the remaining bounces, and the throughput
+// bits since Russian roulette makes the color depend on it
+type RayKey = ([i64; 6], usize, u64);
+
+impl RayCache {
+    pub fn new(resolution: f64) -> Self {
+        Self {
+            resolution,
+            shards: (0..RAY_CACHE_SHARDS)
+                .map(|_| Mutex::new(HashMap::new()))
+                .collect(),
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    // Number of lookups answered from the cache since it was last cleared
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.lock().unwrap().len()).sum()
+    }
+
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            shard.lock().unwrap().clear();
+        }
+        self.hits.store(0, Ordering::Relaxed);
+    }
+
+    fn key(&self, r: Ray, remaining: usize, throughput: f64) -> RayKey {
+        let (o, d) = (r.origin, r.direction);
+        let q = |v: f64| (v / self.resolution).round() as i64;
+
+        (
+            [q(o.0), q(o.1), q(o.2), q(d.0), q(d.1), q(d.2)],
+            remaining,
+            throughput.to_bits(),
+        )
+    }
+
+    fn shard(&self, key: &RayKey) -> &Mutex<HashMap<RayKey, Color>> {
+        let mut h = DefaultHasher::new();
+        key.hash(&mut h);
+
+        &self.shards[h.finish() as usize % RAY_CACHE_SHARDS]
+    }
+
+    fn get(&self, key: &RayKey) -> Option<Color> {
+        let color = self.shard(key).lock().unwrap().get(key).copied();
+        if color.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        }
+
+        color
+    }
+
+    fn insert(&self, key: RayKey, color: Color) {
+        self.shard(&key).lock().unwrap().insert(key, color);
+    }
+}
+
+// Which part of the shading World::color_at_channel returns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderChannel {
+    // The final color, same as color_at
+    Combined,
+    // Light from the light sources at the first hit, without anything it
+    // reflects or refracts
+    Direct,
+    // Only the light reflected at the first hit
+    Reflected,
+    // Only the light refracted through the first hit
+    Refracted,
+}
+
+// Everything that went into shading a single ray, see World::debug_ray
+#[derive(Debug)]
+pub struct DebugTrace<'a> {
+    pub intersections: Vec<Intersection<'a>>,
+    pub hit: Option<Intersection<'a>>,
+    pub computations: Option<Computations<'a>>,
+    // Whether the hit is in shadow from each light, in the order of lights
+    pub in_shadow: Vec<bool>,
+    pub lighting: Option<LightingComponents>,
+    pub reflected: Color,
+    pub refracted: Color,
+    // The final color, as returned by color_at
+    pub color: Color,
+}
+
+// Overview of what a world contains, see World::summary
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldSummary {
+    pub object_count: usize,
+    pub light_count: usize,
+    // World space box around every object
+    pub bounds: BoundingBox,
+    // Number of objects of each shape type, keyed by Shape::type_name
+    pub shape_counts: BTreeMap<&'static str, usize>,
+}
+
 pub struct World {
     pub objects: Vec<Box<dyn Shape>>,
-    pub light: PointLight,
+    pub lights: Vec<Box<dyn Light>>,
+    // Color returned for rays that escape the scene without hitting anything
+    pub background: Color,
+    // Exponential distance fog. Colors are blended towards fog_color by
+    // 1 - e^(-fog_density * t), and a density of 0 disables it.
+    pub fog_color: Color,
+    pub fog_density: f64,
+    // Width of a pixel's footprint per unit of distance along a ray, used to
+    // let patterns blur detail finer than a pixel. Camera::pixel_size is a
+    // good value, and 0 turns it off.
+    pub footprint_scale: f64,
+    // How many EPSILONs over_point and under_point sit off the surface.
+    // Raising it fixes shadow acne on large or badly conditioned scenes.
+    pub offset_scale: f64,
+    // Optional acceleration structure, see World::build_grid. It is ignored
+    // once objects has been pushed to or removed from directly, until rebuilt.
+    pub grid: Option<UniformGrid>,
+    pub roulette: Option<RussianRoulette>,
+    // Largest value any channel of the reflected and refracted light at a hit
+    // may add to its surface color, which keeps grazing mirrors and glass from
+    // producing single super bright "firefly" pixels. None leaves it uncapped.
+    pub secondary_clamp: Option<f64>,
+    // Wash colors brighter than 1 out towards white at the same luminance,
+    // see Color::desaturate_overflow, instead of leaving them to be clipped
+    // channel by channel
+    pub desaturate_overflow: bool,
+    // Optional memoization of traced rays. See RayCache for when it has to be
+    // cleared by hand.
+    pub cache: Option<RayCache>,
+    // Number of rays traced through color_at, see World::ray_count
+    pub rays_traced: AtomicUsize,
 }
 
 impl World {
     pub fn new() -> Self {
         Self {
             objects: Vec::new(),
-            light: PointLight::new(Point::origin(), Color::black()),
+            lights: Vec::new(),
+            background: Color::black(),
+            fog_color: Color::black(),
+            fog_density: 0.0,
+            footprint_scale: 0.0,
+            offset_scale: 1.0,
+            grid: None,
+            roulette: None,
+            secondary_clamp: None,
+            desaturate_overflow: false,
+            cache: None,
+            rays_traced: AtomicUsize::new(0),
         }
     }
 
@@ -35,83 +224,576 @@ impl World {
 
         Self {
             objects: vec![Box::new(s1), Box::new(s2)],
-            light,
+            lights: vec![Box::new(light)],
+            background: Color::black(),
+            fog_color: Color::black(),
+            fog_density: 0.0,
+            footprint_scale: 0.0,
+            offset_scale: 1.0,
+            grid: None,
+            roulette: None,
+            secondary_clamp: None,
+            desaturate_overflow: false,
+            cache: None,
+            rays_traced: AtomicUsize::new(0),
         }
     }
 
-    fn intersect(&self, r: Ray) -> Vec<Intersection> {
-        let mut xs = Vec::new();
-        for object in self.objects.iter() {
-            xs.append(&mut object.intersect(r));
+    // An otherwise empty world holding the given objects, meant to be
+    // followed by with_light
+    pub fn from_shapes(shapes: impl IntoIterator<Item = Box<dyn Shape>>) -> Self {
+        Self {
+            objects: shapes.into_iter().collect(),
+            ..World::new()
         }
+    }
+
+    // Adds a light to the world
+    pub fn with_light(mut self, light: Box<dyn Light>) -> Self {
+        self.lights.push(light);
+        self
+    }
+
+    // Adds an object to the world, returning its index in objects
+    pub fn add_object(&mut self, object: Box<dyn Shape>) -> usize {
+        // Any grid or cached colors were for the old object list
+        self.grid = None;
+        self.clear_cache();
+        self.objects.push(object);
+
+        self.objects.len() - 1
+    }
+
+    // Removes the object at index, shifting all objects after it down by one
+    pub fn remove_object(&mut self, index: usize) -> Box<dyn Shape> {
+        self.grid = None;
+        self.clear_cache();
+
+        self.objects.remove(index)
+    }
+
+    pub fn clear_objects(&mut self) {
+        self.grid = None;
+        self.clear_cache();
+        self.objects.clear();
+    }
+
+    // Flattens every group into the world's object list with its transforms
+    // baked into its shapes, see Group::bake. Object indices change.
+    pub fn bake(&mut self) {
+        self.grid = None;
+        self.clear_cache();
+
+        let mut baked = Vec::with_capacity(self.objects.len());
+        for mut object in std::mem::take(&mut self.objects) {
+            match object.as_any_mut().downcast_mut::<Group>() {
+                Some(group) => {
+                    group.bake();
+                    baked.append(&mut group.children);
+                }
+                None => baked.push(object),
+            }
+        }
+
+        self.objects = baked;
+    }
+
+    pub fn summary(&self) -> WorldSummary {
+        let mut bounds = BoundingBox::empty();
+        let mut shape_counts = BTreeMap::new();
+
+        for object in &self.objects {
+            bounds.add_box(&object.parent_space_bounds());
+            *shape_counts.entry(object.type_name()).or_insert(0) += 1;
+        }
+
+        WorldSummary {
+            object_count: self.objects.len(),
+            light_count: self.lights.len(),
+            bounds,
+            shape_counts,
+        }
+    }
+
+    // Hash of everything that affects how the world renders: the objects
+    // with their transforms and materials, the lights and the shading
+    // settings. Acceleration structures, caches and counters are left out.
+    pub fn scene_hash(&self) -> u64 {
+        let mut h = StableHasher::new();
+
+        h.write_usize(self.objects.len());
+        for object in &self.objects {
+            object.hash_into(&mut h);
+        }
+        h.write_usize(self.lights.len());
+        for light in &self.lights {
+            light.hash_into(&mut h);
+        }
+
+        let (b, f) = (self.background, self.fog_color);
+        h.write_f64s(&[b.0, b.1, b.2, f.0, f.1, f.2, self.fog_density]);
+        h.write_f64s(&[self.footprint_scale, self.offset_scale]);
+        h.write_bool(self.roulette.is_some());
+        if let Some(roulette) = self.roulette {
+            h.write_f64(roulette.threshold);
+            h.write_u64(roulette.seed);
+        }
+        h.write_bool(self.secondary_clamp.is_some());
+        if let Some(clamp) = self.secondary_clamp {
+            h.write_f64(clamp);
+        }
+        h.write_bool(self.desaturate_overflow);
+
+        h.finish()
+    }
+
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    // Number of rays traced since the world was created or the count was
+    // last reset, not counting shadow rays
+    pub fn ray_count(&self) -> usize {
+        self.rays_traced.load(Ordering::Relaxed)
+    }
+
+    pub fn reset_ray_count(&self) {
+        self.rays_traced.store(0, Ordering::Relaxed);
+    }
+
+    // Buckets the current objects into a uniform grid with the given number
+    // of cells per axis. The grid must be rebuilt after objects change.
+    pub fn build_grid(&mut self, resolution: usize) {
+        self.grid = Some(UniformGrid::new(&self.objects, resolution));
+    }
+
+    pub fn intersect(&self, r: Ray) -> Vec<Intersection<'_>> {
+        let mut xs = Vec::new();
+        self.intersect_into(r, &mut xs);
 
-        xs.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
         xs
     }
 
-    fn is_shadowed(&self, point: Point) -> bool {
-        let v = self.light.position - point;
+    // Replaces the contents of xs with the sorted intersections of the ray
+    // with the world, reusing the buffer's allocation
+    pub fn intersect_into<'a>(&'a self, r: Ray, xs: &mut Vec<Intersection<'a>>) {
+        xs.clear();
+        match &self.grid {
+            // A grid built for a different object list would hand back the
+            // wrong indices, so fall back to testing every object
+            Some(grid) if grid.object_count() == self.objects.len() => {
+                // Visit candidates in object order rather than traversal order so
+                // coincident intersections come out in the same order either way
+                let mut candidates = grid.candidates(r);
+                candidates.sort_unstable();
+
+                for i in candidates {
+                    self.objects[i].intersect_into(r, xs);
+                }
+            }
+            _ => {
+                for object in self.objects.iter() {
+                    object.intersect_into(r, xs);
+                }
+            }
+        }
+
+        // The sort is stable, so intersections sharing a t stay in object order
+        xs.sort_by(compare_t);
+    }
+
+    // The object the ray sees first, if any, like clicking on a pixel
+    pub fn pick(&self, r: Ray) -> Option<&dyn Shape> {
+        hit(self.intersect(r)).map(|h| h.object)
+    }
+
+    // Whether something blocks the light at light_position from the point
+    pub(crate) fn is_shadowed(&self, light_position: Point, point: Point) -> bool {
+        let v = light_position - point;
         let distance = v.magnitude();
         let direction = v.normalize();
 
         let r = Ray::new(point, direction);
-        let intersections = self.intersect(r);
-
-        let h = hit(intersections);
-
-        match h {
+        // Shadow catchers only receive shadows, they don't cast them
+        let blockers = self
+            .intersect(r)
+            .into_iter()
+            .filter(|i| !i.object.material().shadow_catcher)
+            .collect();
+
+        match hit(blockers) {
             Some(h) => h.t < distance,
             None => false,
         }
     }
 
-    fn shade_hit(&self, comps: Computations, remaining: usize) -> Color {
-        let shadowed = self.is_shadowed(comps.over_point);
+    // How much of each light reaches the hit, from 0 when all of its samples
+    // are blocked to 1 when none are
+    fn visibility(&self, light: &dyn Light, comps: &Computations) -> f64 {
+        // A light too dim to add anything can't cast a visible shadow
+        // either, so skip its shadow rays
+        if light.intensity_towards(comps.over_point).is_black() {
+            return 1.0;
+        }
 
-        let surface = comps.object.material().lighting(
-            &self.light,
-            comps.point,
-            comps.eyev,
-            comps.normalv,
-            shadowed,
-            comps.object,
-        );
+        light.intensity_at(comps.over_point, self)
+    }
 
-        let reflected = self.reflected_color(comps, remaining);
+    // Fraction of the sample points on a light that the point can see
+    pub(crate) fn visible_fraction(&self, samples: &[Point], point: Point) -> f64 {
+        let lit = samples
+            .iter()
+            .filter(|&&sample| !self.is_shadowed(sample, point))
+            .count();
 
-        surface + reflected
+        lit as f64 / samples.len() as f64
     }
 
-    pub fn color_at(&self, r: Ray, remaining: usize) -> Color {
+    // Lighting from each light at the hit, with its own shadow test, along
+    // with whether any of that light was blocked
+    fn lighting_per_light(&self, comps: &Computations) -> Vec<(LightingComponents, bool)> {
+        let material = comps.object.material();
+        let material = material.for_hit(comps);
+        let footprint = self.footprint(comps);
+
+        self.lights
+            .iter()
+            .map(|light| {
+                let visibility = self.visibility(light.as_ref(), comps);
+                let lighting =
+                    material.lighting_at_hit(light.as_ref(), comps, visibility, footprint);
+
+                (lighting, visibility < 1.0)
+            })
+            .collect()
+    }
+
+    fn prepare_computations<'a>(
+        &self,
+        hit: Intersection<'a>,
+        r: Ray,
+        xs: &[Intersection<'a>],
+    ) -> Computations<'a> {
+        hit.prepare_computations_with_offset(r, xs, self.offset_scale)
+    }
+
+    fn footprint(&self, comps: &Computations) -> f64 {
+        comps.t * self.footprint_scale
+    }
+
+    // throughput is the fraction of the returned color that will reach the
+    // eye, used to decide when reflections and refractions stop being worth
+    // tracing
+    fn shade_hit(&self, comps: Computations, remaining: usize, throughput: f64) -> Color {
+        if comps.object.material().shadow_catcher {
+            return self.surface_color(&comps);
+        }
+
+        let surface = self.surface_color(&comps);
+
+        let reflected = self.reflected_color(comps, remaining, throughput);
+        let refracted = self.refracted_color(comps, remaining, throughput);
+        let secondary = reflected + refracted;
+        let color = if secondary.is_black() {
+            surface
+        } else {
+            surface + self.clamp_secondary(secondary)
+        };
+
+        if self.desaturate_overflow {
+            color.desaturate_overflow()
+        } else {
+            color
+        }
+    }
+
+    // The color of the hit lit directly by every light
+    fn surface_color(&self, comps: &Computations) -> Color {
+        if comps.object.material().shadow_catcher {
+            return self.background * (1.0 - self.shadow_alpha(comps));
+        }
+
+        self.lighting_per_light(comps)
+            .iter()
+            .fold(Color::black(), |sum, (lighting, _)| sum + lighting.total())
+    }
+
+    fn clamp_secondary(&self, color: Color) -> Color {
+        match self.secondary_clamp {
+            Some(max) => Color(color.0.min(max), color.1.min(max), color.2.min(max)),
+            None => color,
+        }
+    }
+
+    // How much shadows darken a shadow catcher at the hit: the fraction of
+    // the surface brightness with every light unblocked that the shadows
+    // take away, or 0 where the surface is fully lit
+    fn shadow_alpha(&self, comps: &Computations) -> f64 {
+        let material = comps.object.material();
+        let material = material.for_hit(comps);
+        let footprint = self.footprint(comps);
+
+        let mut lit_luminance = 0.0;
+        let mut shadowed_luminance = 0.0;
+        for light in &self.lights {
+            let lit = material.lighting_at_hit(light.as_ref(), comps, 1.0, footprint);
+            lit_luminance += lit.total().luminance();
+
+            let visibility = self.visibility(light.as_ref(), comps);
+            shadowed_luminance += if visibility < 1.0 {
+                let shadowed =
+                    material.lighting_at_hit(light.as_ref(), comps, visibility, footprint);
+                shadowed.total().luminance()
+            } else {
+                lit.total().luminance()
+            };
+        }
+
+        if lit_luminance <= 0.0 {
+            return 0.0;
+        }
+
+        (1.0 - shadowed_luminance / lit_luminance).clamp(0.0, 1.0)
+    }
+
+    // Opacity of whatever the ray sees: 0 for the background, 1 for ordinary
+    // objects, and the strength of the shadow for shadow catchers
+    pub fn alpha_at(&self, r: Ray) -> f64 {
+        let intersections = self.intersect(r);
+
+        match hit(intersections.clone()) {
+            Some(hit) if hit.object.material().shadow_catcher => {
+                self.shadow_alpha(&self.prepare_computations(hit, r, &intersections))
+            }
+            Some(_) => 1.0,
+            None => 0.0,
+        }
+    }
+
+    // Traces the ray like color_at but records each step of the shading
+    pub fn debug_ray(&self, r: Ray) -> DebugTrace<'_> {
         let intersections = self.intersect(r);
-        let hit = hit(intersections);
+        let hit = hit(intersections.clone());
+
+        let mut trace = DebugTrace {
+            intersections,
+            hit,
+            computations: None,
+            in_shadow: Vec::new(),
+            lighting: None,
+            reflected: Color::black(),
+            refracted: Color::black(),
+            color: self.apply_fog(self.background, f64::INFINITY),
+        };
+
+        if let Some(hit) = hit {
+            let comps = self.prepare_computations(hit, r, &trace.intersections);
+            let per_light = self.lighting_per_light(&comps);
+            let lighting = per_light
+                .iter()
+                .map(|(lighting, _)| *lighting)
+                .fold(LightingComponents::none(), |sum, l| sum + l);
+            let reflected = self.reflected_color(comps, MAX_REFLECTION_DEPTH, 1.0);
+            let refracted = self.refracted_color(comps, MAX_REFLECTION_DEPTH, 1.0);
+
+            trace.computations = Some(comps);
+            trace.in_shadow = per_light.iter().map(|(_, shadowed)| *shadowed).collect();
+            trace.lighting = Some(lighting);
+            trace.reflected = self.clamp_secondary(reflected);
+            trace.refracted = self.clamp_secondary(refracted);
+            // Shade the hit exactly as color_at does, so the traced color
+            // always matches the rendered one
+            trace.color = self.apply_fog(self.shade_hit(comps, MAX_REFLECTION_DEPTH, 1.0), hit.t);
+        }
+
+        trace
+    }
+
+    pub fn color_at(&self, r: Ray, remaining: usize) -> Color {
+        self.trace(r, remaining, 1.0)
+    }
+
+    // Shades each ray on its own like color_at, for integrators that make
+    // their own rays instead of getting them from a Camera
+    pub fn trace_rays(&self, rays: &[Ray], depth: usize) -> Vec<Color> {
+        rays.iter().map(|&r| self.color_at(r, depth)).collect()
+    }
+
+    // Like trace_rays, with the rays split evenly between the given number
+    // of threads
+    pub fn trace_rays_threaded(&self, rays: &[Ray], depth: usize, threads: usize) -> Vec<Color> {
+        let chunk_size = rays.len().div_ceil(threads.max(1)).max(1);
+
+        thread::scope(|scope| {
+            let workers: Vec<_> = rays
+                .chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || self.trace_rays(chunk, depth)))
+                .collect();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        })
+    }
 
-        if hit.is_none() {
+    // One term of the shading at the first hit, for finding out which of
+    // them looks wrong. Channels other than Combined are the raw term, without
+    // fog or overflow handling, and black where the ray hits nothing.
+    pub fn color_at_channel(&self, r: Ray, remaining: usize, channel: RenderChannel) -> Color {
+        if channel == RenderChannel::Combined {
+            return self.color_at(r, remaining);
+        }
+
+        self.rays_traced.fetch_add(1, Ordering::Relaxed);
+
+        let intersections = self.intersect(r);
+        let Some(hit) = hit(intersections.clone()) else {
             return Color::black();
+        };
+        let comps = self.prepare_computations(hit, r, &intersections);
+
+        match channel {
+            RenderChannel::Direct => self.surface_color(&comps),
+            RenderChannel::Reflected => {
+                self.clamp_secondary(self.reflected_color(comps, remaining, 1.0))
+            }
+            RenderChannel::Refracted => {
+                self.clamp_secondary(self.refracted_color(comps, remaining, 1.0))
+            }
+            RenderChannel::Combined => unreachable!(),
+        }
+    }
+
+    fn trace(&self, r: Ray, remaining: usize, throughput: f64) -> Color {
+        let Some(cache) = &self.cache else {
+            return self.trace_uncached(r, remaining, throughput);
+        };
+
+        let key = cache.key(r, remaining, throughput);
+        if let Some(color) = cache.get(&key) {
+            return color;
         }
 
-        let comps = hit.unwrap().prepare_computations(r);
+        let color = self.trace_uncached(r, remaining, throughput);
+        cache.insert(key, color);
+
+        color
+    }
+
+    fn trace_uncached(&self, r: Ray, remaining: usize, throughput: f64) -> Color {
+        self.rays_traced.fetch_add(1, Ordering::Relaxed);
+
+        let intersections = self.intersect(r);
+        let hit = hit(intersections.clone());
+
+        match hit {
+            Some(hit) => {
+                let comps = self.prepare_computations(hit, r, &intersections);
+                let color = self.shade_hit(comps, remaining, throughput);
+                self.apply_fog(color, hit.t)
+            }
+            None => self.apply_fog(self.background, f64::INFINITY),
+        }
+    }
+
+    fn apply_fog(&self, color: Color, distance: f64) -> Color {
+        if self.fog_density == 0.0 {
+            return color;
+        }
 
-        self.shade_hit(comps, remaining)
+        let fog = 1.0 - (-self.fog_density * distance).exp();
+        color * (1.0 - fog) + self.fog_color * fog
     }
 
-    fn reflected_color(&self, comps: Computations, remaining: usize) -> Color {
-        if remaining <= 0 || comps.object.material().reflective == 0.0 {
+    fn reflected_color(&self, comps: Computations, remaining: usize, throughput: f64) -> Color {
+        let material = comps.object.material();
+        let reflective = material
+            .for_hit(&comps)
+            .reflective_at(comps.object, comps.point);
+
+        if remaining == 0 || reflective == 0.0 {
             return Color::black();
         }
 
         let reflect_ray = Ray::new(comps.over_point, comps.reflectv);
-        let color = self.color_at(reflect_ray, remaining - 1);
 
-        color * comps.object.material().reflective
+        self.trace_secondary(reflect_ray, remaining, throughput, reflective)
+    }
+
+    fn refracted_color(&self, comps: Computations, remaining: usize, throughput: f64) -> Color {
+        let material = comps.object.material();
+        let transparency = material.for_hit(&comps).transparency;
+
+        if remaining == 0 || transparency == 0.0 {
+            return Color::black();
+        }
+
+        // Snell's law, with sin(theta_t)^2 found from cos(theta_i) through
+        // the Pythagorean identity
+        let n_ratio = comps.n1 / comps.n2;
+        let cos_i = comps.eyev.dot(&comps.normalv);
+        let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
+
+        // Total internal reflection
+        if sin2_t > 1.0 {
+            return Color::black();
+        }
+
+        let cos_t = (1.0 - sin2_t).sqrt();
+        let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
+        let refract_ray = Ray::new(comps.under_point, direction);
+
+        self.trace_secondary(refract_ray, remaining, throughput, transparency)
+    }
+
+    // Traces a reflected or refracted ray that contributes weight times its
+    // color to the hit it leaves from, ending it early with Russian roulette
+    // if it is too faint to matter
+    fn trace_secondary(&self, r: Ray, remaining: usize, throughput: f64, weight: f64) -> Color {
+        let mut throughput = throughput * weight;
+        let mut weight = weight;
+
+        if let Some(roulette) = self.roulette {
+            if throughput < roulette.threshold {
+                let survival = throughput / roulette.threshold;
+                let o = r.origin;
+                let d = r.direction;
+                let sample = seeded_random(
+                    roulette.seed,
+                    &[o.0, o.1, o.2, d.0, d.1, d.2, remaining as f64],
+                );
+
+                if sample >= survival {
+                    return Color::black();
+                }
+
+                throughput /= survival;
+                weight /= survival;
+            }
+        }
+
+        self.trace(r, remaining - 1, throughput) * weight
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::f64::consts::PI;
+
     use crate::{
-        constants::MAX_REFLECTION_DEPTH, materials::Material, shapes::plane::Plane,
-        transformation::translation, tuples::Vector,
+        camera::Camera,
+        constants::EPSILON,
+        intersection::Part,
+        lights::{AreaLight, SpotLight},
+        materials::Material,
+        matrices::Matrix,
+        patterns::{checker::Checker, coordinate::Coordinate, solid::Solid},
+        shapes::plane::Plane,
+        transformation::{rotation_x, rotation_z, translation, view_transform, Motion},
+        tuples::Vector,
     };
 
     use super::*;
@@ -121,7 +803,205 @@ mod tests {
         let w = World::new();
 
         assert_eq!(w.objects.len(), 0);
-        assert_eq!(w.light.intensity, Color::black());
+        assert!(w.lights.is_empty());
+        assert_eq!(w.background, Color::black());
+    }
+
+    #[test]
+    fn building_world_from_shapes_and_light() {
+        let default = World::default();
+        let mut s1 = Sphere::new();
+        s1.material.pattern = Box::new(Solid::new(Color(0.8, 1.0, 0.6)));
+        s1.material.diffuse = 0.7;
+        s1.material.specular = 0.2;
+        let mut s2 = Sphere::new();
+        s2.set_transformation(scaling(0.5, 0.5, 0.5));
+        let shapes: Vec<Box<dyn Shape>> = vec![Box::new(s1), Box::new(s2)];
+
+        let w = World::from_shapes(shapes).with_light(default.lights[0].clone());
+
+        assert_eq!(w.objects.len(), 2);
+        assert_eq!(w.lights, default.lights);
+
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let ts: Vec<f64> = w.intersect(r).iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![4.0, 4.5, 5.5, 6.0]);
+        assert_eq!(
+            w.color_at(r, MAX_REFLECTION_DEPTH),
+            default.color_at(r, MAX_REFLECTION_DEPTH)
+        );
+    }
+
+    #[test]
+    fn adding_and_removing_objects() {
+        let mut w = World::new();
+
+        let mut s1 = Sphere::new();
+        s1.set_transformation(translation(0.0, 0.0, -3.0));
+        let s2 = Sphere::new();
+        let mut s3 = Sphere::new();
+        s3.set_transformation(translation(0.0, 0.0, 3.0));
+
+        assert_eq!(w.add_object(Box::new(s1)), 0);
+        assert_eq!(w.add_object(Box::new(s2.clone())), 1);
+        assert_eq!(w.add_object(Box::new(s3)), 2);
+
+        let removed = w.remove_object(1);
+        assert!(removed.equals(&s2));
+
+        let r = Ray::new(Point::new(0.0, 0.0, -10.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = w.intersect(r);
+
+        assert_eq!(w.objects.len(), 2);
+        assert_eq!(xs.len(), 4);
+        assert_eq!(xs[0].t, 6.0);
+        assert_eq!(xs[1].t, 8.0);
+        assert_eq!(xs[2].t, 12.0);
+        assert_eq!(xs[3].t, 14.0);
+    }
+
+    #[test]
+    fn summarizing_default_world() {
+        let summary = World::default().summary();
+
+        assert_eq!(summary.object_count, 2);
+        assert_eq!(summary.light_count, 1);
+        assert_eq!(
+            summary.bounds,
+            BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0))
+        );
+        assert_eq!(summary.shape_counts.len(), 1);
+        assert_eq!(summary.shape_counts["Sphere"], 2);
+    }
+
+    #[test]
+    fn baking_flattens_groups_into_objects() {
+        let mut inner = Group::new();
+        inner.set_transformation(translation(0.0, 1.0, 0.0));
+        inner.add_child(Box::new(Sphere::new()));
+        let mut outer = Group::new();
+        outer.set_transformation(translation(2.0, 0.0, 0.0));
+        outer.add_child(Box::new(inner));
+        outer.add_child(Box::new(Sphere::new()));
+
+        let mut w = World::from_shapes([Box::new(Plane::new()) as Box<dyn Shape>]);
+        w.add_object(Box::new(outer));
+        w.bake();
+
+        assert_eq!(w.objects.len(), 3);
+        assert_eq!(w.objects[1].transformation(), translation(2.0, 1.0, 0.0));
+        assert_eq!(w.objects[2].transformation(), translation(2.0, 0.0, 0.0));
+        assert_eq!(w.objects[1].parent_transformation(), Matrix::identity());
+    }
+
+    #[test]
+    fn identical_worlds_have_the_same_scene_hash() {
+        let w1 = World::default();
+        let mut w2 = World::default();
+
+        assert_eq!(w1.scene_hash(), w2.scene_hash());
+
+        // Caches and counters don't change what gets rendered
+        w2.cache = Some(RayCache::new(EPSILON));
+        w2.color_at(
+            Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            1,
+        );
+        assert_eq!(w1.scene_hash(), w2.scene_hash());
+
+        w2.objects[1].set_transformation(scaling(0.5, 0.5, 0.6));
+        assert_ne!(w1.scene_hash(), w2.scene_hash());
+    }
+
+    #[test]
+    fn scene_hash_sees_motion_between_the_ends_of_the_frame() {
+        let mut w1 = World::default();
+        let mut w2 = World::default();
+
+        // Both spheres start and end at the origin, but only one of them
+        // leaves it in between
+        let mut s1 = Sphere::new();
+        s1.motion = Some(Motion::new(|_| Matrix::identity()));
+        let mut s2 = Sphere::new();
+        s2.motion = Some(Motion::new(|t| translation(0.0, (PI * t).sin(), 0.0)));
+        w1.add_object(Box::new(s1));
+        w2.add_object(Box::new(s2));
+
+        assert_ne!(w1.scene_hash(), w2.scene_hash());
+    }
+
+    #[test]
+    fn summarizing_empty_world() {
+        let summary = World::new().summary();
+
+        assert_eq!(summary.object_count, 0);
+        assert_eq!(summary.light_count, 0);
+        assert!(summary.bounds.is_empty());
+        assert!(summary.shape_counts.is_empty());
+    }
+
+    #[test]
+    fn coincident_intersections_keep_object_order() {
+        let mut w = World::new();
+
+        // The sphere rests on the plane, so a ray straight down leaves the
+        // sphere exactly where it hits the plane
+        let mut sphere = Sphere::glass();
+        sphere.set_transformation(translation(0.0, 1.0, 0.0));
+        w.add_object(Box::new(sphere));
+
+        let mut plane = Plane::new();
+        plane.material.refractive_index = 2.0;
+        w.add_object(Box::new(plane));
+
+        let r = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let brute_force: Vec<(f64, f64)> = w
+            .intersect(r)
+            .iter()
+            .map(|i| (i.t, i.object.material().refractive_index))
+            .collect();
+
+        // The grid always yields unbounded objects like the plane first
+        w.build_grid(4);
+        let accelerated: Vec<(f64, f64)> = w
+            .intersect(r)
+            .iter()
+            .map(|i| (i.t, i.object.material().refractive_index))
+            .collect();
+
+        assert_eq!(brute_force, vec![(3.0, 1.5), (5.0, 1.5), (5.0, 2.0)]);
+        assert_eq!(accelerated, brute_force);
+    }
+
+    #[test]
+    fn stale_grid_falls_back_to_brute_force() {
+        let mut w = World::default();
+        w.build_grid(2);
+
+        // Pushed directly, so the grid never heard of it
+        let mut s = Sphere::new();
+        s.set_transformation(translation(0.0, 0.0, 3.0));
+        w.objects.push(Box::new(s));
+
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = w.intersect(r);
+
+        assert_eq!(xs.len(), 6);
+        assert_eq!(xs[4].t, 7.0);
+        assert_eq!(xs[5].t, 9.0);
+    }
+
+    #[test]
+    fn clearing_objects() {
+        let mut w = World::default();
+        w.build_grid(2);
+
+        w.clear_objects();
+
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(w.objects.is_empty());
+        assert!(w.grid.is_none());
+        assert!(w.intersect(r).is_empty());
     }
 
     #[test]
@@ -138,37 +1018,218 @@ mod tests {
         assert_eq!(xs[3].t, 6.0);
     }
 
+    #[test]
+    fn intersecting_world_into_buffer_matches_allocating_path() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let mut expected: Vec<Intersection> =
+            w.objects.iter().flat_map(|o| o.intersect(r)).collect();
+        expected.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+
+        // Leftovers from a previous ray are cleared out
+        let mut xs = w.intersect(Ray::new(
+            Point::new(0.0, 0.0, 5.0),
+            Vector::new(0.0, 0.0, -1.0),
+        ));
+        w.intersect_into(r, &mut xs);
+
+        assert_eq!(xs.len(), expected.len());
+        for (actual, expected) in xs.iter().zip(expected.iter()) {
+            assert_eq!(actual.t, expected.t);
+            assert!(actual.object.equals(expected.object));
+        }
+    }
+
+    #[test]
+    fn debugging_ray_through_default_world() {
+        let mut w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let trace = w.debug_ray(r);
+
+        assert_eq!(trace.intersections.len(), 4);
+        assert_eq!(trace.hit.unwrap().t, 4.0);
+
+        let comps = trace.computations.unwrap();
+        assert_eq!(comps.point, Point::new(0.0, 0.0, -1.0));
+        assert_eq!(comps.normalv, Vector::new(0.0, 0.0, -1.0));
+        assert_eq!(trace.in_shadow, vec![false]);
+
+        let lighting = trace.lighting.unwrap();
+        let expected = w.color_at(r, MAX_REFLECTION_DEPTH);
+        assert_eq!(
+            lighting.ambient + lighting.diffuse + lighting.specular,
+            expected
+        );
+        assert_eq!(trace.reflected, Color::black());
+        assert_eq!(trace.color, expected);
+
+        // Overflowing highlights are desaturated in the trace as well
+        w.lights = vec![Box::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color(3.0, 0.5, 0.5),
+        ))];
+        w.desaturate_overflow = true;
+        assert_eq!(w.debug_ray(r).color, w.color_at(r, MAX_REFLECTION_DEPTH));
+    }
+
+    #[test]
+    fn debugging_ray_that_misses() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+
+        let trace = w.debug_ray(r);
+
+        assert!(trace.intersections.is_empty());
+        assert!(trace.hit.is_none());
+        assert!(trace.lighting.is_none());
+        assert_eq!(trace.color, w.background);
+    }
+
+    #[test]
+    fn picking_objects_with_rays() {
+        let w = World::default();
+
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let picked = w.pick(r).unwrap();
+        assert!(picked.equals(w.objects[0].as_ref()));
+
+        // Starting inside the outer sphere, the inner one is the first hit
+        let r = Ray::new(Point::new(0.0, 0.0, -0.75), Vector::new(0.0, 0.0, 1.0));
+        let picked = w.pick(r).unwrap();
+        assert!(picked.equals(w.objects[1].as_ref()));
+
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+        assert!(w.pick(r).is_none());
+    }
+
     #[test]
     fn shading_intersection() {
         let w = World::default();
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let shape = &w.objects[0];
-        let i = Intersection {
-            t: 4.0,
-            object: shape.as_ref(),
-        };
+        let i = Intersection::new(4.0, shape.as_ref());
 
         let comps = i.prepare_computations(r);
-        let c = w.shade_hit(comps, MAX_REFLECTION_DEPTH);
+        let c = w.shade_hit(comps, MAX_REFLECTION_DEPTH, 1.0);
 
         assert_eq!(c, Color(0.38066, 0.47583, 0.2855));
     }
 
+    #[test]
+    fn debugging_ray_on_shadow_catcher_matches_color_at() {
+        let mut w = World::default();
+        w.background = Color(0.2, 0.4, 0.8);
+        let mut floor = Plane::new();
+        floor.set_transformation(translation(0.0, -1.0, 0.0));
+        floor.material.shadow_catcher = true;
+        w.add_object(Box::new(floor));
+
+        for r in [
+            Ray::new(Point::new(5.0, 1.0, -5.0), Vector::new(0.0, -1.0, 0.0)),
+            Ray::new(Point::new(1.0, 5.0, 1.0), Vector::new(0.0, -1.0, 0.0)),
+        ] {
+            assert_eq!(w.debug_ray(r).color, w.color_at(r, MAX_REFLECTION_DEPTH));
+        }
+    }
+
+    #[test]
+    fn shadow_catcher_is_transparent_except_in_shadow() {
+        let mut w = World::default();
+        let mut floor = Plane::new();
+        floor.set_transformation(translation(0.0, -1.0, 0.0));
+        floor.material.shadow_catcher = true;
+        w.add_object(Box::new(floor));
+
+        // Lit floor away from the spheres
+        let lit = Ray::new(Point::new(5.0, 1.0, -5.0), Vector::new(0.0, -1.0, 0.0));
+        assert_eq!(w.alpha_at(lit), 0.0);
+        assert_eq!(w.color_at(lit, MAX_REFLECTION_DEPTH), w.background);
+
+        // Floor point straight behind the spheres as seen from the light
+        let occluded = Ray::new(Point::new(1.0, 5.0, 1.0), Vector::new(0.0, -1.0, 0.0));
+        let alpha = w.alpha_at(occluded);
+        assert!(alpha > 0.0 && alpha < 1.0);
+
+        // Ordinary objects stay opaque and misses stay transparent
+        let sphere = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(w.alpha_at(sphere), 1.0);
+        let miss = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(w.alpha_at(miss), 0.0);
+    }
+
     #[test]
     fn shading_intersection_from_inside() {
         let mut w = World::default();
-        w.light = PointLight::new(Point::new(0.0, 0.25, 0.0), Color::white());
-        let r = Ray::new(Point::origin(), Vector::new(0.0, 0.0, 1.0));
-        let shape = &w.objects[1];
-        let i = Intersection {
-            t: 0.5,
-            object: shape.as_ref(),
-        };
+        w.lights = vec![Box::new(PointLight::new(
+            Point::new(0.0, 0.25, 0.0),
+            Color::white(),
+        ))];
+        let r = Ray::new(Point::origin(), Vector::new(0.0, 0.0, 1.0));
+        let shape = &w.objects[1];
+        let i = Intersection::new(0.5, shape.as_ref());
+
+        let comps = i.prepare_computations(r);
+        let c = w.shade_hit(comps, MAX_REFLECTION_DEPTH, 1.0);
+
+        assert_eq!(c, Color(0.90498, 0.90498, 0.90498));
+    }
+
+    #[test]
+    fn shading_cap_with_cap_material() {
+        let mut w = World::default();
+
+        let mut side = Material::new();
+        side.ambient = 1.0;
+        side.diffuse = 0.0;
+        side.specular = 0.0;
+        side.pattern = Box::new(Solid::new(Color(1.0, 0.0, 0.0)));
+
+        let mut cap = side.clone();
+        cap.pattern = Box::new(Solid::new(Color(0.0, 0.0, 1.0)));
+        side.cap_material = Some(Box::new(cap));
+
+        w.objects[0].set_material(side);
+
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let shape = w.objects[0].as_ref();
+
+        let side_hit = Intersection::with_part(4.0, shape, Part::Side);
+        let c = w.shade_hit(side_hit.prepare_computations(r), MAX_REFLECTION_DEPTH, 1.0);
+        assert_eq!(c, Color(1.0, 0.0, 0.0));
+
+        let cap_hit = Intersection::with_part(4.0, shape, Part::Cap);
+        let c = w.shade_hit(cap_hit.prepare_computations(r), MAX_REFLECTION_DEPTH, 1.0);
+        assert_eq!(c, Color(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn shading_inside_with_back_material() {
+        let mut w = World::default();
 
-        let comps = i.prepare_computations(r);
-        let c = w.shade_hit(comps, MAX_REFLECTION_DEPTH);
+        let mut front = Material::new();
+        front.ambient = 1.0;
+        front.diffuse = 0.0;
+        front.specular = 0.0;
+        front.pattern = Box::new(Solid::new(Color(1.0, 0.0, 0.0)));
 
-        assert_eq!(c, Color(0.90498, 0.90498, 0.90498));
+        let mut back = front.clone();
+        back.pattern = Box::new(Solid::new(Color(0.0, 1.0, 0.0)));
+        front.back_material = Some(Box::new(back));
+
+        w.objects[0].set_material(front);
+        let shape = w.objects[0].as_ref();
+
+        let outside = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let i = Intersection::new(4.0, shape);
+        let c = w.shade_hit(i.prepare_computations(outside), MAX_REFLECTION_DEPTH, 1.0);
+        assert_eq!(c, Color(1.0, 0.0, 0.0));
+
+        let inside = Ray::new(Point::origin(), Vector::new(0.0, 0.0, 1.0));
+        let i = Intersection::new(1.0, shape);
+        let c = w.shade_hit(i.prepare_computations(inside), MAX_REFLECTION_DEPTH, 1.0);
+        assert_eq!(c, Color(0.0, 1.0, 0.0));
     }
 
     #[test]
@@ -181,6 +1242,17 @@ mod tests {
         assert_eq!(c, Color::black());
     }
 
+    #[test]
+    fn color_when_ray_misses_is_background() {
+        let mut w = World::default();
+        w.background = Color(0.2, 0.4, 0.8);
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+
+        let c = w.color_at(r, MAX_REFLECTION_DEPTH);
+
+        assert_eq!(c, Color(0.2, 0.4, 0.8));
+    }
+
     #[test]
     fn color_when_ray_hits() {
         let w = World::default();
@@ -191,6 +1263,29 @@ mod tests {
         assert_eq!(c, Color(0.38066, 0.47583, 0.2855));
     }
 
+    #[test]
+    fn tracing_list_of_rays_matches_color_at() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::origin(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let center = c.ray_through_pixel(5, 5);
+
+        assert_eq!(
+            w.trace_rays(&[center], MAX_REFLECTION_DEPTH),
+            vec![w.color_at(center, MAX_REFLECTION_DEPTH)]
+        );
+
+        let rays: Vec<Ray> = (0..11).map(|x| c.ray_through_pixel(x, x)).collect();
+        assert_eq!(
+            w.trace_rays_threaded(&rays, MAX_REFLECTION_DEPTH, 4),
+            w.trace_rays(&rays, MAX_REFLECTION_DEPTH)
+        );
+    }
+
     #[test]
     fn color_with_intersection_behind_ray() {
         let mut w = World::default();
@@ -226,7 +1321,7 @@ mod tests {
         let i = Intersection::new(1.0, w.objects[1].as_ref());
 
         let comps = i.prepare_computations(r);
-        let color = w.reflected_color(comps, MAX_REFLECTION_DEPTH);
+        let color = w.reflected_color(comps, MAX_REFLECTION_DEPTH, 1.0);
 
         assert_eq!(color, Color::black());
     }
@@ -248,7 +1343,7 @@ mod tests {
         let i = Intersection::new(2.0_f64.sqrt(), w.objects[2].as_ref());
 
         let comps = i.prepare_computations(r);
-        let color = w.reflected_color(comps, MAX_REFLECTION_DEPTH);
+        let color = w.reflected_color(comps, MAX_REFLECTION_DEPTH, 1.0);
 
         assert_eq!(color, Color(0.19033, 0.23792, 0.14275));
     }
@@ -270,15 +1365,109 @@ mod tests {
         let i = Intersection::new(2.0_f64.sqrt(), w.objects[2].as_ref());
 
         let comps = i.prepare_computations(r);
-        let color = w.shade_hit(comps, MAX_REFLECTION_DEPTH);
+        let color = w.shade_hit(comps, MAX_REFLECTION_DEPTH, 1.0);
 
         assert_eq!(color, Color(0.87676, 0.92434, 0.82917));
     }
 
+    #[test]
+    fn render_channels_split_direct_and_reflected_light() {
+        let mut w = World::default();
+        let mut shape = Plane::new();
+        shape.material.reflective = 0.5;
+        shape.set_transformation(translation(0.0, -1.0, 0.0));
+        w.objects.push(Box::new(shape));
+
+        let mut matte = World::default();
+        let mut shape = Plane::new();
+        shape.set_transformation(translation(0.0, -1.0, 0.0));
+        matte.objects.push(Box::new(shape));
+
+        let direction = Vector::new(0.0, -(2.0_f64.sqrt() / 2.0), 2.0_f64.sqrt() / 2.0);
+        // Reflects the spheres, and reflects empty space off to the side
+        let towards_spheres = Ray::new(Point::new(0.0, 0.0, -3.0), direction);
+        let away = Ray::new(Point::new(10.0, 0.0, -3.0), direction);
+
+        for r in [towards_spheres, away] {
+            let direct = w.color_at_channel(r, MAX_REFLECTION_DEPTH, RenderChannel::Direct);
+            let reflected = w.color_at_channel(r, MAX_REFLECTION_DEPTH, RenderChannel::Reflected);
+
+            assert_eq!(direct, matte.color_at(r, MAX_REFLECTION_DEPTH));
+            assert_eq!(direct + reflected, w.color_at(r, MAX_REFLECTION_DEPTH));
+            assert_eq!(
+                w.color_at_channel(r, MAX_REFLECTION_DEPTH, RenderChannel::Refracted),
+                Color::black()
+            );
+        }
+
+        assert_eq!(
+            w.color_at_channel(away, MAX_REFLECTION_DEPTH, RenderChannel::Reflected),
+            Color::black()
+        );
+        assert_ne!(
+            w.color_at_channel(
+                towards_spheres,
+                MAX_REFLECTION_DEPTH,
+                RenderChannel::Reflected
+            ),
+            Color::black()
+        );
+    }
+
+    #[test]
+    fn clamping_secondary_color_caps_bright_reflections() {
+        let mut w = World::default();
+        w.lights = vec![Box::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color(20.0, 20.0, 20.0),
+        ))];
+
+        let mut shape = Plane::new();
+        shape.material.reflective = 0.5;
+        shape.set_transformation(translation(0.0, -1.0, 0.0));
+        w.objects.push(Box::new(shape));
+
+        let r = Ray::new(
+            Point::new(0.0, 0.0, -3.0),
+            Vector::new(0.0, -(2.0_f64.sqrt() / 2.0), 2.0_f64.sqrt() / 2.0),
+        );
+        let i = Intersection::new(2.0_f64.sqrt(), w.objects[2].as_ref());
+
+        // No reflections are traced with no bounces left
+        let surface = w.shade_hit(i.prepare_computations(r), 0, 1.0);
+        let uncapped = w.shade_hit(i.prepare_computations(r), MAX_REFLECTION_DEPTH, 1.0);
+
+        w.secondary_clamp = Some(1.0);
+        let capped = w.shade_hit(i.prepare_computations(r), MAX_REFLECTION_DEPTH, 1.0);
+
+        assert!(uncapped.1 > surface.1 + 1.0);
+        assert!(capped.1 <= surface.1 + 1.0 + EPSILON);
+        assert!(capped.1 < uncapped.1);
+    }
+
+    #[test]
+    fn desaturating_overflowing_highlights() {
+        let mut w = World::default();
+        w.lights = vec![Box::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color(3.0, 0.5, 0.5),
+        ))];
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let i = Intersection::new(4.0, w.objects[0].as_ref());
+
+        let raw = w.shade_hit(i.prepare_computations(r), MAX_REFLECTION_DEPTH, 1.0);
+        w.desaturate_overflow = true;
+        let desaturated = w.shade_hit(i.prepare_computations(r), MAX_REFLECTION_DEPTH, 1.0);
+
+        assert!(raw.0 > 1.0);
+        assert_eq!(desaturated, raw.desaturate_overflow());
+        assert!(desaturated.0 <= 1.0 + EPSILON);
+    }
+
     #[test]
     fn color_at_with_mutually_reflective_surfaces() {
         let mut w = World::new();
-        w.light = PointLight::new(Point::origin(), Color::white());
+        w.lights = vec![Box::new(PointLight::new(Point::origin(), Color::white()))];
 
         let mut lower = Plane::new();
         lower.material.reflective = 1.0;
@@ -294,6 +1483,228 @@ mod tests {
         w.color_at(r, MAX_REFLECTION_DEPTH);
     }
 
+    #[test]
+    fn zero_fog_density_leaves_colors_unchanged() {
+        let mut w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let clear = w.color_at(r, MAX_REFLECTION_DEPTH);
+
+        w.fog_color = Color(0.5, 0.5, 0.5);
+
+        assert_eq!(w.color_at(r, MAX_REFLECTION_DEPTH), clear);
+    }
+
+    #[test]
+    fn distant_hits_are_foggier() {
+        let mut w = World::default();
+        w.fog_color = Color::white();
+        w.fog_density = 0.1;
+
+        let near = Ray::new(Point::new(0.0, 0.0, -2.0), Vector::new(0.0, 0.0, 1.0));
+        let far = Ray::new(Point::new(0.0, 0.0, -20.0), Vector::new(0.0, 0.0, 1.0));
+
+        let near_color = w.color_at(near, MAX_REFLECTION_DEPTH);
+        let far_color = w.color_at(far, MAX_REFLECTION_DEPTH);
+
+        // Both rays hit the same spot on the sphere, one from further away
+        let clear = Color(0.38066, 0.47583, 0.2855);
+        let fog = 1.0 - (-0.1_f64).exp();
+        assert_eq!(near_color, clear * (1.0 - fog) + Color::white() * fog);
+        assert!(far_color.0 > near_color.0);
+        assert!(far_color.2 > near_color.2);
+    }
+
+    #[test]
+    fn distant_fading_checkers_blend_to_average() {
+        let mut checker = Checker::new(
+            Box::new(Solid::new(Color::white())),
+            Box::new(Solid::new(Color::black())),
+        );
+        checker.set_fade(true);
+
+        let mut floor = Plane::new();
+        floor.material.pattern = Box::new(checker);
+        floor.material.ambient = 1.0;
+        floor.material.diffuse = 0.0;
+        floor.material.specular = 0.0;
+
+        let mut w = World::new();
+        w.lights = vec![Box::new(PointLight::new(
+            Point::new(0.0, 100.0, 0.0),
+            Color::white(),
+        ))];
+        w.objects.push(Box::new(floor));
+        w.footprint_scale = 0.1;
+
+        // Both rays land in the middle of a white square
+        let down = Vector::new(0.0, -1.0, 0.0);
+        let near = Ray::new(Point::new(0.5, 2.0, 0.5), down);
+        let far = Ray::new(Point::new(0.5, 50.0, 0.5), down);
+
+        assert_eq!(w.color_at(near, MAX_REFLECTION_DEPTH), Color::white());
+        assert_eq!(w.color_at(far, MAX_REFLECTION_DEPTH), Color(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn reflected_color_of_background() {
+        let mut w = World::new();
+        w.lights = vec![Box::new(PointLight::new(
+            Point::new(-10.0, 10.0, -10.0),
+            Color::white(),
+        ))];
+        w.background = Color(0.2, 0.4, 0.8);
+
+        let mut mirror = Sphere::new();
+        mirror.material.reflective = 1.0;
+        w.objects.push(Box::new(mirror));
+
+        // Hitting the sphere head on reflects the ray straight back out into the open
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let i = Intersection::new(4.0, w.objects[0].as_ref());
+
+        let comps = i.prepare_computations(r);
+        let color = w.reflected_color(comps, MAX_REFLECTION_DEPTH, 1.0);
+
+        assert_eq!(color, Color(0.2, 0.4, 0.8));
+    }
+
+    #[test]
+    fn reflectivity_map_modulates_reflected_color() {
+        let mut shape = Plane::new();
+        shape.material.reflective = 1.0;
+        shape.material.reflective_map = Some(Box::new(Checker::new(
+            Box::new(Solid::new(Color::white())),
+            Box::new(Solid::new(Color::black())),
+        )));
+        shape.set_transformation(translation(0.0, -1.0, 0.0));
+
+        let mut w = World::default();
+        w.objects.push(Box::new(shape));
+
+        // Both rays look back up at the default spheres, landing on the
+        // plane on either side of a checker boundary
+        let reflected = |x: f64| {
+            let r = Ray::new(
+                Point::new(x, 0.0, -3.0),
+                Vector::new(0.0, -(2.0_f64.sqrt() / 2.0), 2.0_f64.sqrt() / 2.0),
+            );
+            let i = Intersection::new(2.0_f64.sqrt(), w.objects[2].as_ref());
+
+            w.reflected_color(i.prepare_computations(r), MAX_REFLECTION_DEPTH, 1.0)
+        };
+
+        assert_ne!(reflected(0.1), Color::black());
+        assert_eq!(reflected(-0.1), Color::black());
+    }
+
+    #[test]
+    fn russian_roulette_matches_full_recursion_on_average() {
+        // A sphere between two facing mirrors keeps bouncing rays around
+        let mut w = World::default();
+        for (y, angle) in [(-1.0, 0.0), (1.5, PI)] {
+            let mut mirror = Plane::new();
+            mirror.material.reflective = 0.6;
+            mirror.set_transformation(translation(0.0, y, 0.0) * rotation_z(angle));
+            w.objects.push(Box::new(mirror));
+        }
+
+        let r = Ray::new(
+            Point::new(0.0, 0.0, -3.0),
+            Vector::new(0.0, -0.7, 0.3).normalize(),
+        );
+
+        let expected = w.color_at(r, MAX_REFLECTION_DEPTH);
+        let full_rays = w.ray_count();
+
+        let samples = 1000;
+        let mut total = Color::black();
+        w.reset_ray_count();
+        for seed in 0..samples {
+            w.roulette = Some(RussianRoulette {
+                threshold: 0.5,
+                seed,
+            });
+            total = total + w.color_at(r, MAX_REFLECTION_DEPTH);
+        }
+        let mean = total * (1.0 / samples as f64);
+
+        assert!((mean.0 - expected.0).abs() < 0.01);
+        assert!((mean.1 - expected.1).abs() < 0.01);
+        assert!((mean.2 - expected.2).abs() < 0.01);
+        assert!(w.ray_count() < full_rays * samples as usize);
+    }
+
+    #[test]
+    fn cached_render_matches_uncached_render() {
+        let mut w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::origin(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let uncached = c.render(&w);
+
+        w.cache = Some(RayCache::new(1e-9));
+        let cached = c.render(&w);
+
+        for y in 0..11 {
+            for x in 0..11 {
+                assert_eq!(cached.pixel_at(x, y), uncached.pixel_at(x, y));
+            }
+        }
+        assert_eq!(w.cache.as_ref().unwrap().hits(), 0);
+    }
+
+    #[test]
+    fn cache_answers_repeated_rays() {
+        let mut w = World::default();
+        w.cache = Some(RayCache::new(1e-9));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        let first = w.color_at(r, MAX_REFLECTION_DEPTH);
+        let traced = w.ray_count();
+        let second = w.color_at(r, MAX_REFLECTION_DEPTH);
+
+        assert_eq!(first, second);
+        assert_eq!(w.ray_count(), traced);
+        assert_eq!(w.cache.as_ref().unwrap().hits(), 1);
+
+        w.clear_cache();
+        assert_eq!(w.cache.as_ref().unwrap().len(), 0);
+        assert_eq!(w.cache.as_ref().unwrap().hits(), 0);
+    }
+
+    #[test]
+    fn cache_keeps_stale_colors_until_cleared() {
+        let mut w = World::default();
+        w.cache = Some(RayCache::new(1e-9));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let before = w.color_at(r, MAX_REFLECTION_DEPTH);
+
+        // Edits in place don't go through World, so the cache can't see them
+        let mut m = w.objects[0].material();
+        m.pattern = Box::new(Solid::new(Color(0.0, 0.0, 1.0)));
+        w.objects[0].set_material(m);
+        assert_eq!(w.color_at(r, MAX_REFLECTION_DEPTH), before);
+
+        w.clear_cache();
+        assert_ne!(w.color_at(r, MAX_REFLECTION_DEPTH), before);
+    }
+
+    #[test]
+    fn counting_traced_rays() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        w.color_at(r, MAX_REFLECTION_DEPTH);
+        w.color_at(r, MAX_REFLECTION_DEPTH);
+        assert_eq!(w.ray_count(), 2);
+
+        w.reset_ray_count();
+        assert_eq!(w.ray_count(), 0);
+    }
+
     #[test]
     fn reflected_color_at_max_recursive_depth() {
         let mut w = World::default();
@@ -311,11 +1722,172 @@ mod tests {
         let i = Intersection::new(2.0_f64.sqrt(), w.objects[2].as_ref());
 
         let comps = i.prepare_computations(r);
-        let color = w.reflected_color(comps, 0);
+        let color = w.reflected_color(comps, 0, 1.0);
 
         assert_eq!(color, Color::black());
     }
 
+    #[test]
+    fn refracted_color_with_opaque_surface() {
+        let w = World::default();
+        let shape = w.objects[0].as_ref();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = [Intersection::new(4.0, shape), Intersection::new(6.0, shape)];
+
+        let comps = xs[0].prepare_computations_with_hits(r, &xs);
+
+        assert_eq!(
+            w.refracted_color(comps, MAX_REFLECTION_DEPTH, 1.0),
+            Color::black()
+        );
+    }
+
+    #[test]
+    fn refracted_color_at_max_recursive_depth() {
+        let mut w = World::default();
+        let mut material = w.objects[0].material();
+        material.transparency = 1.0;
+        material.refractive_index = 1.5;
+        w.objects[0].set_material(material);
+
+        let shape = w.objects[0].as_ref();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = [Intersection::new(4.0, shape), Intersection::new(6.0, shape)];
+
+        let comps = xs[0].prepare_computations_with_hits(r, &xs);
+
+        assert_eq!(w.refracted_color(comps, 0, 1.0), Color::black());
+    }
+
+    #[test]
+    fn refracted_color_under_total_internal_reflection() {
+        let mut w = World::default();
+        let mut material = w.objects[0].material();
+        material.transparency = 1.0;
+        material.refractive_index = 1.5;
+        w.objects[0].set_material(material);
+
+        let shape = w.objects[0].as_ref();
+        let half_root2 = 2.0_f64.sqrt() / 2.0;
+        let r = Ray::new(Point::new(0.0, 0.0, half_root2), Vector::new(0.0, 1.0, 0.0));
+        let xs = [
+            Intersection::new(-half_root2, shape),
+            Intersection::new(half_root2, shape),
+        ];
+
+        // Inside the sphere, so the second intersection is the one to look at
+        let comps = xs[1].prepare_computations_with_hits(r, &xs);
+
+        assert_eq!(
+            w.refracted_color(comps, MAX_REFLECTION_DEPTH, 1.0),
+            Color::black()
+        );
+    }
+
+    #[test]
+    fn refracted_color_with_refracted_ray() {
+        let mut w = World::default();
+        let mut a = w.objects[0].material();
+        a.ambient = 1.0;
+        a.pattern = Box::new(Coordinate::new());
+        w.objects[0].set_material(a);
+        let mut b = w.objects[1].material();
+        b.transparency = 1.0;
+        b.refractive_index = 1.5;
+        w.objects[1].set_material(b);
+
+        let (a, b) = (w.objects[0].as_ref(), w.objects[1].as_ref());
+        let r = Ray::new(Point::new(0.0, 0.0, 0.1), Vector::new(0.0, 1.0, 0.0));
+        let xs = [
+            Intersection::new(-0.9899, a),
+            Intersection::new(-0.4899, b),
+            Intersection::new(0.4899, b),
+            Intersection::new(0.9899, a),
+        ];
+
+        let comps = xs[2].prepare_computations_with_hits(r, &xs);
+        let color = w.refracted_color(comps, MAX_REFLECTION_DEPTH, 1.0);
+
+        // The coordinate pattern shows where on the outer sphere the
+        // refracted ray lands
+        assert!((color.0 - 0.0).abs() < 1e-4);
+        assert!((color.1 - 0.99888).abs() < 1e-4);
+        assert!((color.2 - 0.04725).abs() < 1e-4);
+    }
+
+    #[test]
+    fn shade_hit_with_transparent_material() {
+        let mut w = World::default();
+
+        let mut floor = Plane::new();
+        floor.set_transformation(translation(0.0, -1.0, 0.0));
+        floor.material.transparency = 0.5;
+        floor.material.refractive_index = 1.5;
+        w.objects.push(Box::new(floor));
+
+        let mut ball = Sphere::new();
+        ball.material.pattern = Box::new(Solid::new(Color(1.0, 0.0, 0.0)));
+        ball.material.ambient = 0.5;
+        ball.set_transformation(translation(0.0, -3.5, -0.5));
+        w.objects.push(Box::new(ball));
+
+        let r = Ray::new(
+            Point::new(0.0, 0.0, -3.0),
+            Vector::new(0.0, -(2.0_f64.sqrt() / 2.0), 2.0_f64.sqrt() / 2.0),
+        );
+        let xs = [Intersection::new(2.0_f64.sqrt(), w.objects[2].as_ref())];
+
+        let comps = xs[0].prepare_computations_with_hits(r, &xs);
+        let color = w.shade_hit(comps, MAX_REFLECTION_DEPTH, 1.0);
+
+        assert_eq!(color, Color(0.93642, 0.68642, 0.68642));
+    }
+
+    #[test]
+    fn glass_bends_the_background() {
+        let world_with_index = |refractive_index: f64| {
+            let mut w = World::new();
+            w.lights = vec![Box::new(PointLight::new(
+                Point::new(-10.0, 10.0, -10.0),
+                Color::white(),
+            ))];
+
+            // The coordinate pattern makes every point on the wall a
+            // different color
+            let mut wall = Plane::new();
+            wall.set_transformation(translation(0.0, 0.0, 5.0) * rotation_x(PI / 2.0));
+            wall.material.pattern = Box::new(Coordinate::new());
+            wall.material.ambient = 1.0;
+            wall.material.diffuse = 0.0;
+            wall.material.specular = 0.0;
+            w.objects.push(Box::new(wall));
+
+            let mut glass = Sphere::glass();
+            glass.material.refractive_index = refractive_index;
+            glass.material.pattern = Box::new(Solid::new(Color::black()));
+            glass.material.ambient = 0.0;
+            glass.material.diffuse = 0.0;
+            glass.material.specular = 0.0;
+            w.objects.push(Box::new(glass));
+
+            w
+        };
+
+        let r = Ray::new(Point::new(0.5, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let straight_through = Color(0.5, 0.0, 0.0);
+
+        // Glass with the same index as the vacuum around it lets the ray
+        // through unbent, anything denser sends it somewhere else on the wall
+        assert_eq!(
+            world_with_index(1.0).color_at(r, MAX_REFLECTION_DEPTH),
+            straight_through
+        );
+        assert_ne!(
+            world_with_index(1.5).color_at(r, MAX_REFLECTION_DEPTH),
+            straight_through
+        );
+    }
+
     mod shadow {
         use crate::transformation::translation;
 
@@ -326,7 +1898,7 @@ mod tests {
             let w = World::default();
             let p = Point::new(0.0, 10.0, 0.0);
 
-            assert_eq!(w.is_shadowed(p), false);
+            assert!(!w.is_shadowed(w.lights[0].position(), p));
         }
 
         #[test]
@@ -334,7 +1906,7 @@ mod tests {
             let w = World::default();
             let p = Point::new(10.0, -10.0, 10.0);
 
-            assert_eq!(w.is_shadowed(p), true);
+            assert!(w.is_shadowed(w.lights[0].position(), p));
         }
 
         #[test]
@@ -342,7 +1914,7 @@ mod tests {
             let w = World::default();
             let p = Point::new(-20.0, 20.0, -20.0);
 
-            assert_eq!(w.is_shadowed(p), false);
+            assert!(!w.is_shadowed(w.lights[0].position(), p));
         }
 
         #[test]
@@ -350,13 +1922,173 @@ mod tests {
             let w = World::default();
             let p = Point::new(-2.0, 2.0, -2.0);
 
-            assert_eq!(w.is_shadowed(p), false);
+            assert!(!w.is_shadowed(w.lights[0].position(), p));
+        }
+
+        #[test]
+        fn shadow_catchers_cast_no_shadows() {
+            let mut w = World::default();
+            let mut ceiling = Plane::new();
+            ceiling.set_transformation(translation(0.0, 5.0, 0.0));
+            ceiling.material.shadow_catcher = true;
+            w.add_object(Box::new(ceiling));
+            let p = Point::new(-2.0, 2.0, -2.0);
+
+            assert!(!w.is_shadowed(w.lights[0].position(), p));
+
+            // An ordinary plane in the same place does
+            let mut ceiling = Plane::new();
+            ceiling.set_transformation(translation(0.0, 5.0, 0.0));
+            w.add_object(Box::new(ceiling));
+            assert!(w.is_shadowed(w.lights[0].position(), p));
+        }
+
+        #[test]
+        fn shadow_from_one_light_but_not_another() {
+            let mut w = World::default();
+            let a = PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::white());
+            let b = PointLight::new(Point::new(10.0, 10.0, 10.0), Color::white());
+            w.lights = vec![Box::new(a), Box::new(b)];
+
+            // Behind the spheres as seen from a, and facing b
+            let p = Point::new(10.0, -10.0, 10.0);
+
+            assert!(w.is_shadowed(a.position, p));
+            assert!(!w.is_shadowed(b.position, p));
+        }
+
+        #[test]
+        fn jittered_light_softens_shadow_edge() {
+            // A sphere hovering over the floor casts a round shadow whose edge
+            // is about 1.26 away from the point right under the light
+            let mut w = World::new();
+            let mut light = PointLight::new(Point::new(0.0, 10.0, 0.0), Color::white());
+            w.lights = vec![Box::new(light)];
+            let mut floor = Plane::new();
+            floor.material.specular = 0.0;
+            let mut ball = Sphere::new();
+            ball.set_transformation(translation(0.0, 2.0, 0.0));
+            w.objects = vec![Box::new(floor), Box::new(ball)];
+
+            let edge = 10.0 / 63.0_f64.sqrt();
+            let shadowed_fraction = |w: &World| {
+                let samples = 32;
+                let shadowed = (0..samples)
+                    .filter(|&i| {
+                        // Look at spots a hair apart along the edge, like
+                        // supersamples of one pixel would. The light jitters
+                        // differently for each of them.
+                        let target = Point::new(edge, 0.0, i as f64 * 1e-4);
+                        let eye = target + Vector::new(5.0, 5.0, 0.0);
+                        let r = Ray::new(eye, (target - eye).normalize());
+
+                        w.color_at(r, MAX_REFLECTION_DEPTH) == Color(0.1, 0.1, 0.1)
+                    })
+                    .count();
+
+                shadowed as f64 / samples as f64
+            };
+
+            let hard = shadowed_fraction(&w);
+            assert!(hard == 0.0 || hard == 1.0);
+
+            light.jitter_radius = 0.5;
+            w.lights = vec![Box::new(light)];
+            let soft = shadowed_fraction(&w);
+            assert!(soft > 0.0 && soft < 1.0);
+        }
+
+        #[test]
+        fn jittered_shadows_agree_with_light_intensity_at() {
+            let mut light = PointLight::new(Point::new(0.0, 10.0, 0.0), Color::white());
+            light.jitter_radius = 0.5;
+            let mut w = World::new();
+            w.lights = vec![Box::new(light)];
+            let mut ball = Sphere::new();
+            ball.set_transformation(translation(0.0, 2.0, 0.0));
+            w.objects = vec![Box::new(Plane::new()), Box::new(ball)];
+
+            let edge = 10.0 / 63.0_f64.sqrt();
+            for i in 0..16 {
+                let target = Point::new(edge, 0.0, i as f64 * 1e-4);
+                let r = Ray::new(
+                    target + Vector::new(0.0, 1.0, 0.0),
+                    Vector::new(0.0, -1.0, 0.0),
+                );
+                let trace = w.debug_ray(r);
+                let comps = trace.computations.unwrap();
+
+                let intensity = w.lights[0].intensity_at(comps.over_point, &w);
+                assert_eq!(trace.in_shadow, vec![intensity < 1.0]);
+            }
+        }
+
+        #[test]
+        fn area_light_shades_penumbra_in_between() {
+            // Same scene as above, lit by a 2x2 light centered where the
+            // point light was
+            let mut w = World::new();
+            w.lights = vec![Box::new(AreaLight::new(
+                Point::new(-1.0, 10.0, -1.0),
+                Vector::new(2.0, 0.0, 0.0),
+                4,
+                Vector::new(0.0, 0.0, 2.0),
+                4,
+                Color::white(),
+            ))];
+            let mut floor = Plane::new();
+            floor.material.specular = 0.0;
+            let mut ball = Sphere::new();
+            ball.set_transformation(translation(0.0, 2.0, 0.0));
+            w.objects = vec![Box::new(floor), Box::new(ball)];
+
+            let brightness = |x: f64| {
+                let r = Ray::new(Point::new(x, 0.5, 0.0), Vector::new(0.0, -1.0, 0.0));
+                w.color_at(r, MAX_REFLECTION_DEPTH).luminance()
+            };
+            let edge = 10.0 / 63.0_f64.sqrt();
+
+            assert_eq!(brightness(0.0), 0.1);
+            assert!(brightness(edge) > 0.1);
+            assert!(brightness(edge) < brightness(5.0));
+            assert_eq!(
+                w.debug_ray(Ray::new(
+                    Point::new(edge, 0.5, 0.0),
+                    Vector::new(0.0, -1.0, 0.0)
+                ))
+                .in_shadow,
+                vec![true]
+            );
+        }
+
+        #[test]
+        fn spotlight_only_lights_inside_its_cone() {
+            let mut w = World::new();
+            w.lights = vec![Box::new(SpotLight::new(
+                Point::new(0.0, 10.0, 0.0),
+                Vector::new(0.0, -1.0, 0.0),
+                Color::white(),
+                PI / 8.0,
+                PI / 6.0,
+            ))];
+            w.objects = vec![Box::new(Plane::new())];
+
+            let down = Vector::new(0.0, -1.0, 0.0);
+            let under = w.color_at(Ray::new(Point::new(0.0, 1.0, 0.0), down), 0);
+            let outside = w.color_at(Ray::new(Point::new(10.0, 1.0, 0.0), down), 0);
+
+            assert_eq!(under, Color(1.9, 1.9, 1.9));
+            assert_eq!(outside, Color::black());
+            assert_eq!(w.summary().light_count, 1);
         }
 
         #[test]
         fn shade_hit_is_given_intersection_in_shadow() {
             let mut w = World::default();
-            w.light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::white());
+            w.lights = vec![Box::new(PointLight::new(
+                Point::new(0.0, 0.0, -10.0),
+                Color::white(),
+            ))];
 
             let s1 = Sphere::new();
             let mut s2 = Sphere::new();
@@ -367,9 +2099,52 @@ mod tests {
             let i = Intersection::new(4.0, &s2);
 
             let comps = i.prepare_computations(r);
-            let c = w.shade_hit(comps, MAX_REFLECTION_DEPTH);
+            let c = w.shade_hit(comps, MAX_REFLECTION_DEPTH, 1.0);
 
             assert_eq!(c, Color(0.1, 0.1, 0.1));
         }
+
+        #[test]
+        fn black_lights_skip_their_shadow_rays() {
+            let mut w = World::default();
+            w.lights = vec![
+                Box::new(PointLight::new(Point::new(0.0, 0.0, -10.0), Color::white())),
+                Box::new(PointLight::new(Point::new(0.0, 0.0, -10.0), Color::black())),
+            ];
+
+            let s1 = Sphere::new();
+            let mut s2 = Sphere::new();
+            s2.set_transformation(translation(0.0, 0.0, 10.0));
+            w.objects = vec![Box::new(s1), Box::new(s2)];
+
+            let r = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, 1.0));
+
+            assert_eq!(w.debug_ray(r).in_shadow, vec![true, false]);
+        }
+
+        #[test]
+        fn each_light_has_its_own_shadow() {
+            let mut w = World::default();
+            // s1 blocks the first light, nothing blocks the second
+            w.lights = vec![
+                Box::new(PointLight::new(Point::new(0.0, 0.0, -10.0), Color::white())),
+                Box::new(PointLight::new(Point::new(0.0, 0.0, 3.0), Color::white())),
+            ];
+
+            let s1 = Sphere::new();
+            let mut s2 = Sphere::new();
+            s2.set_transformation(translation(0.0, 0.0, 10.0));
+            w.objects = vec![Box::new(s1), Box::new(s2.clone())];
+
+            let r = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, 1.0));
+            let i = Intersection::new(4.0, &s2);
+
+            let c = w.shade_hit(i.prepare_computations(r), MAX_REFLECTION_DEPTH, 1.0);
+
+            // Ambient only from the first light, plus the full head-on
+            // ambient, diffuse and specular from the second
+            assert_eq!(c, Color(2.0, 2.0, 2.0));
+            assert_eq!(w.debug_ray(r).in_shadow, vec![true, false]);
+        }
     }
 }